@@ -0,0 +1,109 @@
+use criterion::{ criterion_group, criterion_main, BenchmarkId, Criterion };
+
+use poliosis_test::engine::draw::{ tiles, ChunkMeshes, Color, Mesh, Renderer };
+use poliosis_test::engine::grid::{ Coord, Grid, Tile };
+
+// a size x size square of air
+fn world(size: i32) -> Grid {
+    let mut grid = Grid::new::<Coord>(Vec::new());
+    for x in 0..size {
+        for y in 0..size {
+            grid.insert((x, y), Tile::Air);
+        }
+    }
+    return grid;
+}
+
+// every kind of tile, mixed
+fn mixed_world(size: i32) -> Grid {
+    let mut grid = Grid::new::<Coord>(Vec::new());
+    for x in 0..size {
+        for y in 0..size {
+            let tile = match (x + y) % 4 {
+                0 => Tile::Air,
+                1 => Tile::Ground,
+                tier => Tile::Building { health: 100., tier: tier as f32, pressure: 0. },
+            };
+            grid.insert((x, y), tile);
+        }
+    }
+    return grid;
+}
+
+fn tile_corners(coord: Coord) -> [[f32;2];2] {
+    let (x, y) = (coord.x() as f32, coord.y() as f32);
+    return [[x - 0.5, y + 0.5], [x + 0.5, y - 0.5]];
+}
+
+// a small frame, rendering waits for the frame to be read back so frames don't pile up on the gpu
+fn renderer(size: i32) -> Option<Renderer> {
+    let mut renderer = pollster::block_on(Renderer::new_headless(64, 64))?;
+    renderer.camera.set_position([size as f32 / 2., size as f32 / 2.]);
+    renderer.camera.set_view_size([size as f32, size as f32]);
+    renderer.set_world_space(true);
+    return Some(renderer);
+}
+
+fn bench_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    for size in [32, 64, 128] {
+        let grid = world(size);
+        let Some(mut renderer) = renderer(size) else {
+            eprintln!("no adapter available, skipping renderer benchmarks");
+            return;
+        };
+
+        group.bench_with_input(BenchmarkId::new("immediate", size * size), &grid, |b, grid| b.iter(|| {
+            for (coord, _) in grid.iter() {
+                renderer.draw_box(tile_corners(coord), 0.03, Color::WHITE);
+            }
+            renderer.render().unwrap();
+            renderer.read_frame()
+        }));
+
+        let mut chunks = ChunkMeshes::new();
+        group.bench_with_input(BenchmarkId::new("chunk_meshes", size * size), &grid, |b, grid| b.iter(|| {
+            chunks.draw(&mut renderer, grid, (0, 0), (size, size), |mesh: &mut Mesh, coord, _| {
+                mesh.draw_box(tile_corners(coord), 0.03, Color::WHITE);
+            });
+            renderer.render().unwrap();
+            renderer.read_frame()
+        }));
+    }
+    group.finish();
+}
+
+// tessellating every tile on the cpu each frame against drawing them from templates with the tile pipeline
+fn bench_tiles(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tiles");
+    for size in [32, 64, 128] {
+        let grid = mixed_world(size);
+        let Some(mut renderer) = renderer(size) else {
+            eprintln!("no adapter available, skipping renderer benchmarks");
+            return;
+        };
+
+        group.bench_with_input(BenchmarkId::new("tessellated", size * size), &grid, |b, grid| b.iter(|| {
+            for (coord, tile) in grid.iter() {
+                match tile {
+                    Tile::Air => renderer.draw_box(tile_corners(coord), 0.03, Color::WHITE),
+                    Tile::Ground => renderer.draw_crossed_box(tile_corners(coord), 0.03, Color::WHITE, 3),
+                    Tile::Building { tier, .. } => renderer.draw_lined_box(tile_corners(coord), 0.03, Color::WHITE, *tier as u8, false),
+                }
+            }
+            renderer.render().unwrap();
+            renderer.read_frame()
+        }));
+
+        renderer.set_tile_templates(&tiles::grid_templates(0.03, 4));
+        group.bench_with_input(BenchmarkId::new("instanced", size * size), &grid, |b, grid| b.iter(|| {
+            tiles::draw_grid(&mut renderer, grid, (0, 0), (size, size), 4, |_, _| Color::WHITE);
+            renderer.render().unwrap();
+            renderer.read_frame()
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame, bench_tiles);
+criterion_main!(benches);
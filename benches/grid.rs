@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use criterion::{ black_box, criterion_group, criterion_main, BenchmarkId, Criterion };
+
+use poliosis_test::engine::grid::{ Coord, Grid, Tile };
+
+// the grid as it was stored before chunks, kept here to compare against
+struct HashGrid {
+    grid: HashMap<i32, HashMap<i32, Tile>>,
+}
+impl HashGrid {
+    fn insert(&mut self, coord: Coord, tile: Tile) {
+        let [x, y]: [i32;2] = coord.into();
+        self.grid.entry(x).or_default().insert(y, tile);
+    }
+
+    fn get(&self, coord: Coord) -> Option<&Tile> {
+        let [x, y]: [i32;2] = coord.into();
+        return self.grid.get(&x)?.get(&y);
+    }
+
+    fn to_ref_vec(&self) -> Vec<(Coord, &Tile)> {
+        let mut ref_vec: Vec<(Coord, &Tile)> = Vec::new();
+        for (x, col) in self.grid.iter() {
+            for (y, tile) in col.iter() {
+                ref_vec.push((Coord::from((*x, *y)), tile));
+            }
+        }
+
+        ref_vec.sort_unstable_by( |a, b| a.0.cmp(&b.0) );
+        return ref_vec;
+    }
+}
+
+// a square world of ground with buildings scattered over it, roughly what a game grid looks like
+fn world(size: i32) -> Vec<(Coord, Tile)> {
+    let mut tiles = Vec::new();
+    for x in -size / 2..size / 2 {
+        for y in -size / 2..size / 2 {
+            let tile = match (x * 7 + y * 13) % 5 {
+                0 => Tile::Building { health: 100., tier: 1., pressure: 100. },
+                _ => Tile::Ground,
+            };
+            tiles.push((Coord::from((x, y)), tile));
+        }
+    }
+    return tiles;
+}
+
+fn hash_grid(tiles: &[(Coord, Tile)]) -> HashGrid {
+    let mut grid = HashGrid { grid: HashMap::new() };
+    for &(coord, tile) in tiles {
+        grid.insert(coord, tile);
+    }
+    return grid;
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for size in [64, 256] {
+        let tiles = world(size);
+        group.bench_with_input(BenchmarkId::new("hashmap", size), &tiles, |b, tiles| b.iter(|| hash_grid(tiles)));
+        group.bench_with_input(BenchmarkId::new("chunked", size), &tiles, |b, tiles| b.iter(|| Grid::new(tiles.clone())));
+    }
+    group.finish();
+}
+
+fn bench_get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    for size in [64, 256] {
+        let tiles = world(size);
+        let coords: Vec<Coord> = tiles.iter().map(|(coord, _)| *coord).collect();
+        let old = hash_grid(&tiles);
+        let new = Grid::new(tiles);
+
+        group.bench_with_input(BenchmarkId::new("hashmap", size), &coords, |b, coords| b.iter(|| {
+            coords.iter().filter(|coord| old.get(black_box(**coord)).is_some()).count()
+        }));
+        group.bench_with_input(BenchmarkId::new("chunked", size), &coords, |b, coords| b.iter(|| {
+            coords.iter().filter(|coord| new.get(black_box(**coord)).is_some()).count()
+        }));
+    }
+    group.finish();
+}
+
+fn bench_iter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+    for size in [64, 256] {
+        let tiles = world(size);
+        let old = hash_grid(&tiles);
+        let new = Grid::new(tiles);
+
+        group.bench_function(BenchmarkId::new("hashmap", size), |b| b.iter(|| {
+            old.to_ref_vec().into_iter().filter(|(_, tile)| **tile == Tile::Ground).count()
+        }));
+        group.bench_function(BenchmarkId::new("chunked", size), |b| b.iter(|| {
+            new.iter().filter(|(_, tile)| **tile == Tile::Ground).count()
+        }));
+        group.bench_function(BenchmarkId::new("chunked_sorted", size), |b| b.iter(|| {
+            new.to_ref_vec().into_iter().filter(|(_, tile)| **tile == Tile::Ground).count()
+        }));
+    }
+    group.finish();
+}
+
+fn bench_iter_region(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter_region");
+    let tiles = world(256);
+    let old = hash_grid(&tiles);
+    let new = Grid::new(tiles);
+
+    // about what fits on screen
+    let (min, max) = ([-20, -12], [20, 12]);
+    group.bench_function("hashmap", |b| b.iter(|| {
+        let mut count = 0;
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                count += old.get(Coord::from((x, y))).is_some() as usize;
+            }
+        }
+        count
+    }));
+    group.bench_function("chunked", |b| b.iter(|| new.iter_region(black_box(Coord::from(min)), black_box(Coord::from(max))).count()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert, bench_get, bench_iter, bench_iter_region);
+criterion_main!(benches);
@@ -4,4 +4,7 @@ pub mod save;
 pub mod settings;
 pub mod ui;
 pub mod window;
-pub mod sound;
\ No newline at end of file
+pub mod sound;
+pub mod capture;
+#[cfg(test)]
+pub(crate) mod testing;
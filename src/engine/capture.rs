@@ -0,0 +1,229 @@
+use std::io;
+use std::path::{ Path, PathBuf };
+
+use chrono::prelude::Local;
+use winit::event::VirtualKeyCode;
+
+use crate::engine::draw::Frame;
+
+pub const SCREENSHOT_DIR: &str = "./screenshots";
+/// Every recording gets its own directory in here, named after when it was started.
+pub const RECORDING_DIR: &str = "./recordings";
+/// How many rendered frames a recording skips between the ones it keeps, by default.
+pub const DEFAULT_RECORD_EVERY: u32 = 10;
+
+// sortable and without characters some file systems don't allow
+fn timestamp() -> String {
+    return Local::now().format("%Y-%m-%d_%H-%M-%S%.3f").to_string();
+}
+
+/// Writes `frame` to `dir` as a PNG named after the current time and returns its path.
+pub fn write_screenshot<P: AsRef<Path>>(dir: P, frame: &Frame) -> io::Result<PathBuf> {
+    let path = dir.as_ref().join(format!("screenshot_{}.png", timestamp()));
+    frame.write_png(&path)?;
+    return Ok(path);
+}
+
+/// Keeps every `every`th rendered frame as a numbered PNG sequence, `frame_000000.png`, `frame_000001.png`
+/// and so on, which tools like ffmpeg turn into a timelapse.
+///
+/// The render loop calls `tick` before every frame and captures it when it returns true, then hands it to `write`.
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    every: u32,
+    frames: u64,
+    written: u32,
+}
+impl Recorder {
+    pub fn new<P: AsRef<Path>>(dir: P, every: u32) -> Self {
+        Recorder {
+            dir: dir.as_ref().to_path_buf(),
+            every: every.max(1),
+            frames: 0,
+            written: 0,
+        }
+    }
+
+    /// A recording into a new directory in `RECORDING_DIR`.
+    pub fn start(every: u32) -> Self {
+        Recorder::new(Path::new(RECORDING_DIR).join(timestamp()), every)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// How many frames were written so far.
+    pub fn written(&self) -> u32 {
+        self.written
+    }
+
+    /// Counts a frame about to be rendered and returns whether it's one to keep, the first one always is.
+    pub fn tick(&mut self) -> bool {
+        let keep = self.frames.is_multiple_of(u64::from(self.every));
+        self.frames += 1;
+        return keep;
+    }
+
+    /// Writes `frame` as the next one of the sequence and returns its path.
+    pub fn write(&mut self, frame: &Frame) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!("frame_{:06}.png", self.written));
+        frame.write_png(&path)?;
+        self.written += 1;
+        return Ok(path);
+    }
+}
+
+/// Screenshots and recordings taken with keys, so every render loop handles them the same way.
+///
+/// The loop passes pressed keys to `key_pressed`, asks `before_frame` whether to capture the frame it's about
+/// to render and hands what was captured to `after_frame`.
+#[derive(Debug, Default)]
+pub struct CaptureKeys {
+    screenshot_key: Option<VirtualKeyCode>,
+    recording_key: Option<(VirtualKeyCode, u32)>,
+    screenshot_requested: bool,
+    recorder: Option<Recorder>,
+    // whether the frame being rendered is one the recording keeps
+    record: bool,
+}
+impl CaptureKeys {
+    /// Pressing `key` saves the next frame to `SCREENSHOT_DIR`.
+    pub fn bind_screenshot(&mut self, key: VirtualKeyCode) {
+        self.screenshot_key = Some(key);
+    }
+
+    /// Pressing `key` starts recording every `every`th frame to `RECORDING_DIR`, pressing it again stops.
+    pub fn bind_recording(&mut self, key: VirtualKeyCode, every: u32) {
+        self.recording_key = Some((key, every));
+    }
+
+    pub fn is_bound(&self, key: VirtualKeyCode) -> bool {
+        self.screenshot_key == Some(key) || self.recording_key.is_some_and(|(recording_key, _)| recording_key == key)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn key_pressed(&mut self, key: VirtualKeyCode) {
+        if self.screenshot_key == Some(key) {
+            self.screenshot_requested = true;
+        }
+        if let Some((_, every)) = self.recording_key.filter(|(recording_key, _)| *recording_key == key) {
+            self.recorder = match self.recorder.take() {
+                Some(recorder) => {
+                    println!("recorded {} frames to {}", recorder.written(), recorder.dir().display());
+                    None
+                },
+                None => Some(Recorder::start(every)),
+            };
+        }
+    }
+
+    /// Whether the frame about to be rendered should be captured.
+    pub fn before_frame(&mut self) -> bool {
+        self.record = self.recorder.as_mut().is_some_and(|recorder| recorder.tick());
+        return self.screenshot_requested || self.record;
+    }
+
+    /// Writes the frame captured after `before_frame` where it was asked for, errors are printed.
+    pub fn after_frame(&mut self, frame: Option<Frame>) {
+        if let Some(frame) = frame {
+            if self.screenshot_requested {
+                match write_screenshot(SCREENSHOT_DIR, &frame) {
+                    Ok(path) => println!("saved screenshot to {}", path.display()),
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            if let Some(recorder) = self.recorder.as_mut().filter(|_| self.record) {
+                if let Err(err) = recorder.write(&frame) {
+                    eprintln!("{}", err);
+                }
+            }
+        }
+        self.screenshot_requested = false;
+        self.record = false;
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::test_dir;
+    use std::fs;
+
+    // ----- HELPER FUNCTIONS -----
+    fn frame() -> Frame {
+        return Frame { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 255] };
+    }
+
+    // ----- SCREENSHOT TESTS -----
+    #[test]
+    fn test_write_screenshot() {
+        let dir = test_dir("capture", "screenshot");
+        let path = write_screenshot(&dir, &frame()).unwrap();
+
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        assert!(path.file_name().unwrap().to_str().unwrap().starts_with("screenshot_"));
+        assert_eq!(&fs::read(&path).unwrap()[..8], b"\x89PNG\r\n\x1a\n", "ERROR: screenshot wasn't written as a PNG");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // ----- RECORDER TESTS -----
+    #[test]
+    fn test_recorder_tick() {
+        let mut recorder = Recorder::new(test_dir("capture", "tick"), 3);
+        let kept: Vec<bool> = (0..7).map(|_| recorder.tick()).collect();
+        assert_eq!(kept, vec![true, false, false, true, false, false, true]);
+
+        // recording every 0th frame keeps all of them
+        let mut recorder = Recorder::new(test_dir("capture", "tick"), 0);
+        assert!((0..3).all(|_| recorder.tick()));
+    }
+
+    #[test]
+    fn test_recorder_write() {
+        let dir = test_dir("capture", "write");
+        let mut recorder = Recorder::new(&dir, 1);
+
+        assert_eq!(recorder.write(&frame()).unwrap(), dir.join("frame_000000.png"));
+        assert_eq!(recorder.write(&frame()).unwrap(), dir.join("frame_000001.png"));
+        assert_eq!(recorder.written(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // ----- CAPTURE KEYS TESTS -----
+    #[test]
+    fn test_capture_keys_screenshot() {
+        let mut keys = CaptureKeys::default();
+        keys.bind_screenshot(VirtualKeyCode::F12);
+        assert!(keys.is_bound(VirtualKeyCode::F12));
+        assert!(!keys.is_bound(VirtualKeyCode::F11));
+
+        assert!(!keys.before_frame());
+        keys.key_pressed(VirtualKeyCode::F11);
+        assert!(!keys.before_frame(), "ERROR: unbound keys shouldn't capture frames");
+        keys.key_pressed(VirtualKeyCode::F12);
+        assert!(keys.before_frame());
+        // nothing captured, so nothing is written, but the request is done with
+        keys.after_frame(None);
+        assert!(!keys.before_frame(), "ERROR: a screenshot should only be taken once");
+    }
+
+    #[test]
+    fn test_capture_keys_recording() {
+        let mut keys = CaptureKeys::default();
+        keys.bind_recording(VirtualKeyCode::F11, 2);
+        keys.key_pressed(VirtualKeyCode::F11);
+        assert!(keys.is_recording());
+        let captured: Vec<bool> = (0..4).map(|_| { let capture = keys.before_frame(); keys.after_frame(None); capture }).collect();
+        assert_eq!(captured, vec![true, false, true, false]);
+
+        keys.key_pressed(VirtualKeyCode::F11);
+        assert!(!keys.is_recording());
+        assert!(!keys.before_frame());
+    }
+}
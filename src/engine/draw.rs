@@ -1,12 +1,36 @@
-use std::f32::consts::SQRT_2;
+use std::fs::{ self, File };
+use std::io::{ self, BufWriter };
+use std::ops::Range;
 
-use winit::{
-    event::*,
-    event_loop::{ControlFlow, EventLoop, EventLoopBuilder},
-    window::{WindowBuilder, Window},
-};
+use winit::window::Window;
 use wgpu::{util::DeviceExt, DepthStencilState};
-use wgpu_glyph::{ab_glyph, GlyphBrushBuilder, Section, Text};
+use wgpu_glyph::{ab_glyph, GlyphBrushBuilder, GlyphCruncher, OwnedSection};
+
+pub mod atlas;
+pub mod camera;
+pub mod chunks;
+pub mod fill;
+pub mod layer;
+pub mod mesh;
+pub mod path;
+#[cfg(test)]
+pub(crate) mod snapshot;
+pub mod stroke;
+pub mod text;
+pub mod tiles;
+
+pub use atlas::{ ImageError, ImageId };
+pub use camera::Camera;
+pub use chunks::{ ChunkMeshes, MeshStore };
+pub use fill::Fill;
+pub use layer::Layer;
+pub use mesh::Mesh;
+pub use path::{ FillRule, Path };
+pub use stroke::{ LineCap, LineJoin, Stroke };
+pub use text::{ FontId, HAlign, RichText, Span, VAlign };
+pub use wgpu_glyph::ab_glyph::InvalidFont;
+
+use atlas::Atlas;
 
 #[derive( Debug, PartialEq, Clone, Copy )]
 pub struct Color {
@@ -310,295 +334,671 @@ impl Point {
 }
 
 
+// headless renderers draw in RGBA so frames can be read back as is, and sRGB like window surfaces usually are
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+// shapes are multisampled to smooth their edges, on adapters that can
+const MSAA_SAMPLES: u32 = 4;
+
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     position: [f32; 3],
     color: [f32; 4],
+    // how the shape is filled past its color, see Fill::apply
+    fill_color: [f32; 4],
+    fill: [f32; 4],
+    fill_kind: u32,
 }
 impl Vertex {
     fn new(position: [f32;3], color: [f32;4]) -> Vertex {
         Vertex { 
             position,
             color,
+            fill_color: color,
+            fill: [0.; 4],
+            fill_kind: 0,
+        }
+    }
+}
+
+// one tile drawn by the tile pipeline, where its template is moved to and what it's tinted with
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileInstance {
+    offset: [f32; 3],
+    color: [f32; 4],
+}
+
+// a corner of a sprite, where it is in the atlas and what it's tinted with
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteVertex {
+    position: [f32; 3],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+// where and how what is drawn goes, the clip being the top of the clip stack, None if it's empty
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DrawState {
+    layer: Layer,
+    world_space: bool,
+    clip: Option<[u32;4]>,
+}
+
+// a stretch of the shapes and sprites drawn directly in the same state, as ranges of their indices
+#[derive(Debug, Clone, PartialEq)]
+struct Run {
+    state: DrawState,
+    shapes: Range<u32>,
+    sprites: Range<u32>,
+}
+
+/// A mesh kept on the gpu by a Renderer, see `Renderer::create_mesh`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MeshId(usize);
+
+// a gpu buffer that is written again instead of being created anew every frame, it only grows when what's written doesn't fit
+struct DynamicBuffer {
+    buffer: wgpu::Buffer,
+    usage: wgpu::BufferUsages,
+}
+impl DynamicBuffer {
+    fn new(device: &wgpu::Device, usage: wgpu::BufferUsages) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        DynamicBuffer {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor { label: None, size: 1024, usage, mapped_at_creation: false }),
+            usage,
+        }
+    }
+
+    // vertices, sprite corners, tile instances and u32 indices are always a multiple of COPY_BUFFER_ALIGNMENT long, like writes have to be
+    fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) {
+        let size = data.len() as u64;
+        if size > self.buffer.size() {
+            self.buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: size.max(self.buffer.size() * 2),
+                usage: self.usage,
+                mapped_at_creation: false,
+            });
+        }
+
+        queue.write_buffer(&self.buffer, 0, data);
+    }
+}
+
+// a mesh's vertices and indices on the gpu
+struct GpuMesh {
+    vertex_buffer: DynamicBuffer,
+    index_buffer: DynamicBuffer,
+    index_count: u32,
+}
+impl GpuMesh {
+    fn new(device: &wgpu::Device) -> Self {
+        GpuMesh {
+            vertex_buffer: DynamicBuffer::new(device, wgpu::BufferUsages::VERTEX),
+            index_buffer: DynamicBuffer::new(device, wgpu::BufferUsages::INDEX),
+            index_count: 0,
+        }
+    }
+
+    // panics if the mesh has more vertices than the gpu can index, instead of drawing garbage
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, mesh: &Mesh, max_index: u32) {
+        assert!(
+            mesh.vertices.len() <= max_index as usize + 1,
+            "ERROR: mesh has {} vertices but the gpu can only index {}", mesh.vertices.len(), max_index as usize + 1
+        );
+        self.vertex_buffer.write(device, queue, bytemuck::cast_slice(&mesh.vertices));
+        self.index_buffer.write(device, queue, bytemuck::cast_slice(&mesh.indices));
+        self.index_count = mesh.indices.len() as u32;
+    }
+}
+
+// what a Renderer draws into, a window's surface or a texture that is read back instead of being shown
+enum RenderTarget {
+    Window {
+        surface: wgpu::Surface,
+        config: wgpu::SurfaceConfiguration,
+    },
+    Offscreen {
+        texture: wgpu::Texture,
+    },
+}
+
+/// A rendered frame read back from the gpu, as rows of RGBA pixels from the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+impl Frame {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8;4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        return [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]];
+    }
+
+    /// Encodes the frame as an 8 bit RGBA PNG, creating the directories leading to `path`.
+    pub fn write_png<P: AsRef<std::path::Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.pixels).map_err(io::Error::other)?;
+        return Ok(());
+    }
+}
+
+/// A texture frames can be drawn into and copied back from with `read_texture`.
+pub fn create_capture_texture(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>, format: wgpu::TextureFormat) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Capture target"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        view_formats: &[],
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    })
+}
+
+#[derive(Debug)]
+pub enum ReadbackError {
+    /// Only 8 bit RGBA and BGRA textures are read back.
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// The buffer the texture was copied into couldn't be mapped to be read.
+    Map(wgpu::BufferAsyncError),
+}
+impl std::fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "An error occurred while reading a frame back: ")?;
+        match self {
+            ReadbackError::UnsupportedFormat(format) => write!(f, "can't read back {:?} textures", format),
+            ReadbackError::Map(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl std::error::Error for ReadbackError {}
+
+/// Copies a texture back from the gpu, it has to be 8 bit RGBA or BGRA and created with
+/// `TextureUsages::COPY_SRC`, like the ones from `create_capture_texture`.
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Frame, ReadbackError> {
+    let bgra = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(ReadbackError::UnsupportedFormat(format)),
+    };
+    let width = texture.width();
+    let height = texture.height();
+
+    // rows of a texture copy have to be padded to a multiple of COPY_BYTES_PER_ROW_ALIGNMENT
+    let row_bytes = width * 4;
+    let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Frame readback"),
+        size: (padded_row_bytes * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None, });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    // the callback has run once the device is done waiting, a dropped one never mapped the buffer
+    receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError)).map_err(ReadbackError::Map)?;
+
+    let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+    for row in slice.get_mapped_range().chunks(padded_row_bytes as usize) {
+        pixels.extend_from_slice(&row[..row_bytes as usize]);
+    }
+    buffer.unmap();
+
+    if bgra {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
         }
     }
+
+    return Ok(Frame { width, height, pixels });
 }
 
 pub struct Renderer {
     pub size: winit::dpi::PhysicalSize<u32>,
-    surface: wgpu::Surface,
+    // physical pixels per logical pixel of the window
+    scale_factor: f64,
+    target: RenderTarget,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
 
     render_pipeline: wgpu::RenderPipeline,
+    tile_pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    // shapes are drawn into these and resolved into the target, None without multisampling
+    msaa_view: Option<wgpu::TextureView>,
+    // layers drawn after text are resolved into this instead of the target when multisampling,
+    // and laid over it with the composite pipeline so they don't cover the text up
+    layer_target: Option<(wgpu::TextureView, wgpu::BindGroup)>,
+    composite_pipeline: wgpu::RenderPipeline,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
     depth_view: wgpu::TextureView,
-
-    pub vertices: Vec<Vertex>,
-    pub indices: Vec<u16>,
+    // the glyph brush only draws single sampled
+    text_depth_view: wgpu::TextureView,
+
+    // the shapes drawn this frame, uploaded and cleared by every render
+    immediate: Mesh,
+    immediate_buffers: GpuMesh,
+    // the largest vertex index the gpu draws, see Renderer::max_index
+    max_index: u32,
+    // indexed by MeshId, None for removed meshes whose ids are free to be reused
+    meshes: Vec<Option<GpuMesh>>,
+    // the retained meshes drawn this frame, with the state they are drawn in
+    queued_meshes: Vec<(MeshId, DrawState)>,
+
+    // every tile template appended into one mesh, and the index range of each, by variant
+    tile_templates: GpuMesh,
+    tile_template_ranges: Vec<Range<u32>>,
+    // the tiles drawn this frame by variant, uploaded one variant after the other into tile_buffer
+    tiles: Vec<Vec<TileInstance>>,
+    tile_upload: Vec<TileInstance>,
+    tile_buffer: DynamicBuffer,
+
+    sprite_pipeline: wgpu::RenderPipeline,
+    // every image loaded is packed into the atlas texture, so all sprites are drawn in one go
+    atlas: Atlas,
+    atlas_texture: wgpu::Texture,
+    atlas_bind_group: wgpu::BindGroup,
+    // the sprites drawn this frame
+    sprites: Vec<SpriteVertex>,
+    sprite_indices: Vec<u32>,
+    sprite_vertex_buffer: DynamicBuffer,
+    sprite_index_buffer: DynamicBuffer,
 
     staging_belt: wgpu::util::StagingBelt,
     glyph_brush: wgpu_glyph::GlyphBrush<DepthStencilState>,
+    // text is queued for every pass drawing the frame, the glyph brush forgets it after one
+    sections: Vec<(DrawState, OwnedSection)>,
+
+    capture_requested: bool,
+    captured: Option<Frame>,
+
+    pub camera: Camera,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    // an identity matrix, for shapes drawn in clip space
+    clip_bind_group: wgpu::BindGroup,
+
+    // where and in which space what is drawn from now on goes
+    layer: Layer,
+    world_space: bool,
+    // the parts of the target what is drawn from now on is clipped to, in pixels, each within the ones below it
+    clip_stack: Vec<[u32;4]>,
+    // the runs of this frame before the current one, which starts at these shape and sprite indices
+    runs: Vec<Run>,
+    run_start: [u32;2],
+    // the part of each layer that is drawn, by Layer::index, all of it for None
+    clips: [Option<[Point;2]>;4],
 }
 impl Renderer {
-    pub fn draw_triangle<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;3], color: C) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let points: [Point;3] = points.map(|p| p.into());
-        let offset = self.vertices.len();
+    /// Whether the shapes drawn after this are in world space, where the camera moves them, or in clip space,
+    /// which is the default and where UI is drawn. Sprites are drawn in either too, text is always drawn in clip space.
+    pub fn set_world_space(&mut self, world_space: bool) {
+        if world_space != self.world_space {
+            self.close_run();
+            self.world_space = world_space;
+        }
+    }
 
-        self.vertices.push(Vertex::new(points[0].into(), color));
-        self.vertices.push(Vertex::new(points[1].into(), color));
-        self.vertices.push(Vertex::new(points[2].into(), color));
+    pub fn is_world_space(&self) -> bool {
+        self.world_space
+    }
 
-        self.indices.push((offset + 0) as u16);
-        self.indices.push((offset + 1) as u16);
-        self.indices.push((offset + 2) as u16);
+    /// The layer shapes, meshes, sprites and text drawn after this go in, `Layer::World` by default.
+    /// Everything in a layer is drawn over the layers before it, whatever its depth.
+    pub fn set_layer(&mut self, layer: Layer) {
+        if layer != self.layer {
+            self.close_run();
+            self.layer = layer;
+        }
     }
 
-    pub fn draw_rect<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;2], color: C) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let points: [Point;2] = points.map(|p| p.into());
-        let offset = self.vertices.len();
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
 
-        self.vertices.push(Vertex::new(points[0].into(), color));
-        self.vertices.push(Vertex::new([points[0].x, points[1].y, 0.], color));
-        self.vertices.push(Vertex::new([points[1].x, points[0].y, 0.], color));
-        self.vertices.push(Vertex::new(points[1].into(), color));
+    /// Only draws the part of a layer between two corners in clip space, in every frame until `clear_clip`.
+    pub fn set_clip<P: Into<Point>>(&mut self, layer: Layer, corners: [P;2]) {
+        self.clips[layer.index()] = Some(corners.map(|p| p.into()));
+    }
 
-        self.indices.push((offset + 0) as u16);
-        self.indices.push((offset + 1) as u16);
-        self.indices.push((offset + 2) as u16);
+    /// Draws all of a layer again.
+    pub fn clear_clip(&mut self, layer: Layer) {
+        self.clips[layer.index()] = None;
+    }
 
-        self.indices.push((offset + 2) as u16);
-        self.indices.push((offset + 1) as u16);
-        self.indices.push((offset + 3) as u16);
+    /// Only draws what is drawn from now on within two corners in clip space, and within the clips pushed before,
+    /// until `pop_clip`. Meant for scrolling panels and other UI that mustn't spill out of its frame,
+    /// clips apply to shapes, meshes, sprites and text alike but not to tiles.
+    pub fn push_clip<P: Into<Point>>(&mut self, corners: [P;2]) {
+        let scissor = layer::scissor(corners.map(|p| p.into()), self.size);
+        self.push_scissor(scissor);
     }
 
-    pub fn draw_poly<C: Into<Color>, P: Into<Point>>(&mut self, points: Vec<P>, color: C) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let points: Vec<Point> = points.into_iter().map(|p| p.into()).collect();
-        let offset = self.vertices.len() as u16;
-
-        // add indices first because it is non-consuming
-        for i in 0..(points.len() - 2) as u16 {
-            self.indices.push(offset + 0);
-            self.indices.push(offset + i + 1);
-            self.indices.push(offset + i + 2);
+    /// Like `push_clip`, with the clip in pixels from the top left of the target.
+    pub fn push_pixel_clip(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.push_scissor([x, y, width, height]);
+    }
+
+    fn push_scissor(&mut self, scissor: [u32;4]) {
+        self.close_run();
+        let scissor = match self.clip_stack.last() {
+            Some(&top) => layer::intersect(top, scissor),
+            None => scissor,
+        };
+        self.clip_stack.push(scissor);
+    }
+
+    /// Stops clipping to the last clip pushed, clipping to the one before it again if there is one.
+    pub fn pop_clip(&mut self) {
+        self.close_run();
+        self.clip_stack.pop();
+    }
+
+    /// How many clips are pushed.
+    pub fn clip_depth(&self) -> usize {
+        self.clip_stack.len()
+    }
+
+    fn state(&self) -> DrawState {
+        DrawState {
+            layer: self.layer,
+            world_space: self.world_space,
+            clip: self.clip_stack.last().copied(),
         }
+    }
 
-        for point in points {
-            self.vertices.push(Vertex::new(point.into(), color));
+    // ends the run of what was drawn so far in the current state, empty runs aren't kept
+    fn close_run(&mut self) {
+        let end = [self.immediate.indices.len() as u32, self.sprite_indices.len() as u32];
+        if end != self.run_start {
+            self.runs.push(Run {
+                state: self.state(),
+                shapes: self.run_start[0]..end[0],
+                sprites: self.run_start[1]..end[1],
+            });
         }
+        self.run_start = end;
+    }
+
+
+    /// Fills the shapes `draw` draws with `fill` instead of their own colors, see `Mesh::with_fill`.
+    pub fn with_fill<F: FnOnce(&mut Renderer)>(&mut self, fill: Fill, draw: F) {
+        let start = self.immediate.vertices.len();
+        draw(self);
+        self.immediate.fill_from(start, fill);
+    }
+
+    /// Multiplies the alpha of the shapes `draw` draws by `opacity`.
+    pub fn with_opacity<F: FnOnce(&mut Renderer)>(&mut self, opacity: f32, draw: F) {
+        let start = self.immediate.vertices.len();
+        draw(self);
+        self.immediate.fade_from(start, opacity);
+    }
+
+    pub fn draw_triangle<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;3], color: C) {
+        self.immediate.draw_triangle(points, color);
+    }
+
+    pub fn draw_rect<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;2], color: C) {
+        self.immediate.draw_rect(points, color);
+    }
+
+    pub fn draw_poly<C: Into<Color>, P: Into<Point>>(&mut self, points: Vec<P>, color: C) {
+        self.immediate.draw_poly(points, color);
     }
 
     pub fn draw_line<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;2], thickness: f32, color: C) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let points: [Point;2] = points.map(|p| p.into());
+        self.immediate.draw_line(points, thickness, color);
+    }
+
+    pub fn draw_polygon<C: Into<Color>, P: Into<Point>>(&mut self, points: Vec<P>, rule: FillRule, color: C) {
+        self.immediate.draw_polygon(points, rule, color);
+    }
 
-        let delta_x = points[0].x - points[1].x;
-        let delta_y = points[0].y - points[1].y;
+    pub fn draw_circle<C: Into<Color>, P: Into<Point>>(&mut self, center: P, radius: f32, color: C) {
+        self.immediate.draw_circle(center, radius, color);
+    }
 
-        // length of the hypotenuse
-        let l = ((delta_x).powi(2) + (delta_y).powi(2)).sqrt();
+    pub fn draw_ellipse<C: Into<Color>, P: Into<Point>>(&mut self, center: P, radii: [f32;2], color: C) {
+        self.immediate.draw_ellipse(center, radii, color);
+    }
 
-        let x = (((delta_y * 2.) / l)).sin();
-        let y = (((delta_x * 2.) / l) * -1.).sin();
+    pub fn draw_arc<P: Into<Point>>(&mut self, center: P, radius: f32, angles: [f32;2], stroke: &Stroke) {
+        self.immediate.draw_arc(center, radius, angles, stroke);
+    }
 
-        let thickness = thickness/2.;
-        let p = Point::from([x * thickness, y * thickness]);
+    pub fn fill_path<C: Into<Color>>(&mut self, path: &Path, rule: FillRule, color: C) {
+        self.immediate.fill_path(path, rule, color);
+    }
 
-        self.draw_poly([
-            points[0] + p,
-            points[0] - p,
+    pub fn stroke_path(&mut self, path: &Path, stroke: &Stroke) {
+        self.immediate.stroke_path(path, stroke);
+    }
 
-            points[1] - p,
-            points[1] + p,
-        ].into(), color);
+    pub fn draw_polyline<P: Into<Point>>(&mut self, points: Vec<P>, closed: bool, stroke: &Stroke) {
+        self.immediate.draw_polyline(points, closed, stroke);
     }
 
     pub fn draw_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let corners: [Point;2] = corners.map(|p| p.into());
-        let offset = self.vertices.len() as u16;
-
-        let vertices = vec![
-            corners[0],
-            Point::from([corners[0].x, corners[1].y]),
-            corners[1],
-            Point::from([corners[1].x, corners[0].y]),
-
-            corners[0] + Point::from([thickness, thickness * -1.]),
-            Point::from([corners[0].x, corners[1].y]).add_f32(thickness),
-            corners[1] + Point::from([thickness * -1., thickness]),
-            Point::from([corners[1].x, corners[0].y]).sub_f32(thickness),
-        ];
-        let mut vertices = vertices.into_iter().map(|p| Vertex::new(p.into(), color)).collect();
-        self.vertices.append(&mut vertices);
-
-        let indices = vec![
-            0, 1, 4,
-            1, 2, 5,
-            2, 3, 6,
-            3, 0, 7,
-
-            4, 1, 5,
-            5, 2, 6,
-            6, 3, 7,
-            7, 0, 4,
-        ];
-        let mut indices = indices.into_iter().map(|i| i + offset).collect();
-        self.indices.append(&mut indices);
+        self.immediate.draw_box(corners, thickness, color);
     }
 
     pub fn draw_lined_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C, lines: u8, mirrored: bool) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let corners: [Point;2] = corners.map(|p| p.into());
-        self.draw_box(corners, thickness, color);
-
-        let width = corners[1].x - corners[0].x;
-        let height = corners[0].y - corners[1].y;
-        let base_offset_x = (width * SQRT_2).powi(2) / (lines as f32 + 1.);
-        let base_offset_y = (height * SQRT_2).powi(2) / (lines as f32 + 1.);
-        let t = thickness / 2.;
-
-        let mut x = [0, 1];
-        let mut flip = 1.;
-
-        if mirrored {
-            x = [1, 0];
-            flip *= -1.;
+        self.immediate.draw_lined_box(corners, thickness, color, lines, mirrored);
+    }
+
+    pub fn draw_crossed_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C, lines: u8) {
+        self.immediate.draw_crossed_box(corners, thickness, color, lines);
+    }
+
+    /// Uploads a mesh to the gpu once, so it can be drawn every frame with `draw_mesh` without being
+    /// tessellated or uploaded again. Meant for geometry that rarely changes, like the tiles of the grid.
+    pub fn create_mesh(&mut self, mesh: &Mesh) -> MeshId {
+        let mut gpu_mesh = GpuMesh::new(&self.device);
+        gpu_mesh.upload(&self.device, &self.queue, mesh, self.max_index);
+
+        match self.meshes.iter().position(|slot| slot.is_none()) {
+            Some(free) => {
+                self.meshes[free] = Some(gpu_mesh);
+                return MeshId(free);
+            },
+            None => {
+                self.meshes.push(Some(gpu_mesh));
+                return MeshId(self.meshes.len() - 1);
+            },
         }
+    }
 
-        for i in 0..lines {
-            let p;
-            if i + 1 <= (lines / 2) {
-                let offset_x = base_offset_x * (i + 1) as f32;
-                let offset_y = base_offset_y * (i + 1) as f32;
-                p = [
-                    [corners[x[0]].x + t * flip, corners[0].y + t - offset_y], 
-                    [corners[x[0]].x - t * flip + offset_x * flip, corners[0].y - t]
-                ];
-            } else {
-                let offset_x = base_offset_x * (i + 1 - lines / 2) as f32;
-                let offset_y = base_offset_y * (i + 1 - lines / 2) as f32;
-                p = [
-                    [corners[x[1]].x + t * flip - offset_x * flip, corners[1].y + t], 
-                    [corners[x[1]].x - t * flip, corners[1].y - t + offset_y]
-                ];
-            }
-            self.draw_poly([
-                [p[0][0] + t, p[0][1]], 
-                [p[0][0] - t, p[0][1]], 
-                [p[1][0] - t, p[1][1]], 
-                [p[1][0] + t, p[1][1]], 
-            ].into(), color);
+    /// Replaces a mesh's shapes, reusing its gpu buffers when the new ones fit. Does nothing for removed meshes.
+    pub fn update_mesh(&mut self, id: MeshId, mesh: &Mesh) {
+        if let Some(Some(gpu_mesh)) = self.meshes.get_mut(id.0) {
+            gpu_mesh.upload(&self.device, &self.queue, mesh, self.max_index);
         }
     }
 
-    pub fn draw_crossed_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C, lines: u8) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let corners: [Point;2] = corners.map(|p| p.into());
-        self.draw_box(corners, thickness, color);
-
-        let width = corners[1].x - corners[0].x;
-        let height = corners[0].y - corners[1].y;
-        let base_offset_x = (width * SQRT_2).powi(2) / (lines as f32 + 1.);
-        let base_offset_y = (height * SQRT_2).powi(2) / (lines as f32 + 1.);
-        let t = thickness / 2.;
-
-        let mut x = [0, 1];
-        let mut flip = 1.;
-
-        for i in 0..lines {
-            let p;
-            if i + 1 <= (lines / 2) {
-                let offset_x = base_offset_x * (i + 1) as f32;
-                let offset_y = base_offset_y * (i + 1) as f32;
-                p = [
-                    [corners[x[0]].x + t * flip, corners[0].y + t - offset_y], 
-                    [corners[x[0]].x - t * flip + offset_x * flip, corners[0].y - t]
-                ];
-            } else {
-                let offset_x = base_offset_x * (i + 1 - lines / 2) as f32;
-                let offset_y = base_offset_y * (i + 1 - lines / 2) as f32;
-                p = [
-                    [corners[x[1]].x + t * flip - offset_x * flip, corners[1].y + t], 
-                    [corners[x[1]].x - t * flip, corners[1].y - t + offset_y]
-                ];
-            }
-            self.draw_poly([
-                [p[0][0] + t, p[0][1]], 
-                [p[0][0] - t, p[0][1]], 
-                [p[1][0] - t, p[1][1]], 
-                [p[1][0] + t, p[1][1]], 
-            ].into(), color);
+    /// Frees a mesh's gpu buffers, its id may be handed out again by `create_mesh`.
+    pub fn remove_mesh(&mut self, id: MeshId) {
+        if let Some(slot) = self.meshes.get_mut(id.0) {
+            *slot = None;
+        }
+    }
+
+    /// How many meshes are kept on the gpu.
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Draws a mesh from `create_mesh` this frame, in world or clip space depending on `set_world_space`.
+    /// Meshes are drawn before the shapes drawn directly in their layer, so those stay on top of them at the same depth.
+    pub fn draw_mesh(&mut self, id: MeshId) {
+        self.queued_meshes.push((id, self.state()));
+    }
+
+    /// Sets the meshes tiles are drawn from with `draw_tile`, a tile's variant is the index of its template.
+    /// Templates are a unit large and centered on the origin, `tiles::grid_templates` has the ones of the grid.
+    pub fn set_tile_templates(&mut self, templates: &[Mesh]) {
+        let mut mesh = Mesh::new();
+        self.tile_template_ranges.clear();
+        for template in templates {
+            let start = mesh.indices.len() as u32;
+            mesh.append(template);
+            self.tile_template_ranges.push(start..mesh.indices.len() as u32);
         }
+        self.tile_templates.upload(&self.device, &self.queue, &mesh, self.max_index);
+        self.tiles.resize_with(templates.len(), Vec::new);
+    }
 
-        x = [1, 0];
-        flip *= -1.;
-
-        for i in 0..lines {
-            let p;
-            if i + 1 <= (lines / 2) {
-                let offset_x = base_offset_x * (i + 1) as f32;
-                let offset_y = base_offset_y * (i + 1) as f32;
-                p = [
-                    [corners[x[0]].x + t * flip, corners[0].y + t - offset_y], 
-                    [corners[x[0]].x - t * flip + offset_x * flip, corners[0].y - t]
-                ];
-            } else {
-                let offset_x = base_offset_x * (i + 1 - lines / 2) as f32;
-                let offset_y = base_offset_y * (i + 1 - lines / 2) as f32;
-                p = [
-                    [corners[x[1]].x + t * flip - offset_x * flip, corners[1].y + t], 
-                    [corners[x[1]].x - t * flip, corners[1].y - t + offset_y]
-                ];
-            }
-            self.draw_poly([
-                [p[0][0] + t, p[0][1]], 
-                [p[0][0] - t, p[0][1]], 
-                [p[1][0] - t, p[1][1]], 
-                [p[1][0] + t, p[1][1]], 
-            ].into(), color);
+    /// Draws the template of `variant` this frame, moved to `position` and with its colors multiplied by `color`.
+    /// Tiles are drawn on the gpu from their template instead of being tessellated, always in world space in the world
+    /// layer, before its meshes and the shapes drawn directly. Variants without a template aren't drawn.
+    pub fn draw_tile<C: Into<Color>, P: Into<Point>>(&mut self, position: P, variant: usize, color: C) {
+        if let Some(tiles) = self.tiles.get_mut(variant) {
+            tiles.push(TileInstance { offset: position.into().into(), color: color.into().into() });
         }
     }
 
+    /// Decodes a PNG and adds it to the atlas sprites are drawn from, see `add_image`.
+    pub fn load_image(&mut self, png: &[u8]) -> Result<ImageId, ImageError> {
+        let (width, height, rgba) = atlas::decode_png(png)?;
+        self.add_image(width, height, &rgba)
+    }
+
+    /// Adds rows of 8 bit RGBA pixels from the top left to the atlas sprites are drawn from, like art rasterized once
+    /// and kept. Images stay in the atlas for as long as the renderer, which fails to add them once it's full.
+    pub fn add_image(&mut self, width: u32, height: u32, rgba: &[u8]) -> Result<ImageId, ImageError> {
+        if rgba.len() != width as usize * height as usize * 4 {
+            return Err(ImageError::WrongSize { width, height, found: rgba.len() });
+        }
+        let (id, [x, y]) = self.atlas.insert(width, height)?;
+        let (padded_width, padded_height) = (width + atlas::PADDING * 2, height + atlas::PADDING * 2);
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.atlas_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas::extrude(width, height, rgba),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_width * 4),
+                rows_per_image: Some(padded_height),
+            },
+            wgpu::Extent3d { width: padded_width, height: padded_height, depth_or_array_layers: 1 },
+        );
+        return Ok(id);
+    }
+
+    /// The width and height of an image in pixels, None for ids of other renderers.
+    pub fn image_size(&self, id: ImageId) -> Option<[u32;2]> {
+        self.atlas.size(id)
+    }
+
+    /// Draws a whole image stretched between two corners, see `draw_sprite`.
+    pub fn draw_image<C: Into<Color>, P: Into<Point>>(&mut self, id: ImageId, corners: [P;2], tint: C) {
+        self.draw_sprite(id, [[0., 0.], [1., 1.]], corners, tint);
+    }
+
+    /// Draws the part of an image between two uv coordinates, from 0 at its top left to 1 at its bottom right, stretched
+    /// between two corners at the depth of the first, with its colors multiplied by `tint`. Sprites are drawn in world
+    /// or clip space depending on `set_world_space` and after every shape in their layer, so they stay on top of them at the same depth.
+    /// Images are filtered, so parts of sprite sheets should have some room between them.
+    pub fn draw_sprite<C: Into<Color>, P: Into<Point>>(&mut self, id: ImageId, uv: [[f32;2];2], corners: [P;2], tint: C) {
+        let Some([[u0, v0], [u1, v1]]) = self.atlas.uv(id, uv) else {
+            return;
+        };
+        let [a, b]: [Point;2] = corners.map(|p| p.into());
+        let tint: Color = tint.into();
+        let color: [f32;4] = tint.into();
+
+        let offset = self.sprites.len() as u32;
+        for (x, y, u, v) in [(a.x, a.y, u0, v0), (a.x, b.y, u0, v1), (b.x, a.y, u1, v0), (b.x, b.y, u1, v1)] {
+            self.sprites.push(SpriteVertex { position: [x, y, a.z], uv: [u, v], color });
+        }
+        self.sprite_indices.extend([0, 1, 2, 2, 1, 3].map(|i| offset + i));
+    }
+
+    /// Text is drawn over everything else in its layer, whatever its depth. `scale` is the height of a line in
+    /// clip space, glyphs keep their shape whatever the target's aspect ratio, see `logical_size` for text that
+    /// stays the same size on screen.
     pub fn draw_text<C: Into<Color>, P: Into<Point>>(&mut self, position: P, text: &str, color: C, scale: f32) {
-        let color: Color = color.into();
-        let color: [f32;4] = color.into();
-        let position: Point = position.into();
-
-        let width = self.size.width as f32;
-        let height = self.size.height as f32;
-
-        self.glyph_brush.queue(Section {
-            screen_position: ((width / 2.) + (position.x / 2.) * width + 1., (height / 2.) + ((position.y * -1.) / 2.) * height),
-            text: vec![Text::new(text)
-                .with_color(color)
-                .with_scale(ab_glyph::PxScale {x: (scale / 2.) * width, y: (scale / 2.) * height})
-                .with_z(position.z)],
-            ..Section::default()
-        });
+        self.draw_rich_text(position, &RichText::new(color, scale).with_text(text));
+    }
+
+    /// Draws spans of text laid out together from `position`, like `draw_text`.
+    pub fn draw_rich_text<P: Into<Point>>(&mut self, position: P, text: &RichText) {
+        let section = text.section(position.into(), self.size, self.glyph_brush.fonts());
+        self.sections.push((self.state(), section));
+    }
+
+    /// The corners of the box text drawn at `position` takes up in clip space, top left first, as wide as the
+    /// glyphs advance and as high as its lines. None if there's no text.
+    pub fn measure_rich_text<P: Into<Point>>(&mut self, position: P, text: &RichText) -> Option<[Point;2]> {
+        let section = text.section(position.into(), self.size, self.glyph_brush.fonts());
+        let bounds = self.glyph_brush.glyph_bounds(&section)?;
+        return Some(text::to_clip(bounds, self.size));
+    }
+
+    /// The width and height in clip space of text drawn with `draw_text`.
+    pub fn measure_text(&mut self, text: &str, scale: f32) -> [f32;2] {
+        let bounds = self.measure_rich_text(Point::ZERO, &RichText::new(Color::WHITE, scale).with_text(text));
+        return bounds.map_or([0., 0.], |[a, b]| [b.x - a.x, a.y - b.y]);
     }
 
-    fn create_depth_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView {
+    /// Loads a TrueType or OpenType font for text to be drawn in alongside the default one.
+    pub fn load_font(&mut self, bytes: Vec<u8>) -> Result<FontId, InvalidFont> {
+        let font = ab_glyph::FontArc::try_from_vec(bytes)?;
+        return Ok(FontId(self.glyph_brush.add_font(font).0));
+    }
+
+    fn create_depth_view(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>, sample_count: u32) -> wgpu::TextureView {
         let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Depth view"),
             size: wgpu::Extent3d {
-                width: config.width,
-                height: config.height,
+                width: size.width,
+                height: size.height,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Depth32Float,
             view_formats: &[],
@@ -607,6 +1007,118 @@ impl Renderer {
         depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
     }
 
+    fn create_msaa_view(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>, format: wgpu::TextureFormat, sample_count: u32) -> Option<wgpu::TextureView> {
+        if sample_count == 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisampled frame"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            view_formats: &[],
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    fn create_layer_target(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        size: winit::dpi::PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<(wgpu::TextureView, wgpu::BindGroup)> {
+        if sample_count == 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Layer"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            view_formats: &[],
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Layer"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        Some((view, bind_group))
+    }
+
+    // the pipelines only differ in their shaders and vertex buffers, they draw in the same depth and blend the same way
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        buffers: &[wgpu::VertexBufferLayout],
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING), // experiment with overlapping shapes and this
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::GreaterEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_offscreen_texture(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) -> wgpu::Texture {
+        create_capture_texture(device, size, OFFSCREEN_FORMAT)
+    }
+
     pub async fn new(window: &Window) -> Self {
 
         let size = window.inner_size();
@@ -648,188 +1160,698 @@ impl Renderer {
         };
         surface.configure(&device, &config);
 
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
-        
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: None,
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING), // experiment with overlapping shapes and this
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::GreaterEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
-        
-        let vertices = Vec::new();
-
-        let indices = Vec::new();
+        let sample_count = Renderer::supported_sample_count(&adapter, surface_format);
+        let mut renderer = Renderer::with_target(device, queue, Renderer::max_index(&adapter), sample_count, size, surface_format, RenderTarget::Window { surface, config });
+        renderer.scale_factor = window.scale_factor();
+        return renderer;
+    }
 
-        let staging_belt = wgpu::util::StagingBelt::new(1024);
+    /// A renderer that draws into an offscreen texture instead of a window, for tests and running without a display.
+    /// Falls back to a software adapter if there is no gpu, None if there is no adapter at all.
+    ///
+    /// Frames are rendered with `render` as usual and read back with `read_frame`.
+    pub async fn new_headless(width: u32, height: u32) -> Option<Self> {
+        let size = winit::dpi::PhysicalSize::new(width.max(1), height.max(1));
 
-        let depth_view = Renderer::create_depth_view(&device, &config);
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor{ 
+            backends: wgpu::Backends::all(), 
+            dx12_shader_compiler: wgpu::Dx12Compiler::Fxc 
+        });
 
-        let inconsolata = ab_glyph::FontArc::try_from_slice(include_bytes!(
-            "../Inconsolata-Regular.ttf"
-        )).unwrap();
+        let mut adapter = None;
+        for force_fallback_adapter in [false, true] {
+            adapter = instance.request_adapter(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                },
+            ).await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+
+        let adapter = adapter?;
+        let (device, queue) = adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                // software and gl adapters don't always reach the default limits
+                limits: wgpu::Limits::downlevel_defaults(),
+                ..Default::default()
+            },
+            None,
+        ).await.ok()?;
+
+        let texture = Renderer::create_offscreen_texture(&device, size);
+
+        let sample_count = Renderer::supported_sample_count(&adapter, OFFSCREEN_FORMAT);
+        Some(Renderer::with_target(device, queue, Renderer::max_index(&adapter), sample_count, size, OFFSCREEN_FORMAT, RenderTarget::Offscreen { texture }))
+    }
+
+    // adapters without full u32 index support, like WebGL ones, only reach indices up to 2^24 - 1
+    fn max_index(adapter: &wgpu::Adapter) -> u32 {
+        match adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::FULL_DRAW_INDEX_UINT32) {
+            true => u32::MAX,
+            false => (1 << 24) - 1,
+        }
+    }
+
+    // MSAA_SAMPLES if both the target's format and the depth buffer's can be multisampled that much, 1 otherwise
+    fn supported_sample_count(adapter: &wgpu::Adapter, format: wgpu::TextureFormat) -> u32 {
+        let supported = |format| adapter.get_texture_format_features(format).flags.sample_count_supported(MSAA_SAMPLES);
+        match supported(format) && supported(wgpu::TextureFormat::Depth32Float) {
+            true => MSAA_SAMPLES,
+            false => 1,
+        }
+    }
+
+    fn with_target(device: wgpu::Device, queue: wgpu::Queue, max_index: u32, sample_count: u32, size: winit::dpi::PhysicalSize<u32>, format: wgpu::TextureFormat, target: RenderTarget) -> Self {
+        // every pipeline shares the camera bind groups, so they share a layout instead of deriving one each
+        let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4, 2 => Float32x4, 3 => Float32x4, 4 => Uint32],
+        };
+        let render_pipeline = Renderer::create_pipeline(
+            &device,
+            &pipeline_layout,
+            &device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("shapes.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("fill.wgsl"), include_str!("shapes.wgsl")).into()),
+            }),
+            std::slice::from_ref(&vertex_layout),
+            format,
+            sample_count,
+        );
+        let tile_pipeline = Renderer::create_pipeline(
+            &device,
+            &pipeline_layout,
+            &device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("tiles.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(concat!(include_str!("fill.wgsl"), include_str!("tiles.wgsl")).into()),
+            }),
+            &[vertex_layout, wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<TileInstance>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![5 => Float32x3, 6 => Float32x4],
+            }],
+            format,
+            sample_count,
+        );
+
+        let atlas_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Atlas"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sprite_pipeline = Renderer::create_pipeline(
+            &device,
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&camera_bind_group_layout, &atlas_bind_group_layout],
+                push_constant_ranges: &[],
+            }),
+            &device.create_shader_module(wgpu::include_wgsl!("sprites.wgsl")),
+            &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4],
+            }],
+            format,
+            sample_count,
+        );
+
+        let composite_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Layer"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let composite_shader = device.create_shader_module(wgpu::include_wgsl!("composite.wgsl"));
+        // drawn straight into the resolved target, over what's there already
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Composite"),
+            layout: Some(&device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&composite_bind_group_layout],
+                push_constant_ranges: &[],
+            })),
+            vertex: wgpu::VertexState {
+                module: &composite_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &composite_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let layer_target = Renderer::create_layer_target(&device, &composite_bind_group_layout, size, format, sample_count);
+
+        // images are srgb like the targets drawn into, so they're blended in linear space like shapes
+        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Atlas"),
+            size: wgpu::Extent3d {
+                width: atlas::ATLAS_SIZE,
+                height: atlas::ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            view_formats: &[],
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Atlas"),
+            layout: &atlas_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_texture.create_view(&wgpu::TextureViewDescriptor::default())),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&device.create_sampler(&wgpu::SamplerDescriptor {
+                        mag_filter: wgpu::FilterMode::Linear,
+                        min_filter: wgpu::FilterMode::Linear,
+                        ..Default::default()
+                    })),
+                },
+            ],
+        });
+
+        let camera = Camera::new(size);
+        let camera_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Camera"),
+                contents: bytemuck::cast_slice(&camera.view_proj()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let clip_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Clip space"),
+                contents: bytemuck::cast_slice(&Camera::new(size).view_proj()),
+                usage: wgpu::BufferUsages::UNIFORM,
+            }
+        );
+        let bind_group = |buffer: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let camera_bind_group = bind_group(&camera_buffer);
+        let clip_bind_group = bind_group(&clip_buffer);
+
+        let immediate_buffers = GpuMesh::new(&device);
+        let tile_templates = GpuMesh::new(&device);
+        let tile_buffer = DynamicBuffer::new(&device, wgpu::BufferUsages::VERTEX);
+        let sprite_vertex_buffer = DynamicBuffer::new(&device, wgpu::BufferUsages::VERTEX);
+        let sprite_index_buffer = DynamicBuffer::new(&device, wgpu::BufferUsages::INDEX);
+
+        let staging_belt = wgpu::util::StagingBelt::new(1024);
+
+        let msaa_view = Renderer::create_msaa_view(&device, size, format, sample_count);
+        let depth_view = Renderer::create_depth_view(&device, size, sample_count);
+        let text_depth_view = Renderer::create_depth_view(&device, size, 1);
+
+        let inconsolata = ab_glyph::FontArc::try_from_slice(text::INCONSOLATA).unwrap();
     
         let glyph_brush = GlyphBrushBuilder::using_font(inconsolata)
             .depth_stencil_state(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
                 depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Greater,
+                depth_compare: wgpu::CompareFunction::GreaterEqual,
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             })
-            .build(&device, surface_format);
+            .build(&device, format);
 
         Renderer {
             size,
-            surface,
+            scale_factor: 1.,
+            target,
             device,
             queue,
-            config,
 
             render_pipeline,
+            tile_pipeline,
+            format,
+            sample_count,
+            msaa_view,
+            layer_target,
+            composite_pipeline,
+            composite_bind_group_layout,
             depth_view,
-
-            vertices,
-            indices,
+            text_depth_view,
+
+            immediate: Mesh::new(),
+            immediate_buffers,
+            max_index,
+            meshes: Vec::new(),
+            queued_meshes: Vec::new(),
+
+            tile_templates,
+            tile_template_ranges: Vec::new(),
+            tiles: Vec::new(),
+            tile_upload: Vec::new(),
+            tile_buffer,
+
+            sprite_pipeline,
+            atlas: Atlas::default(),
+            atlas_texture,
+            atlas_bind_group,
+            sprites: Vec::new(),
+            sprite_indices: Vec::new(),
+            sprite_vertex_buffer,
+            sprite_index_buffer,
 
             staging_belt,
             glyph_brush,
+            sections: Vec::new(),
+
+            capture_requested: false,
+            captured: None,
+
+            camera,
+            camera_buffer,
+            camera_bind_group,
+            clip_bind_group,
+
+            layer: Layer::default(),
+            world_space: false,
+            clip_stack: Vec::new(),
+            runs: Vec::new(),
+            run_start: [0, 0],
+            clips: [None;4],
         }
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.depth_view = Renderer::create_depth_view(&self.device, &self.config)
+            match &mut self.target {
+                RenderTarget::Window { surface, config } => {
+                    config.width = new_size.width;
+                    config.height = new_size.height;
+                    surface.configure(&self.device, config);
+                },
+                RenderTarget::Offscreen { texture } => {
+                    *texture = Renderer::create_offscreen_texture(&self.device, new_size);
+                },
+            }
+            self.msaa_view = Renderer::create_msaa_view(&self.device, new_size, self.format, self.sample_count);
+            self.layer_target = Renderer::create_layer_target(&self.device, &self.composite_bind_group_layout, new_size, self.format, self.sample_count);
+            self.depth_view = Renderer::create_depth_view(&self.device, new_size, self.sample_count);
+            self.text_depth_view = Renderer::create_depth_view(&self.device, new_size, 1);
+            self.camera.set_viewport(new_size);
         }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+    /// Sets how many physical pixels a logical one is, as winit reports it for the window.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor > 0. {
+            self.scale_factor = scale_factor;
+        }
+    }
 
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None, });
+    /// A point `x` and `y` logical pixels from the top left of the target in clip space, for UI laid out
+    /// the same on any resolution and scale factor.
+    pub fn logical_point(&self, x: f32, y: f32) -> Point {
+        let [width, height] = self.logical_size(x, y);
+        return Point { x: width - 1., y: 1. - height, z: 0. };
+    }
 
-        let vertex_buffer = self.device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&self.vertices),
-                usage: wgpu::BufferUsages::VERTEX,
+    /// A width and height in logical pixels in clip space, the height being what text `height` logical pixels
+    /// high is drawn with.
+    pub fn logical_size(&self, width: f32, height: f32) -> [f32;2] {
+        let scale_factor = self.scale_factor as f32;
+        return [
+            width * scale_factor / (self.size.width as f32 / 2.),
+            height * scale_factor / (self.size.height as f32 / 2.),
+        ];
+    }
+
+    /// How many samples every pixel of the shapes is drawn with, more than 1 when their edges are anti-aliased.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self.target, RenderTarget::Offscreen { .. })
+    }
+
+    fn bind_group(&self, world_space: bool) -> &wgpu::BindGroup {
+        if world_space { &self.camera_bind_group } else { &self.clip_bind_group }
+    }
+
+    fn is_layer_empty(&self, layer: Layer) -> bool {
+        let tiles = layer == Layer::World && self.tiles.iter().any(|tiles| !tiles.is_empty());
+        !tiles
+            && !self.queued_meshes.iter().any(|(_, state)| state.layer == layer)
+            && !self.runs.iter().any(|run| run.state.layer == layer)
+            && !self.sections.iter().any(|(state, _)| state.layer == layer)
+    }
+
+    // what of a layer's scissor something drawn in it with `clip` is clipped to, None if nothing
+    fn clip_scissor(layer_scissor: [u32;4], clip: Option<[u32;4]>) -> Option<[u32;4]> {
+        let scissor = match clip {
+            Some(clip) => layer::intersect(layer_scissor, clip),
+            None => layer_scissor,
+        };
+        if layer::is_empty(scissor) { None } else { Some(scissor) }
+    }
+
+    // the shapes and sprites of a layer, text is drawn apart from them
+    fn draw_layer<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, layer: Layer, layer_scissor: [u32;4]) {
+        let [x, y, width, height] = layer_scissor;
+        render_pass.set_scissor_rect(x, y, width, height);
+
+        if layer == Layer::World {
+            render_pass.set_pipeline(&self.tile_pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.tile_templates.vertex_buffer.buffer.slice(..));
+            render_pass.set_index_buffer(self.tile_templates.index_buffer.buffer.slice(..), wgpu::IndexFormat::Uint32);
+            // each variant's instances are bound from where they start instead of being offset with the first instance,
+            // which not every adapter supports
+            let mut start = 0;
+            for (template, tiles) in self.tile_template_ranges.iter().zip(&self.tiles) {
+                if !tiles.is_empty() {
+                    let end = start + std::mem::size_of_val(tiles.as_slice()) as wgpu::BufferAddress;
+                    render_pass.set_vertex_buffer(1, self.tile_buffer.buffer.slice(start..end));
+                    render_pass.draw_indexed(template.clone(), 0, 0..tiles.len() as u32);
+                    start = end;
+                }
             }
-        );
+        }
 
-        let index_buffer = self.device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(&self.indices),
-                usage: wgpu::BufferUsages::INDEX,
+        render_pass.set_pipeline(&self.render_pipeline);
+
+        for &(id, state) in &self.queued_meshes {
+            let Some(Some(mesh)) = self.meshes.get(id.0) else {
+                continue;
+            };
+            let Some([x, y, width, height]) = Renderer::clip_scissor(layer_scissor, state.clip).filter(|_| state.layer == layer) else {
+                continue;
+            };
+            render_pass.set_scissor_rect(x, y, width, height);
+            render_pass.set_bind_group(0, self.bind_group(state.world_space), &[]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
+        }
+
+        // the runs of the layer with what they're clipped to, leaving out those clipped away entirely
+        let runs: Vec<(&Run, [u32;4])> = self.runs.iter()
+            .filter(|run| run.state.layer == layer)
+            .filter_map(|run| Some((run, Renderer::clip_scissor(layer_scissor, run.state.clip)?)))
+            .collect();
+
+        render_pass.set_vertex_buffer(0, self.immediate_buffers.vertex_buffer.buffer.slice(..));
+        render_pass.set_index_buffer(self.immediate_buffers.index_buffer.buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (run, [x, y, width, height]) in runs.iter().filter(|(run, _)| !run.shapes.is_empty()) {
+            render_pass.set_scissor_rect(*x, *y, *width, *height);
+            render_pass.set_bind_group(0, self.bind_group(run.state.world_space), &[]);
+            render_pass.draw_indexed(run.shapes.clone(), 0, 0..1);
+        }
+
+        render_pass.set_pipeline(&self.sprite_pipeline);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.sprite_vertex_buffer.buffer.slice(..));
+        render_pass.set_index_buffer(self.sprite_index_buffer.buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (run, [x, y, width, height]) in runs.iter().filter(|(run, _)| !run.sprites.is_empty()) {
+            render_pass.set_scissor_rect(*x, *y, *width, *height);
+            render_pass.set_bind_group(0, self.bind_group(run.state.world_space), &[]);
+            render_pass.draw_indexed(run.sprites.clone(), 0, 0..1);
+        }
+    }
+
+    // every layer gets a pass of its own with the depth cleared, so nothing in it is hidden by the layers before it
+    fn draw_frame(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let full = [0, 0, self.size.width, self.size.height];
+        // multisampled layers can only be resolved over the target as they are before text is drawn into it
+        let mut text_drawn = false;
+
+        for layer in Layer::ALL {
+            let first = layer == Layer::World;
+            let scissor = match self.clips[layer.index()] {
+                Some(corners) => layer::scissor(corners, self.size),
+                None => full,
+            };
+            // the first pass clears the frame even if there is nothing to draw
+            if !first && (layer::is_empty(scissor) || self.is_layer_empty(layer)) {
+                continue;
             }
-        );
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(0.0),
-                    store: true,
+            let composite = match &self.layer_target {
+                Some((layer_view, _)) if text_drawn => Some(layer_view),
+                _ => None,
+            };
+            let load = match (first, composite) {
+                (true, _) => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                (false, Some(_)) => wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                (false, None) => wgpu::LoadOp::Load,
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: self.msaa_view.as_ref().unwrap_or(view),
+                    resolve_target: composite.or(self.msaa_view.as_ref().map(|_| view)),
+                    ops: wgpu::Operations {
+                        load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
                 }),
-                stencil_ops: None,
-            }),
-        });
+            });
+            if !layer::is_empty(scissor) {
+                self.draw_layer(&mut render_pass, layer, scissor);
+                // some backends clip resolving multisampled targets too
+                render_pass.set_scissor_rect(full[0], full[1], full[2], full[3]);
+            }
+            drop(render_pass);
+
+            if let (Some(_), Some((_, bind_group))) = (composite, &self.layer_target) {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                render_pass.set_pipeline(&self.composite_pipeline);
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.indices.len() as u32, 0, 0..1);
-
-        drop(render_pass);
-
-        self.glyph_brush.draw_queued(
-            &self.device,
-            &mut self.staging_belt,
-            &mut encoder,
-            &view,
-            wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(0.0),
-                    store: true,
-                }),
-                stencil_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(0),
-                    store: true,
-                }),
+            // text is drawn a clip at a time, one after the other
+            let sections: Vec<&(DrawState, OwnedSection)> = self.sections.iter().filter(|(state, _)| state.layer == layer).collect();
+            for clipped in sections.chunk_by(|a, b| a.0.clip == b.0.clip) {
+                let Some([x, y, width, height]) = Renderer::clip_scissor(scissor, clipped[0].0.clip) else {
+                    continue;
+                };
+                for (_, section) in clipped {
+                    self.glyph_brush.queue(section);
+                }
+                self.glyph_brush.draw_queued_with_transform_and_scissoring(
+                    &self.device,
+                    &mut self.staging_belt,
+                    encoder,
+                    view,
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.text_depth_view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0.0),
+                            store: true,
+                        }),
+                        stencil_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0),
+                            store: true,
+                        }),
+                    },
+                    wgpu_glyph::orthographic_projection(self.size.width, self.size.height),
+                    wgpu_glyph::Region { x, y, width, height },
+                ).unwrap();
+                text_drawn = true;
+            }
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = match &self.target {
+            RenderTarget::Window { surface, .. } => Some(surface.get_current_texture()?),
+            RenderTarget::Offscreen { .. } => None,
+        };
+
+        let view = match (&output, &self.target) {
+            (Some(output), _) => output.texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, RenderTarget::Offscreen { texture }) => texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, RenderTarget::Window { .. }) => unreachable!(),
+        };
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None, });
+
+        self.close_run();
+        for run in &self.runs {
+            self.immediate.sort_back_to_front(run.shapes.start as usize..run.shapes.end as usize);
+        }
+        self.immediate_buffers.upload(&self.device, &self.queue, &self.immediate, self.max_index);
+
+        self.tile_upload.clear();
+        for tiles in &self.tiles {
+            self.tile_upload.extend_from_slice(tiles);
+        }
+        self.tile_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(&self.tile_upload));
+
+        self.sprite_vertex_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(&self.sprites));
+        self.sprite_index_buffer.write(&self.device, &self.queue, bytemuck::cast_slice(&self.sprite_indices));
+
+        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&self.camera.view_proj()));
+
+        self.draw_frame(&mut encoder, &view);
+
+        // most surfaces can't be copied from, so a captured window frame is drawn a second time into a texture that can be
+        let capture_texture = match &self.target {
+            RenderTarget::Window { config, .. } if self.capture_requested => {
+                let texture = create_capture_texture(&self.device, self.size, config.format);
+                self.draw_frame(&mut encoder, &texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                Some(texture)
             },
-            self.size.width,
-            self.size.height,
-        ).unwrap();
+            _ => None,
+        };
         self.staging_belt.finish();
     
         self.queue.submit(Some(encoder.finish()));
-        output.present();
 
-        self.vertices = Vec::new();
-        self.indices = Vec::new();
+        if self.capture_requested {
+            self.captured = match (&capture_texture, &self.target) {
+                (Some(texture), _) | (None, RenderTarget::Offscreen { texture }) => {
+                    read_texture(&self.device, &self.queue, texture).map_err(|err| eprintln!("{}", err)).ok()
+                },
+                (None, RenderTarget::Window { .. }) => None,
+            };
+            self.capture_requested = false;
+        }
+
+        if let Some(output) = output {
+            output.present();
+        }
+        self.staging_belt.recall();
+
+        self.immediate.clear();
+        self.queued_meshes.clear();
+        for tiles in &mut self.tiles {
+            tiles.clear();
+        }
+        self.sprites.clear();
+        self.sprite_indices.clear();
+        self.sections.clear();
+        // drawing keeps going in the same layer and space next frame
+        self.runs.clear();
+        self.run_start = [0, 0];
     
         Ok(())
     }
+
+    /// Copies the last rendered frame back from the gpu, None for renderers drawing to a window,
+    /// whose frames are gone once they are shown, use `request_capture` for those, or if it couldn't be read back.
+    pub fn read_frame(&self) -> Option<Frame> {
+        let RenderTarget::Offscreen { texture } = &self.target else {
+            return None;
+        };
+        return read_texture(&self.device, &self.queue, texture).map_err(|err| eprintln!("{}", err)).ok();
+    }
+
+    /// Keeps a copy of the next frame `render` draws, to be picked up with `take_capture`.
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// The frame kept after `request_capture`, None if nothing was rendered since. Clears the request.
+    pub fn take_capture(&mut self) -> Option<Frame> {
+        self.capture_requested = false;
+        self.captured.take()
+    }
 }
 
 // ----- TESTS -----
 #[cfg(test)]
 mod tests {
     use super::*;    
+    use std::f32::consts::PI;
+    use crate::engine::grid::{ Coord, Grid, Tile };
 
     // ----- COLOR TESTS -----
     #[test]
@@ -909,253 +1931,885 @@ mod tests {
         );
     }
 
-    // ----- RENDERER TESTS -----
-    // creating a winit EventLoop in non main thread requires this import and to configure it with .with_any_thread(true)
-    use winit::platform::wayland::EventLoopBuilderExtWayland;
+    // ----- HEADLESS RENDERER TESTS -----
+    use super::snapshot::headless;
+
+    // the pixels of a frame that aren't the black it's cleared to
+    fn lit_pixels(frame: &Frame) -> Vec<(u32, u32)> {
+        (0..frame.width * frame.height)
+            .map(|i| (i % frame.width, i / frame.width))
+            .filter(|&(x, y)| frame.pixel(x, y) != [0, 0, 0, 255])
+            .collect()
+    }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
-    fn test_renderer() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.render().unwrap();
-            });
+    fn test_headless_clear() {
+        let Some(mut renderer) = headless(64, 32) else { return };
+        assert!(renderer.is_headless());
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (64, 32));
+        assert_eq!(frame.pixels.len(), 64 * 32 * 4);
+        assert!(frame.pixels.chunks(4).all(|pixel| pixel == [0, 0, 0, 255]), "ERROR: headless frame wasn't cleared to black");
+    }
+
+    #[test]
+    fn test_headless_draw_rect() {
+        // a width that isn't a multiple of 64 pixels makes the readback rows padded
+        let Some(mut renderer) = headless(100, 50) else { return };
+        renderer.draw_rect([[-0.5, 0.5], [0.5, -0.5]], Color::RED);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(50, 25), [255, 0, 0, 255], "ERROR: center of the frame should be covered by the rect");
+        assert_eq!(frame.pixel(10, 25), [0, 0, 0, 255], "ERROR: left of the rect should be left black");
+        assert_eq!(frame.pixel(50, 5), [0, 0, 0, 255], "ERROR: above the rect should be left black");
+
+        // drawn shapes are cleared after every frame
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(50, 25), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_headless_orientation() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        // top left quarter of the screen
+        renderer.draw_rect([[-1., 1.], [0., 0.]], Color::GREEN);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(5, 5), [0, 255, 0, 255], "ERROR: frame rows should start at the top left");
+        assert_eq!(frame.pixel(35, 35), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_headless_resize() {
+        let Some(mut renderer) = headless(32, 32) else { return };
+        renderer.resize(winit::dpi::PhysicalSize::new(48, 16));
+        renderer.draw_rect([[-1., 1.], [1., -1.]], Color::BLUE);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (48, 16));
+        assert!(frame.pixels.chunks(4).all(|pixel| pixel == [0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_headless_mesh() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let mut mesh = Mesh::new();
+        mesh.draw_rect([[-1., 1.], [0., 0.]], Color::RED);
+        let id = renderer.create_mesh(&mesh);
+
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(5, 5), [255, 0, 0, 255]);
+
+        // meshes stay uploaded but are only drawn in frames they're drawn in
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(5, 5), [0, 0, 0, 255], "ERROR: mesh was drawn without draw_mesh");
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(5, 5), [255, 0, 0, 255], "ERROR: mesh was lost after a frame");
+
+        // a larger mesh than the buffers were made for
+        mesh.clear();
+        for i in 0..200 {
+            mesh.draw_rect([[0., 1. - i as f32 * 0.005], [1., 0.]], Color::GREEN);
         }
-        pollster::block_on(run())
+        renderer.update_mesh(id, &mesh);
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(35, 5), [0, 255, 0, 255]);
+        assert_eq!(frame.pixel(5, 5), [0, 0, 0, 255], "ERROR: updated mesh kept its old shapes");
+
+        renderer.remove_mesh(id);
+        assert_eq!(renderer.mesh_count(), 0);
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(35, 5), [0, 0, 0, 255], "ERROR: removed mesh was drawn");
+
+        assert_eq!(renderer.create_mesh(&mesh), id, "ERROR: removed mesh's id wasn't reused");
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
-    fn test_renderer_resize() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |event, _, _| {
-                renderer.draw_triangle([[0.0, 0.5], [-0.5, -0.5], [0.5, -0.5]], Color::RED);
-                match event {
-                    Event::WindowEvent { event: WindowEvent::Resized(new_size), .. } => {
-                        renderer.resize(new_size);
-                    },
-                    Event::MainEventsCleared => {
-                        renderer.render().unwrap()
-                    },
-                    _ => {},
-                }
-            });
+    fn test_headless_many_vertices() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        // empty rects, so the red one's vertices come after the first 65,536, past what u16 indices reach
+        let mut mesh = Mesh::new();
+        for _ in 0..(u16::MAX as usize + 1) / 4 {
+            mesh.draw_rect([[0., 0.], [0., 0.]], Color::BLUE);
+        }
+        mesh.draw_rect([[-1., 1.], [0., 0.]], Color::RED);
+        assert!(mesh.vertices.len() > u16::MAX as usize + 1);
+
+        for (x, y) in [(-1., 1.), (0., 1.)] {
+            renderer.draw_rect([[x, y], [x, y]], Color::BLUE);
+        }
+        for _ in 0..(u16::MAX as usize + 1) / 4 {
+            renderer.draw_rect([[0., 0.], [0., 0.]], Color::BLUE);
         }
-        pollster::block_on(run())
+        renderer.draw_rect([[0., 0.], [1., -1.]], Color::GREEN);
+        let id = renderer.create_mesh(&mesh);
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(5, 5), [255, 0, 0, 255], "ERROR: mesh with more vertices than u16 indices reach was drawn wrong");
+        assert_eq!(frame.pixel(35, 35), [0, 255, 0, 255], "ERROR: frame with more vertices than u16 indices reach was drawn wrong");
+        assert_eq!(frame.pixel(35, 5), [0, 0, 0, 255]);
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
-    fn test_renderer_draw_triangle() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_triangle([[0.0, 0.5], [-0.5, -0.5], [0.5, -0.5]], Color::RED);
-
-                renderer.render().unwrap();
-            });
+    fn test_headless_mesh_order() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let mut mesh = Mesh::new();
+        mesh.draw_rect([[-1., 1.], [1., -1.]], Color::RED);
+        let id = renderer.create_mesh(&mesh);
+
+        // drawn after the mesh in code, but also before it
+        renderer.draw_rect([[-1., 1.], [0., -1.]], Color::BLUE);
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(5, 20), [0, 0, 255, 255], "ERROR: shapes drawn directly should be on top of meshes");
+        assert_eq!(frame.pixel(35, 20), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_headless_mesh_world_space() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let mut mesh = Mesh::new();
+        mesh.draw_rect([[9., 11.], [10., 10.]], Color::GREEN);
+        let id = renderer.create_mesh(&mesh);
+
+        renderer.camera.set_position([10., 10.]);
+        renderer.set_world_space(true);
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(15, 15), [0, 255, 0, 255], "ERROR: world space mesh wasn't moved by the camera");
+
+        renderer.set_world_space(false);
+        renderer.draw_mesh(id);
+        renderer.render().unwrap();
+        assert_eq!(renderer.read_frame().unwrap().pixel(15, 15), [0, 0, 0, 255], "ERROR: clip space mesh was moved by the camera");
+    }
+
+    #[test]
+    fn test_headless_tiles() {
+        let Some(mut renderer) = headless(64, 64) else { return };
+        let grid = Grid::new(vec![
+            ((0, 0), Tile::Air),
+            ((1, 0), Tile::Ground),
+            ((0, 1), Tile::Building { health: 100., tier: 2., pressure: 0. }),
+            ((1, 1), Tile::Building { health: 100., tier: 7., pressure: 0. }),
+        ]);
+        let color = |coord: Coord, _: &Tile| Color::from([0.25 + coord.x() as f32 * 0.5, 0.5, 1. - coord.y() as f32 * 0.5]);
+        renderer.camera.set_position([0.5, 0.5]);
+        renderer.camera.set_view_size([2.5, 2.5]);
+
+        // the same tiles tessellated on the cpu
+        renderer.set_world_space(true);
+        for (coord, tile) in grid.iter() {
+            let mut template = tiles::grid_templates(0.05, 4).swap_remove(tiles::grid_variant(tile, 4));
+            for vertex in &mut template.vertices {
+                vertex.position[0] += coord.x() as f32;
+                vertex.position[1] += coord.y() as f32;
+                vertex.color = (color(coord, tile) * vertex.color).into();
+            }
+            renderer.immediate.append(&template);
         }
-        pollster::block_on(run())
+        renderer.render().unwrap();
+        let tessellated = renderer.read_frame().unwrap();
+
+        renderer.set_tile_templates(&tiles::grid_templates(0.05, 4));
+        tiles::draw_grid(&mut renderer, &grid, (0, 0), (1, 1), 4, color);
+        renderer.render().unwrap();
+        let instanced = renderer.read_frame().unwrap();
+
+        assert!(instanced.pixels.chunks(4).any(|pixel| pixel != [0, 0, 0, 255]), "ERROR: no tiles were drawn");
+        let (mismatched, _) = snapshot::compare(&tessellated, &instanced, SHAPE_TOLERANCE);
+        assert_eq!(mismatched, 0, "ERROR: instanced tiles should look like tessellated ones");
+
+        // tiles are only drawn in the frame they're drawn in, and variants without a template not at all
+        renderer.draw_tile([0., 0.], 100, Color::WHITE);
+        renderer.render().unwrap();
+        assert!(renderer.read_frame().unwrap().pixels.chunks(4).all(|pixel| pixel == [0, 0, 0, 255]));
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
-    fn test_renderer_alpha() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_triangle([[0.25, 0.5], [-0.25, -0.5], [0.75, -0.5]], Color::BLUE);
-                renderer.draw_triangle([[-0.25, 0.5], [-0.75, -0.5], [0.25, -0.5]], Color::RED.with_alpha(0.5));
-
-                renderer.render().unwrap();
-            });
+    fn test_headless_tiles_order() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let mut template = Mesh::new();
+        template.draw_rect([[-0.5, 0.5], [0.5, -0.5]], Color::WHITE);
+        renderer.set_tile_templates(&[template.clone(), template]);
+
+        renderer.set_world_space(true);
+        renderer.draw_rect([[-0.5, 0.5], [0.5, 0.]], Color::BLUE);
+        // the second variant is drawn from a later part of the instance buffer
+        renderer.draw_tile([0., 0.], 1, Color::GREEN);
+        renderer.draw_tile([0., 0.], 0, Color::RED);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(20, 14), [0, 0, 255, 255], "ERROR: shapes drawn directly should be on top of tiles");
+        assert_eq!(frame.pixel(20, 26), [0, 255, 0, 255], "ERROR: later variants should be on top of earlier ones");
+    }
+
+    #[test]
+    fn test_headless_antialiasing() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        if renderer.sample_count() == 1 {
+            eprintln!("adapter can't multisample, skipping anti-aliasing test");
+            return;
         }
-        pollster::block_on(run())
+        // a diagonal edge through the middle of the pixels it crosses
+        renderer.draw_triangle([[-1., -1.], [1., -1.], [1., 1.]], Color::WHITE);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        let edge = (0..40).map(|i| frame.pixel(i, 39 - i)[0]);
+        assert!(edge.clone().all(|red| red > 0 && red < 255), "ERROR: pixels on the edge should be partly covered, got {:?}", edge.collect::<Vec<_>>());
+        assert_eq!(frame.pixel(35, 20), [255, 255, 255, 255]);
+        assert_eq!(frame.pixel(5, 20), [0, 0, 0, 255]);
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
-    fn test_renderer_z() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_triangle([[0.25, 0.5, 1.0], [-0.25, -0.5, 1.0], [0.75, -0.5, 1.0]], Color::BLUE);
-                renderer.draw_text([-0.25, 0.0, 0.75], "Hello World!", Color::WHITE, 0.1);
-                renderer.draw_triangle([[-0.25, 0.5, 0.0], [-0.75, -0.5, 0.0], [0.25, -0.5, 0.0]], Color::RED);
-                renderer.draw_triangle([[0.0, 0.75, 0.5], [-0.5, -0.25, 0.5], [0.5, -0.25, 0.5]], Color::GREEN);
-
-                renderer.render().unwrap();
+    fn test_headless_fill() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        renderer.with_fill(Fill::linear([-1., 0.], [1., 0.], Color::RED, Color::BLUE), |renderer| {
+            renderer.draw_rect([[-1., 1.], [1., 0.]], Color::WHITE);
+        });
+        renderer.with_opacity(0.5, |renderer| renderer.draw_rect([[-1., 0.], [1., -1.]], Color::GREEN));
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        let [left, middle, right] = [0, 20, 39].map(|x| frame.pixel(x, 10));
+        assert!(left[0] > 240 && left[2] < 60 && right[2] > 240 && right[0] < 60, "ERROR: gradient should go from red to blue, got {:?} and {:?}", left, right);
+        assert!(middle[0] > 100 && middle[2] > 100, "ERROR: middle of the gradient should be a mix, got {:?}", middle);
+        // half opaque green over black, in srgb
+        let faded = frame.pixel(20, 30);
+        assert!(faded[1] > 150 && faded[1] < 210, "ERROR: shape wasn't faded, got {:?}", faded);
+    }
+
+    #[test]
+    fn test_headless_fill_zoom() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        // vertical lines half a unit thick every unit, the edge of the one at 0 is in the middle of the frame
+        renderer.camera.set_position([0.25, 0.]);
+        renderer.set_world_space(true);
+        for view in [4., 1., 0.01] {
+            renderer.camera.set_view_size([view, view]);
+            renderer.with_fill(Fill::hatch(PI / 2., 1., 0.5, Color::WHITE, Color::BLACK), |renderer| {
+                renderer.draw_rect([[-10., 10.], [10., -10.]], Color::WHITE);
             });
+            renderer.render().unwrap();
+
+            let frame = renderer.read_frame().unwrap();
+            let row: Vec<u8> = (0..40).map(|x| frame.pixel(x, 20)[0]).collect();
+            assert_eq!((row[18], row[21]), (255, 0), "ERROR: hatch edge isn't where it should be at a view of {}: {:?}", view, row);
+            let blurred = row.iter().filter(|&&red| red > 0 && red < 255).count();
+            assert!(blurred <= 8, "ERROR: hatch should stay sharp at a view of {}, got {:?}", view, row);
         }
-        pollster::block_on(run())
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
+    fn test_headless_sprites() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        // red and green over blue and white
+        let pixels = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+        let image = renderer.add_image(2, 2, &pixels).unwrap();
+        assert_eq!(renderer.image_size(image), Some([2, 2]));
+        assert!(matches!(renderer.add_image(2, 2, &pixels[..12]), Err(ImageError::WrongSize { .. })));
+
+        renderer.draw_image(image, [[-1., 1.], [1., -1.]], Color::WHITE);
+        renderer.render().unwrap();
+        let frame = renderer.read_frame().unwrap();
+        let corners = [(5, 5), (34, 5), (5, 34), (34, 34)].map(|(x, y)| frame.pixel(x, y));
+        assert_eq!(corners, [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [255, 255, 255, 255]], "ERROR: image wasn't drawn right side up");
+
+        // tinted, and the top right quarter stretched over the frame
+        renderer.draw_image(image, [[-1., 1.], [1., -1.]], [1., 0., 1.]);
+        renderer.draw_sprite(image, [[0.5, 0.], [1., 0.5]], [[-1., 1., 0.5], [0., 0., 0.5]], Color::WHITE);
+        renderer.render().unwrap();
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(34, 34), [255, 0, 255, 255], "ERROR: sprite wasn't tinted");
+        assert_eq!(frame.pixel(15, 2), [0, 255, 0, 255], "ERROR: sprite should only show its part of the image");
+
+        // sprites follow the camera in world space
+        renderer.camera.set_position([10., 10.]);
+        renderer.set_world_space(true);
+        renderer.draw_image(image, [[9., 11.], [10., 10.]], Color::WHITE);
+        renderer.render().unwrap();
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!((frame.pixel(2, 2), frame.pixel(30, 30)), ([255, 0, 0, 255], [0, 0, 0, 255]), "ERROR: world space sprite wasn't moved by the camera");
+
+        // png bytes of any color type are loaded too
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 1, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.write_header().unwrap().write_image_data(&[0, 255, 0]).unwrap();
+        let loaded = renderer.load_image(&png).unwrap();
+        assert_ne!(loaded, image);
+        assert!(matches!(renderer.load_image(&png[..8]), Err(ImageError::Decode(_))));
+        renderer.draw_image(loaded, [[9., 11.], [11., 9.]], Color::WHITE);
+        renderer.render().unwrap();
+        assert!(renderer.read_frame().unwrap().pixels.chunks(4).all(|pixel| pixel == [0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_headless_sprite_depth() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let half = renderer.add_image(2, 1, &[255, 255, 255, 255, 0, 0, 0, 0]).unwrap();
+        let whole = renderer.add_image(1, 1, &[255, 255, 255, 255]).unwrap();
+
+        // in front of the sprites, though drawn before them
+        renderer.draw_rect([[-1., 1., 1.], [1., 0.5, 1.]], Color::GREEN);
+        // behind the transparent half of the first sprite, drawn after it
+        renderer.draw_image(half, [[-1., 1., 0.5], [1., -1., 0.5]], Color::RED);
+        renderer.draw_image(whole, [[-1., 1., 0.25], [1., -1., 0.25]], Color::BLUE);
+        // at the same depth as the sprites, which are drawn after shapes
+        renderer.draw_rect([[-1., -0.5, 0.5], [1., -1., 0.5]], Color::WHITE);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(5, 2), [0, 255, 0, 255], "ERROR: shapes in front of sprites should hide them");
+        assert_eq!(frame.pixel(5, 20), [255, 0, 0, 255], "ERROR: sprites in front of others should hide them");
+        assert_eq!(frame.pixel(34, 20), [0, 0, 255, 255], "ERROR: transparent parts of sprites shouldn't hide what's behind them");
+        assert_eq!(frame.pixel(5, 37), [255, 0, 0, 255], "ERROR: sprites should be on top of shapes at the same depth");
+    }
+
+    #[test]
+    fn test_headless_layers() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        // in front of everything and drawn first, but in the layer below the others
+        renderer.draw_rect([[-1., 1., 1.], [1., -1., 1.]], Color::RED);
+        renderer.set_layer(Layer::Debug);
+        renderer.draw_rect([[0., 1.], [1., 0.]], Color::GREEN);
+        renderer.set_layer(Layer::Ui);
+        renderer.draw_rect([[-1., 1.], [1., 0.]], Color::BLUE);
+        assert_eq!(renderer.layer(), Layer::Ui);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(5, 5), [0, 0, 255, 255], "ERROR: later layers should be on top whatever the depth");
+        assert_eq!(frame.pixel(35, 5), [0, 255, 0, 255], "ERROR: layers should be drawn in order, not in the order they're drawn into");
+        assert_eq!(frame.pixel(20, 35), [255, 0, 0, 255]);
+
+        // the layer and space stay set across frames
+        renderer.set_world_space(true);
+        renderer.render().unwrap();
+        let mut mesh = Mesh::new();
+        mesh.draw_rect([[-1., 1.], [1., -1.]], Color::WHITE);
+        let id = renderer.create_mesh(&mesh);
+        renderer.draw_mesh(id);
+        renderer.draw_rect([[-1., 1.], [0., 0.]], Color::BLUE);
+        renderer.set_layer(Layer::Overlay);
+        renderer.draw_rect([[-1., 1., 1.], [1., -1., 1.]], Color::RED);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!((renderer.layer(), renderer.is_world_space()), (Layer::Overlay, true));
+        assert_eq!((frame.pixel(5, 5), frame.pixel(35, 35)), ([0, 0, 255, 255], [255, 255, 255, 255]), "ERROR: meshes should be drawn in their own layer");
+    }
+
+    #[test]
+    fn test_headless_clip() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let full = [[-1., 1.], [1., -1.]];
+        let image = renderer.add_image(1, 1, &[255, 255, 255, 255]).unwrap();
+        renderer.set_clip(Layer::Ui, [[-1., 1.], [0., 0.]]);
+        renderer.set_clip(Layer::World, [[0., 0.], [1., -1.]]);
+
+        renderer.set_layer(Layer::Ui);
+        renderer.draw_image(image, full, Color::GREEN);
+        renderer.set_layer(Layer::World);
+        renderer.draw_rect(full, Color::RED);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        let quarters = [(5, 5), (35, 5), (5, 35), (35, 35)].map(|(x, y)| frame.pixel(x, y));
+        assert_eq!(quarters, [[0, 255, 0, 255], [0, 0, 0, 255], [0, 0, 0, 255], [255, 0, 0, 255]], "ERROR: layers should only be drawn within their clip");
+
+        // clips stay until they're cleared, empty ones draw nothing of their layer
+        renderer.clear_clip(Layer::Ui);
+        renderer.set_clip(Layer::World, [[0., 0.], [0., 1.]]);
+        renderer.draw_rect(full, Color::RED);
+        renderer.set_layer(Layer::Ui);
+        renderer.draw_rect([[-1., 1.], [1., 0.]], Color::BLUE);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!((frame.pixel(35, 5), frame.pixel(35, 35)), ([0, 0, 255, 255], [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_headless_clip_stack() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let full = [[-1., 1.], [1., -1.]];
+        let mut mesh = Mesh::new();
+        mesh.draw_rect(full, Color::GREEN);
+        let id = renderer.create_mesh(&mesh);
+
+        // the left half, then its top half within it, then the left half again
+        renderer.push_clip([[-1., 1.], [0., -1.]]);
+        renderer.draw_rect(full, Color::RED);
+        renderer.push_pixel_clip(0, 0, 40, 20);
+        assert_eq!(renderer.clip_depth(), 2);
+        renderer.draw_mesh(id);
+        renderer.draw_text([-1., 1.], "WW", Color::BLUE, 1.);
+        renderer.pop_clip();
+        renderer.draw_rect([[-1., -0.5, 1.], [1., -1., 1.]], Color::WHITE);
+        renderer.pop_clip();
+        renderer.draw_rect([[0.5, -0.5, 1.], [1., -1., 1.]], Color::WHITE);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(renderer.clip_depth(), 0);
+        assert_eq!(frame.pixel(25, 5), [0, 0, 0, 255], "ERROR: nested clips should be within the ones below them");
+        assert_eq!(frame.pixel(5, 25), [255, 0, 0, 255], "ERROR: meshes should be clipped");
+        assert_eq!(frame.pixel(5, 35), [255, 255, 255, 255]);
+        assert_eq!(frame.pixel(25, 35), [0, 0, 0, 255], "ERROR: popping a clip should clip to the one below it again");
+        assert_eq!(frame.pixel(35, 35), [255, 255, 255, 255], "ERROR: nothing should be clipped once every clip is popped");
+        let text: Vec<(u32, u32)> = (0..40 * 40).map(|i| (i % 40, i / 40)).filter(|&(x, y)| frame.pixel(x, y) == [0, 0, 255, 255]).collect();
+        assert!(!text.is_empty(), "ERROR: text should be drawn within its clip");
+        assert!(text.iter().all(|&(x, y)| x < 20 && y < 20), "ERROR: text should be clipped");
+    }
+
+    #[test]
+    fn test_headless_measure_text() {
+        let Some(mut renderer) = headless(128, 64) else { return };
+        let text = RichText::new(Color::WHITE, 0.5).with_text("Hello");
+        let [a, b] = renderer.measure_rich_text([-0.5, 0.5], &text).unwrap();
+        renderer.draw_rich_text([-0.5, 0.5], &text);
+        renderer.render().unwrap();
+
+        // every lit pixel is within the measured box, and the glyphs reach most of the way across it
+        let frame = renderer.read_frame().unwrap();
+        let lit = lit_pixels(&frame);
+        let to_pixels = |p: Point| [(p.x + 1.) * 64., (1. - p.y) * 32.];
+        let ([left, top], [right, bottom]) = (to_pixels(a), to_pixels(b));
+        assert!(lit.iter().all(|&(x, y)| x as f32 >= left - 1. && x as f32 <= right && y as f32 >= top && y as f32 <= bottom), "ERROR: text should be drawn within its measured bounds");
+        let lit_width = lit.iter().map(|(x, _)| *x).max().unwrap() - lit.iter().map(|(x, _)| *x).min().unwrap();
+        assert!(lit_width as f32 > (right - left) * 0.8, "ERROR: measured bounds should be as wide as the text");
+
+        let [width, height] = renderer.measure_text("Hello", 0.5);
+        assert!((width - (b.x - a.x)).abs() < 1e-5 && (height - (a.y - b.y)).abs() < 1e-5);
+        assert!(height >= 0.5, "ERROR: text should measure at least as high as its scale");
+        let [double, _] = renderer.measure_text("HelloHello", 0.5);
+        assert!((double - width * 2.).abs() < 1e-3);
+        assert_eq!(renderer.measure_rich_text([0., 0.], &RichText::new(Color::WHITE, 0.5)), None);
+    }
+
+    #[test]
+    fn test_headless_text_layout() {
+        let Some(mut renderer) = headless(128, 64) else { return };
+        let text = RichText::new(Color::WHITE, 0.25).with_text("Hi");
+
+        let [_, right] = renderer.measure_rich_text([0.5, 0.], &text.clone().with_align(HAlign::Right, VAlign::Top)).unwrap();
+        assert!((right.x - 0.5).abs() < 0.02, "ERROR: right aligned text should end at its position");
+        let [top, bottom] = renderer.measure_rich_text([0.5, 0.], &text.clone().with_align(HAlign::Center, VAlign::Center)).unwrap();
+        assert!((top.x + bottom.x) / 2. - 0.5 < 0.02 && (top.y + bottom.y).abs() < 0.02, "ERROR: centered text should be centered on its position");
+        let [_, bottom] = renderer.measure_rich_text([0., 0.], &text.clone().with_align(HAlign::Left, VAlign::Bottom)).unwrap();
+        assert!(bottom.y.abs() < 0.02);
+        let [top, bottom] = renderer.measure_rich_text([0., 0.], &text.clone().with_align(HAlign::Left, VAlign::Baseline)).unwrap();
+        assert!(top.y > 0. && bottom.y < 0., "ERROR: baseline aligned text should go above and below its position");
+
+        // wrapped between words once lines get wider than the wrap width
+        let long = RichText::new(Color::WHITE, 0.25).with_text("one two three four");
+        let [a, b] = renderer.measure_rich_text([-1., 1.], &long).unwrap();
+        let [c, d] = renderer.measure_rich_text([-1., 1.], &long.clone().with_wrap(0.7)).unwrap();
+        assert!(d.x - c.x <= 0.7 && d.x - c.x < b.x - a.x, "ERROR: wrapped text should fit its wrap width");
+        assert!(c.y - d.y > (a.y - b.y) * 1.5, "ERROR: wrapped text should take more lines");
+    }
+
+    #[test]
+    fn test_headless_text_aspect() {
+        let Some(mut renderer) = headless(64, 64) else { return };
+        let [square_width, square_height] = renderer.measure_text("Hello", 0.5);
+        renderer.resize(winit::dpi::PhysicalSize::new(128, 64));
+        let [wide_width, wide_height] = renderer.measure_text("Hello", 0.5);
+        assert_eq!(square_height, wide_height);
+        assert!((square_width * 32. - wide_width * 64.).abs() < 0.5, "ERROR: text should be as wide in pixels whatever the aspect ratio");
+
+        // logical pixels are as many physical ones as the scale factor
+        assert_eq!(renderer.logical_size(32., 16.), [0.5, 0.5]);
+        assert_eq!(renderer.logical_point(32., 16.), Point { x: -0.5, y: 0.5, z: 0. });
+        renderer.set_scale_factor(2.);
+        assert_eq!(renderer.scale_factor(), 2.);
+        assert_eq!(renderer.logical_size(32., 16.), [1., 1.]);
+        renderer.set_scale_factor(0.);
+        assert_eq!(renderer.scale_factor(), 2.);
+
+        let [_, height] = renderer.logical_size(0., 8.);
+        let [_, measured] = renderer.measure_text("Hello", height);
+        renderer.resize(winit::dpi::PhysicalSize::new(128, 128));
+        let [_, height] = renderer.logical_size(0., 8.);
+        let [_, resized] = renderer.measure_text("Hello", height);
+        assert!((measured * 32. - resized * 64.).abs() < 0.5, "ERROR: text sized in logical pixels should stay the same size in pixels");
+    }
+
+    #[test]
+    fn test_headless_rich_text() {
+        let Some(mut renderer) = headless(128, 64) else { return };
+        assert!(renderer.load_font(b"not a font".to_vec()).is_err());
+        let font = renderer.load_font(text::INCONSOLATA.to_vec()).unwrap();
+        assert_eq!(font, FontId(1));
+
+        let text = RichText::new(Color::WHITE, 0.5)
+            .with_font(font)
+            .with_text("Money ")
+            .with_span(Span::new("-20").with_color(Color::RED).with_font(FontId::DEFAULT));
+        renderer.draw_rich_text([-1., 0.5], &text);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        let lit = lit_pixels(&frame);
+        // antialiased edges are neither
+        let is_white = |&&(x, y): &&(u32, u32)| frame.pixel(x, y)[1] > 128;
+        let is_red = |&&(x, y): &&(u32, u32)| { let pixel = frame.pixel(x, y); pixel[0] > 128 && pixel[1] < 64 };
+        let white_right = lit.iter().filter(is_white).map(|(x, _)| *x).max().unwrap();
+        let red_left = lit.iter().filter(is_red).map(|(x, _)| *x).min().unwrap();
+        assert!(white_right < red_left, "ERROR: styled spans should be drawn after the text before them in their own color");
+    }
+
+    #[test]
+    fn test_headless_layer_text() {
+        let Some(mut renderer) = headless(128, 64) else { return };
+        // text in the world, covered by the ui, and text in the ui over the world
+        renderer.draw_text([-1., 1.], "Hi", Color::WHITE, 0.5);
+        renderer.draw_rect([[0., 1.], [1., -1.]], Color::RED);
+        renderer.set_layer(Layer::Ui);
+        renderer.draw_rect([[-1., 1.], [0., -1.]], Color::BLUE);
+        renderer.draw_text([0., 1.], "Hi", Color::WHITE, 0.5);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        let (left, right): (Vec<_>, Vec<_>) = (0..128 * 64).map(|i| (i % 128, frame.pixel(i % 128, i / 128))).partition(|(x, _)| *x < 64);
+        assert!(left.iter().all(|(_, pixel)| *pixel == [0, 0, 255, 255]), "ERROR: text should be covered by the layers after its own");
+        assert!(right.iter().any(|(_, pixel)| pixel[1] > 128), "ERROR: text should be drawn over the layers before its own");
+        assert!(right.iter().any(|(_, pixel)| *pixel == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_headless_translucent_order() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let square = |z: f32| vec![[-1., 1., z], [1., 1., z], [1., -1., z], [-1., -1., z]];
+        // red in front of blue, though drawn before it
+        renderer.draw_poly(square(0.5), Color::RED.with_alpha(0.5));
+        renderer.draw_poly(square(0.25), Color::BLUE.with_alpha(0.5));
+        renderer.set_layer(Layer::Ui);
+        // translucent, then opaque at the same depth
+        renderer.draw_rect([[-1., 1.], [0., 0.]], Color::WHITE.with_alpha(0.5));
+        renderer.draw_rect([[-1., 1.], [0., 0.]], Color::GREEN);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        let blended = frame.pixel(30, 30);
+        assert!(blended[2] > 100 && blended[0] > blended[2], "ERROR: translucent shapes should be blended back to front, got {:?}", blended);
+        assert_eq!(frame.pixel(5, 5), [0, 255, 0, 255], "ERROR: shapes at the same depth should be drawn in the order they're drawn in");
+    }
+
+    #[test]
+    fn test_headless_text() {
+        let Some(mut renderer) = headless(128, 64) else { return };
+        // text at the cleared depth passes the depth test like shapes do
+        renderer.draw_text([-0.5, 0.5], "Hello", Color::WHITE, 0.5);
+        renderer.render().unwrap();
+
+        let frame = renderer.read_frame().unwrap();
+        assert!(frame.pixels.chunks(4).any(|pixel| pixel[0] > 128), "ERROR: text wasn't drawn into the headless frame");
+    }
+
+    #[test]
+    fn test_headless_capture() {
+        let Some(mut renderer) = headless(32, 32) else { return };
+        renderer.draw_rect([[-1., 1.], [1., -1.]], Color::BLUE);
+        renderer.render().unwrap();
+        assert_eq!(renderer.take_capture(), None, "ERROR: frames should only be captured after request_capture");
+
+        renderer.request_capture();
+        renderer.draw_rect([[-1., 1.], [1., -1.]], Color::RED);
+        renderer.render().unwrap();
+        // the next frame doesn't replace the captured one
+        renderer.render().unwrap();
+
+        let frame = renderer.take_capture().unwrap();
+        assert_eq!((frame.width, frame.height), (32, 32));
+        assert!(frame.pixels.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+        assert_eq!(renderer.take_capture(), None);
+    }
+
+    // ----- SNAPSHOT TESTS -----
+    // golden images live in src/engine/draw/snapshots, run with UPDATE_SNAPSHOTS=1 to rewrite them after intended changes
+    use super::snapshot::{ assert_snapshot, side_by_side };
+
+    // edges of shapes can land on slightly different pixels on different adapters, text more so
+    const SHAPE_TOLERANCE: u8 = 2;
+    const TEXT_TOLERANCE: u8 = 48;
+
+    fn snapshot<F: FnOnce(&mut Renderer)>(name: &str, width: u32, height: u32, tolerance: u8, draw: F) {
+        let Some(mut renderer) = headless(width, height) else { return };
+        draw(&mut renderer);
+        renderer.render().unwrap();
+        assert_snapshot(name, &renderer.read_frame().unwrap(), tolerance);
+    }
+
+    #[test]
+    fn test_renderer_resize() {
+        let Some(mut renderer) = headless(128, 128) else { return };
+        renderer.resize(winit::dpi::PhysicalSize::new(192, 96));
+        renderer.draw_triangle([[0.0, 0.5], [-0.5, -0.5], [0.5, -0.5]], Color::RED);
+        renderer.render().unwrap();
+        assert_snapshot("resize", &renderer.read_frame().unwrap(), SHAPE_TOLERANCE);
+    }
+
+    #[test]
+    fn test_renderer_camera() {
+        snapshot("camera", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.camera.set_position([10., 10.]);
+            renderer.camera.set_view_size([4., 4.]);
+            renderer.set_world_space(true);
+            // the tiles at 10, 10 and 11, 10
+            renderer.draw_rect([[9.5, 10.5], [10.5, 9.5]], Color::RED);
+            renderer.draw_rect([[10.5, 10.5], [11.5, 9.5]], Color::GREEN);
+            renderer.set_world_space(false);
+            // the camera doesn't move what is drawn in clip space
+            renderer.draw_rect([[-1., -0.8], [1., -1.]], Color::BLUE);
+        });
+    }
+
+    #[test]
+    fn test_renderer_draw_triangle() {
+        snapshot("draw_triangle", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.draw_triangle([[0.0, 0.5], [-0.5, -0.5], [0.5, -0.5]], Color::RED);
+        });
+    }
+
+    #[test]
+    fn test_renderer_alpha() {
+        snapshot("alpha", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.draw_triangle([[0.25, 0.5], [-0.25, -0.5], [0.75, -0.5]], Color::BLUE);
+            renderer.draw_triangle([[-0.25, 0.5], [-0.75, -0.5], [0.25, -0.5]], Color::RED.with_alpha(0.5));
+        });
+    }
+
+    #[test]
+    fn test_renderer_z() {
+        snapshot("z", 256, 256, TEXT_TOLERANCE, |renderer| {
+            renderer.draw_triangle([[0.25, 0.5, 1.0], [-0.25, -0.5, 1.0], [0.75, -0.5, 1.0]], Color::BLUE);
+            renderer.draw_text([-0.25, 0.0, 0.75], "Hello World!", Color::WHITE, 0.1);
+            renderer.draw_triangle([[-0.25, 0.5, 0.0], [-0.75, -0.5, 0.0], [0.25, -0.5, 0.0]], Color::RED);
+            renderer.draw_triangle([[0.0, 0.75, 0.5], [-0.5, -0.25, 0.5], [0.5, -0.25, 0.5]], Color::GREEN);
+        });
+    }
+
+    #[test]
     fn test_renderer_draw_rect() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_rect([[-0.5, 0.5], [0.5, -0.5]], Color::RED);
-
-                renderer.render().unwrap();
-            });
-        }
-        pollster::block_on(run())
+        snapshot("draw_rect", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.draw_rect([[-0.5, 0.5], [0.5, -0.5]], Color::RED);
+        });
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
     fn test_renderer_draw_poly() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_poly([[0.0, 0.9], [-0.75, 0.5], [-0.5, -0.75], [0.5, -0.75], [0.75, 0.5]].into(), Color::RED);
-
-                renderer.render().unwrap();
-            });
-        }
-        pollster::block_on(run())
+        snapshot("draw_poly", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.draw_poly([[0.0, 0.9], [-0.75, 0.5], [-0.5, -0.75], [0.5, -0.75], [0.75, 0.5]].into(), Color::RED);
+        });
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
     fn test_renderer_draw_line() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-
-            let mut ox = 0.;
-            let mut oy = 0.;
-
-            event_loop.run(move |_, _, _| {
-                renderer.draw_line([[0.5 - ox, 0.5 - oy], [-0.5 + ox, -0.5 + oy]], 0.1, Color::RED);
-
-                renderer.render().unwrap();
-
-                // this animation allows for displaying that the line is drawn correctly for all 360 degrees of rotation
-                if oy < 1. {
-                    oy += 0.01;
-                } else {
-                    ox += 0.01;
-                    if ox > 1. {
-                        oy = 0.;
-                        ox = 0.
-                    }
-                }
-                std::thread::sleep(std::time::Duration::from_millis(50))
+        // lines through the center at every 15 degrees, to check they're drawn correctly at any rotation
+        snapshot("draw_line", 256, 256, SHAPE_TOLERANCE, |renderer| {
+            for i in 0..12 {
+                let angle = (i as f32 * 15.).to_radians();
+                let (x, y) = (angle.cos() * 0.9, angle.sin() * 0.9);
+                renderer.draw_line([[x, y], [-x, -y]], 0.02, Color::RED.with_alpha(0.5));
+            }
+        });
+    }
+
+    #[test]
+    fn test_renderer_draw_polyline() {
+        snapshot("draw_polyline", 256, 256, SHAPE_TOLERANCE, |renderer| {
+            let zigzag = |y: f32| vec![[-0.8, y], [-0.5, y + 0.2], [-0.2, y - 0.05], [0.1, y + 0.2], [0.2, y]];
+            // joins, top to bottom
+            for (i, join) in [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel].into_iter().enumerate() {
+                renderer.draw_polyline(zigzag(0.6 - i as f32 * 0.4), false, &Stroke::new(0.08, Color::RED).with_join(join));
+            }
+            // caps, with thin lines where they end
+            for (i, cap) in [LineCap::Butt, LineCap::Round, LineCap::Square].into_iter().enumerate() {
+                let y = 0.7 - i as f32 * 0.4;
+                renderer.draw_polyline(vec![[0.45, y], [0.75, y]], false, &Stroke::new(0.1, Color::GREEN).with_cap(cap));
+                renderer.draw_line([[0.45, y + 0.1, 0.1], [0.45, y - 0.1, 0.1]], 0.01, Color::WHITE);
+                renderer.draw_line([[0.75, y + 0.1, 0.1], [0.75, y - 0.1, 0.1]], 0.01, Color::WHITE);
+            }
+            // a closed dashed outline and a sharp corner past the miter limit
+            let dashed = Stroke::new(0.04, Color::BLUE).with_cap(LineCap::Round).with_dashes(&[0.15, 0.08], 0.);
+            renderer.draw_polyline(vec![[-0.8, -0.5], [-0.2, -0.5], [-0.2, -0.9], [-0.8, -0.9]], true, &dashed);
+            renderer.draw_polyline(vec![[0.1, -0.9], [0.4, -0.4], [0.7, -0.9]], false, &Stroke::new(0.06, Color::WHITE));
+            renderer.draw_polyline(vec![[0.2, -0.9], [0.4, -0.5], [0.6, -0.9]], false, &Stroke::new(0.06, Color::WHITE).with_miter_limit(1.5));
+        });
+    }
+
+    #[test]
+    fn test_renderer_draw_curves() {
+        snapshot("draw_curves", 256, 256, SHAPE_TOLERANCE, |renderer| {
+            renderer.draw_circle([-0.6, 0.6], 0.3, Color::RED);
+            renderer.draw_ellipse([0., 0.6], [0.2, 0.3], Color::GREEN);
+            renderer.draw_arc([0.6, 0.6], 0.25, [0., PI * 1.5], &Stroke::new(0.06, Color::BLUE).with_cap(LineCap::Round));
+
+            // a building outline with a hole, and the same bowtie under both fill rules
+            let house = Path::new()
+                .move_to([-0.9, -0.3]).line_to([-0.3, -0.3]).line_to([-0.3, 0.1]).quad_to([-0.6, 0.4], [-0.9, 0.1]).close()
+                .move_to([-0.7, -0.2]).line_to([-0.5, -0.2]).line_to([-0.5, 0.]).line_to([-0.7, 0.]).close();
+            renderer.fill_path(&house, FillRule::EvenOdd, Color::WHITE);
+            let star: Vec<[f32;2]> = (0..5).map(|i| {
+                let angle = PI / 2. + i as f32 * 4. * PI / 5.;
+                [angle.cos() * 0.3, angle.sin() * 0.3 - 0.1]
+            }).collect();
+            renderer.draw_polygon(star.iter().map(|p| [p[0], p[1]]).collect(), FillRule::EvenOdd, Color::RED);
+            renderer.draw_polygon(star.iter().map(|p| [p[0] + 0.65, p[1]]).collect(), FillRule::NonZero, Color::RED);
+
+            // a pipe along a cubic curve and a rounded UI panel
+            let pipe = Path::new().move_to([-0.9, -0.9]).cubic_to([-0.5, -0.4], [-0.1, -1.], [0.2, -0.6]);
+            renderer.stroke_path(&pipe, &Stroke::new(0.08, Color::GREEN).with_join(LineJoin::Round));
+            renderer.fill_path(&Path::rounded_rect([[0.35, -0.5], [0.9, -0.9]], 0.1), FillRule::NonZero, Color::BLUE);
+        });
+    }
+
+    #[test]
+    fn test_renderer_fills() {
+        snapshot("fills", 256, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.with_fill(Fill::linear([-0.95, 0.], [-0.55, 0.], Color::RED, Color::BLUE), |renderer| {
+                renderer.draw_rect([[-0.95, 0.9], [-0.55, 0.1]], Color::WHITE);
             });
-        }
-        pollster::block_on(run())
+            renderer.with_fill(Fill::radial([-0.25, 0.5], 0.2, Color::WHITE, Color::GREEN.with_alpha(0.)), |renderer| {
+                renderer.draw_circle([-0.25, 0.5], 0.2, Color::WHITE);
+            });
+            renderer.with_fill(Fill::hatch(PI / 4., 0.08, 0.02, [1., 1., 1.], [0.2, 0.08, 0.]), |renderer| {
+                renderer.draw_rect([[0.05, 0.9], [0.45, 0.1]], Color::WHITE);
+            });
+            renderer.with_fill(Fill::crosshatch(PI / 4., 0.1, 0.015, Color::GREEN, Color::BLACK), |renderer| {
+                renderer.fill_path(&Path::rounded_rect([[0.55, 0.9], [0.95, 0.1]], 0.1), FillRule::NonZero, Color::WHITE);
+            });
+
+            // per shape opacity, over what is drawn before it
+            renderer.draw_rect([[-0.95, -0.1], [-0.35, -0.9]], Color::BLUE);
+            renderer.with_opacity(0.5, |renderer| {
+                renderer.draw_circle([-0.45, -0.5, 0.1], 0.3, Color::RED);
+            });
+
+            // hatched tiles, the hatch is laid out in the template so every tile gets the same one
+            renderer.camera.set_position([2.5, 1.]);
+            renderer.camera.set_view_size([4., 2.]);
+            let mut template = Mesh::new();
+            template.with_fill(Fill::hatch(-PI / 4., 0.2, 0.05, Color::WHITE, Color::TRANSPARENT), |mesh| {
+                mesh.draw_rect([[-0.4, 0.4], [0.4, -0.4]], Color::WHITE);
+            });
+            renderer.set_tile_templates(&[template]);
+            renderer.draw_tile([2.5, 0.5], 0, Color::WHITE);
+            renderer.draw_tile([3.5, 0.5], 0, Color::RED);
+        });
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
     fn test_renderer_draw_box() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_box([[-0.5, 0.5], [0.5, -0.5]], 0.1, Color::RED);
-
-                renderer.render().unwrap();
-            });
+        snapshot("draw_box", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.draw_box([[-0.5, 0.5], [0.5, -0.5]], 0.1, Color::RED);
+        });
+    }
+
+    // the extra lines only line up in boxes as large as the original manual tests drew them,
+    // so every line count from 0 to 5 gets its own frame
+    fn line_counts<F: Fn(&mut Renderer, u8)>(name: &str, draw: F) {
+        let mut frames = Vec::new();
+        for n in 0..6 {
+            let Some(mut renderer) = headless(96, 96) else { return };
+            draw(&mut renderer, n);
+            renderer.render().unwrap();
+            frames.push(renderer.read_frame().unwrap());
         }
-        pollster::block_on(run())
+        assert_snapshot(name, &side_by_side(&frames), SHAPE_TOLERANCE);
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
     fn test_renderer_draw_lined_box() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            let mut ox = 0.;
-            let mut oy = 0.;
-            let mut n = 0;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_lined_box([[-0.5 + ox, 0.5 - oy], [0.5 - ox, -0.5 + oy]], 0.1, Color::RED, n, false);
-
-
-                if n < 5 {
-                    n += 1;
-                } else {
-                    n = 0;
-                }
-                /*
-                if oy < 1. {
-                    oy += 0.01;
-                } else {
-                    ox += 0.01;
-                    if ox > 1. {
-                        oy = 0.;
-                        ox = 0.
-                    }
-                }
-                 */
-
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                renderer.render().unwrap();
-            });
-        }
-        pollster::block_on(run())
+        line_counts("draw_lined_box", |renderer, n| {
+            renderer.draw_lined_box([[-0.5, 0.5], [0.5, -0.5]], 0.1, Color::RED, n, false);
+        });
+        line_counts("draw_lined_box_mirrored", |renderer, n| {
+            renderer.draw_lined_box([[-0.5, 0.5], [0.5, -0.5]], 0.1, Color::RED, n, true);
+        });
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
     fn test_renderer_draw_crossed_box() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            let mut n = 0;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_crossed_box([[-0.5, 0.5], [0.5, -0.5]], 0.1, Color::RED, n);
-
-                if n < 5 {
-                    n += 1;
-                } else {
-                    n = 0;
-                }
+        line_counts("draw_crossed_box", |renderer, n| {
+            renderer.draw_crossed_box([[-0.5, 0.5], [0.5, -0.5]], 0.1, Color::RED, n);
+        });
+    }
 
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                renderer.render().unwrap();
-            });
-        }
-        pollster::block_on(run())
+    #[test]
+    fn test_renderer_tiles() {
+        snapshot("tiles", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            renderer.camera.set_position([1., 1.]);
+            renderer.camera.set_view_size([4., 4.]);
+            renderer.set_tile_templates(&tiles::grid_templates(0.05, 4));
+            renderer.draw_tile([0., 0.], tiles::AIR, Color::WHITE);
+            renderer.draw_tile([1., 0.], tiles::GROUND, [0.6, 0.3, 0.]);
+            for tier in 0..5 {
+                renderer.draw_tile([tier as f32 - 1., 2.], tiles::grid_variant(&Tile::Building { health: 100., tier: tier as f32, pressure: 0. }, 4), Color::GREEN);
+            }
+            renderer.draw_tile([2., 1.], tiles::GROUND, Color::WHITE.with_alpha(0.5));
+        });
+    }
+
+    #[test]
+    fn test_renderer_sprites() {
+        snapshot("sprites", 128, 128, SHAPE_TOLERANCE, |renderer| {
+            // a warning ring fading out at its edges, rasterized here instead of loaded
+            let ring: Vec<u8> = (0..16 * 16).flat_map(|i| {
+                let (x, y) = ((i % 16) as f32 - 7.5, (i / 16) as f32 - 7.5);
+                let alpha = (1. - ((x * x + y * y).sqrt() - 5.5).abs() / 2.).clamp(0., 1.);
+                [255, 255, 255, (alpha * 255.) as u8]
+            }).collect();
+            let ring = renderer.add_image(16, 16, &ring).unwrap();
+            // a sheet of two frames with a transparent column between them, from a grayscale png
+            let mut sheet = Vec::new();
+            let mut encoder = png::Encoder::new(&mut sheet, 5, 2);
+            encoder.set_color(png::ColorType::GrayscaleAlpha);
+            encoder.write_header().unwrap().write_image_data(&[
+                255, 255, 128, 255, 0, 0, 64, 255, 255, 255,
+                128, 255, 255, 255, 0, 0, 255, 255, 64, 255,
+            ]).unwrap();
+            let sheet = renderer.load_image(&sheet).unwrap();
+
+            // failing buildings with the ring over them, in world space
+            renderer.camera.set_position([1., 1.]);
+            renderer.camera.set_view_size([4., 4.]);
+            renderer.set_world_space(true);
+            for (x, y) in [(0., 0.), (1., 0.), (0., 1.), (2., 2.)] {
+                renderer.draw_lined_box([[x - 0.5, y + 0.5], [x + 0.5, y - 0.5]], 0.05, Color::GREEN, 2, false);
+            }
+            renderer.draw_image(ring, [[-0.3, 0.3, 0.1], [0.3, -0.3, 0.1]], Color::RED);
+            renderer.draw_image(ring, [[1.7, 2.3, 0.1], [2.3, 1.7, 0.1]], [1., 0.8, 0.]);
+
+            // both frames of the sheet as UI, the second one flipped
+            renderer.set_world_space(false);
+            renderer.draw_sprite(sheet, [[0., 0.], [0.4, 1.]], [[0.5, -0.5], [0.75, -0.9]], Color::WHITE);
+            renderer.draw_sprite(sheet, [[1., 0.], [0.6, 1.]], [[0.75, -0.5], [1., -0.9]], Color::BLUE + Color::GREEN * 0.5);
+        });
     }
 
     #[test]
-    #[ignore = "requires manual validation, run separetely"]
     fn test_renderer_text() {
-        async fn run() {
-            let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
-            let window = Window::new(&event_loop).unwrap();
-            let mut renderer = Renderer::new(&window).await;
-            event_loop.run(move |_, _, _| {
-                renderer.draw_text([0., 0.,], "Hello World!", Color::WHITE, 0.1);
-                renderer.render().unwrap();
-            });
-        }
-        pollster::block_on(run())
+        snapshot("text", 256, 128, TEXT_TOLERANCE, |renderer| {
+            renderer.draw_text([-0.9, 0.5, 0.5], "Hello World!", Color::WHITE, 0.2);
+        });
     }
 }
\ No newline at end of file
@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::fmt;
+use std::io::Cursor;
+
+/// The width and height in pixels of the texture images are packed into.
+pub const ATLAS_SIZE: u32 = 1024;
+// images are surrounded by a copy of their edge pixels, so filtering at their edges doesn't blend in their neighbours
+pub(super) const PADDING: u32 = 1;
+
+/// An image in a Renderer's atlas, see `Renderer::load_image`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageId(usize);
+
+#[derive(Debug)]
+pub enum ImageError {
+    Decode(png::DecodingError),
+    /// The pixels passed in aren't `width * height` RGBA pixels.
+    WrongSize { width: u32, height: u32, found: usize },
+    Empty,
+    /// The image is larger than the atlas, padding included.
+    TooLarge { width: u32, height: u32 },
+    /// There is no room left in the atlas for the image.
+    AtlasFull { width: u32, height: u32 },
+}
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "An error occurred while loading an image: ")?;
+        match self {
+            ImageError::Decode(err) => write!(f, "{}", err),
+            ImageError::WrongSize { width, height, found } => write!(f, "expected {} bytes for {}x{} RGBA pixels, found {}", *width as usize * *height as usize * 4, width, height, found),
+            ImageError::Empty => write!(f, "image has no pixels"),
+            ImageError::TooLarge { width, height } => write!(f, "{}x{} image doesn't fit in a {}x{} atlas", width, height, ATLAS_SIZE, ATLAS_SIZE),
+            ImageError::AtlasFull { width, height } => write!(f, "no room left in the atlas for a {}x{} image", width, height),
+        }
+    }
+}
+impl Error for ImageError {}
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        ImageError::Decode(err)
+    }
+}
+
+/// Decodes a PNG of any color type and bit depth into rows of 8 bit RGBA pixels from the top left,
+/// returned with its width and height.
+pub fn decode_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), ImageError> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    // palettes and low bit depths are expanded, 16 bit channels cut to 8
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    pixels.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        // expanded into rgb or rgba by the transformations
+        png::ColorType::Indexed => unreachable!(),
+    };
+    return Ok((info.width, info.height, rgba));
+}
+
+// the image with PADDING copies of its edge pixels all around it
+pub(super) fn extrude(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let (padded_width, padded_height) = (width + PADDING * 2, height + PADDING * 2);
+    let mut padded = Vec::with_capacity((padded_width * padded_height * 4) as usize);
+    for y in 0..padded_height {
+        let y = y.saturating_sub(PADDING).min(height - 1);
+        for x in 0..padded_width {
+            let x = x.saturating_sub(PADDING).min(width - 1);
+            let i = ((y * width + x) * 4) as usize;
+            padded.extend_from_slice(&rgba[i..i + 4]);
+        }
+    }
+    return padded;
+}
+
+// a row of images as high as the highest of them, filled left to right
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    width: u32,
+}
+
+/// Where images are packed into one texture, in rows from the top left, so sprites of any of them are drawn together.
+#[derive(Debug, Default)]
+pub(super) struct Atlas {
+    shelves: Vec<Shelf>,
+    // x, y, width and height of every image without its padding, by ImageId
+    images: Vec<[u32;4]>,
+}
+impl Atlas {
+    /// Makes room for an image, returning its id and where its padded pixels go in the atlas.
+    pub fn insert(&mut self, width: u32, height: u32) -> Result<(ImageId, [u32;2]), ImageError> {
+        if width == 0 || height == 0 {
+            return Err(ImageError::Empty);
+        }
+        let (padded_width, padded_height) = (width + PADDING * 2, height + PADDING * 2);
+        if padded_width > ATLAS_SIZE || padded_height > ATLAS_SIZE {
+            return Err(ImageError::TooLarge { width, height });
+        }
+
+        // the lowest shelf with room for it, so tall shelves are left for tall images
+        let shelf = self.shelves.iter_mut()
+            .filter(|shelf| shelf.height >= padded_height && ATLAS_SIZE - shelf.width >= padded_width)
+            .min_by_key(|shelf| shelf.height);
+        let position = match shelf {
+            Some(shelf) => {
+                shelf.width += padded_width;
+                [shelf.width - padded_width, shelf.y]
+            },
+            None => {
+                let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if y + padded_height > ATLAS_SIZE {
+                    return Err(ImageError::AtlasFull { width, height });
+                }
+                self.shelves.push(Shelf { y, height: padded_height, width: padded_width });
+                [0, y]
+            },
+        };
+
+        self.images.push([position[0] + PADDING, position[1] + PADDING, width, height]);
+        return Ok((ImageId(self.images.len() - 1), position));
+    }
+
+    pub fn size(&self, id: ImageId) -> Option<[u32;2]> {
+        self.images.get(id.0).map(|image| [image[2], image[3]])
+    }
+
+    /// Where a part of an image is in the atlas's texture coordinates, `uv` going from 0 at the image's
+    /// top left corner to 1 at its bottom right one.
+    pub fn uv(&self, id: ImageId, uv: [[f32;2];2]) -> Option<[[f32;2];2]> {
+        let [x, y, width, height] = self.images.get(id.0)?.map(|n| n as f32);
+        let size = ATLAS_SIZE as f32;
+        return Some(uv.map(|[u, v]| [(x + u * width) / size, (y + v * height) / size]));
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----- ATLAS TESTS -----
+    #[test]
+    fn test_atlas_insert() {
+        let mut atlas = Atlas::default();
+        let (a, position_a) = atlas.insert(30, 10).unwrap();
+        let (b, position_b) = atlas.insert(30, 20).unwrap();
+        let (c, position_c) = atlas.insert(30, 8).unwrap();
+        assert_eq!(position_a, [0, 0]);
+        assert_eq!(position_b, [0, 12], "ERROR: a taller image should start a new shelf");
+        assert_eq!(position_c, [32, 0], "ERROR: a smaller image should go in the lowest shelf it fits in");
+
+        assert_eq!(atlas.size(b), Some([30, 20]));
+        assert_eq!(atlas.uv(a, [[0., 0.], [1., 1.]]), Some([[1. / 1024., 1. / 1024.], [31. / 1024., 11. / 1024.]]));
+        assert_eq!(atlas.uv(c, [[0.5, 0.], [1., 0.5]]), Some([[48. / 1024., 1. / 1024.], [63. / 1024., 5. / 1024.]]));
+        assert_eq!(atlas.uv(ImageId(3), [[0., 0.], [1., 1.]]), None);
+    }
+
+    #[test]
+    fn test_atlas_full() {
+        let mut atlas = Atlas::default();
+        assert!(matches!(atlas.insert(0, 10), Err(ImageError::Empty)));
+        assert!(matches!(atlas.insert(ATLAS_SIZE, 10), Err(ImageError::TooLarge { .. })), "ERROR: the padding should count towards the size");
+        atlas.insert(ATLAS_SIZE - 2, ATLAS_SIZE - 2).unwrap();
+        assert!(matches!(atlas.insert(1, 1), Err(ImageError::AtlasFull { width: 1, height: 1 })));
+    }
+
+    #[test]
+    fn test_extrude() {
+        let rgba = [1, 1, 1, 1, 2, 2, 2, 2];
+        let padded = extrude(2, 1, &rgba);
+        assert_eq!(padded.len(), 4 * 3 * 4);
+        let row: Vec<u8> = padded[..16].chunks(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(row, [1, 1, 2, 2]);
+        assert!(padded.chunks(16).all(|other| other == &padded[..16]), "ERROR: the edge rows should be copied above and below");
+    }
+
+    // ----- PNG TESTS -----
+    #[test]
+    fn test_decode_png() {
+        let (width, height, rgba) = decode_png(&encode_png(2, 1, png::ColorType::Rgba, &[255, 0, 0, 255, 0, 0, 255, 128])).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(rgba, [255, 0, 0, 255, 0, 0, 255, 128]);
+
+        let (_, _, rgba) = decode_png(&encode_png(1, 2, png::ColorType::Rgb, &[0, 255, 0, 1, 2, 3])).unwrap();
+        assert_eq!(rgba, [0, 255, 0, 255, 1, 2, 3, 255], "ERROR: rgb pixels should be opaque");
+        let (_, _, rgba) = decode_png(&encode_png(2, 1, png::ColorType::GrayscaleAlpha, &[10, 20, 30, 40])).unwrap();
+        assert_eq!(rgba, [10, 10, 10, 20, 30, 30, 30, 40]);
+        let (_, _, rgba) = decode_png(&encode_png(1, 1, png::ColorType::Grayscale, &[7])).unwrap();
+        assert_eq!(rgba, [7, 7, 7, 255]);
+
+        assert!(matches!(decode_png(b"not a png"), Err(ImageError::Decode(_))));
+    }
+
+    // ----- HELPER FUNCTIONS -----
+    fn encode_png(width: u32, height: u32, color_type: png::ColorType, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+        return bytes;
+    }
+}
@@ -0,0 +1,359 @@
+use std::time::Duration;
+
+use crate::engine::grid::Coord;
+
+use super::Point;
+
+/// How long pans and zooms started by `pan_to`, `zoom_to` and `animate_to` take, unless given otherwise.
+pub const DEFAULT_ANIMATION: Duration = Duration::from_millis(150);
+// keeps the view from collapsing, which would make the projection divide by zero
+const MIN_VIEW_SIZE: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Animation {
+    from_position: Point,
+    from_view_size: [f32;2],
+    to_position: Point,
+    to_view_size: [f32;2],
+    elapsed: Duration,
+    duration: Duration,
+}
+
+/// Looks at the world from `position`, showing `view_size` world units across the screen.
+///
+/// Grid tiles are one world unit large and centered on their coord, so a camera at a coord showing
+/// `[7., 5.]` shows the 7 by 5 tiles around it. A camera at the origin showing `[2., 2.]` maps world
+/// space straight to clip space, which is what `Camera::new` starts with.
+///
+/// Shapes the Renderer draws in world space are moved by the camera on the gpu through `view_proj`,
+/// so they can stay the same while the view pans and zooms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    position: Point,
+    view_size: [f32;2],
+    viewport: winit::dpi::PhysicalSize<u32>,
+    animation: Option<Animation>,
+}
+impl Camera {
+    pub fn new(viewport: winit::dpi::PhysicalSize<u32>) -> Self {
+        Camera {
+            position: Point::ZERO,
+            view_size: [2., 2.],
+            viewport,
+            animation: None,
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    pub fn view_size(&self) -> [f32;2] {
+        self.view_size
+    }
+
+    /// How many times larger the world is drawn than in a camera showing `[2., 2.]`, going by the height of the view.
+    pub fn zoom(&self) -> f32 {
+        2. / self.view_size[1]
+    }
+
+    /// The size of the screen in pixels, used to convert screen positions.
+    pub fn viewport(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.viewport
+    }
+
+    pub fn set_viewport(&mut self, viewport: winit::dpi::PhysicalSize<u32>) {
+        self.viewport = viewport;
+    }
+
+    /// Moves the camera right away, stopping any animation.
+    pub fn set_position<P: Into<Point>>(&mut self, position: P) {
+        let position: Point = position.into();
+        self.position = Point { z: 0., ..position };
+        self.animation = None;
+    }
+
+    /// Zooms the camera right away, stopping any animation.
+    pub fn set_view_size(&mut self, view_size: [f32;2]) {
+        self.view_size = clamp_view_size(view_size);
+        self.animation = None;
+    }
+
+    /// Where the camera is heading, its position if it isn't animating.
+    pub fn target_position(&self) -> Point {
+        self.animation.map_or(self.position, |animation| animation.to_position)
+    }
+
+    /// The view size the camera is heading to, its view size if it isn't animating.
+    pub fn target_view_size(&self) -> [f32;2] {
+        self.animation.map_or(self.view_size, |animation| animation.to_view_size)
+    }
+
+    /// Smoothly moves and zooms the camera over `duration`, starting from wherever it is now,
+    /// so a new target can be given before the last one was reached. Played by `update`.
+    pub fn animate_to<P: Into<Point>>(&mut self, position: P, view_size: [f32;2], duration: Duration) {
+        let position: Point = position.into();
+        self.animation = Some(Animation {
+            from_position: self.position,
+            from_view_size: self.view_size,
+            to_position: Point { z: 0., ..position },
+            to_view_size: clamp_view_size(view_size),
+            elapsed: Duration::ZERO,
+            duration,
+        });
+        // a zero duration gets there right away
+        self.update(Duration::ZERO);
+    }
+
+    pub fn pan_to<P: Into<Point>>(&mut self, position: P, duration: Duration) {
+        self.animate_to(position, self.target_view_size(), duration);
+    }
+
+    pub fn zoom_to(&mut self, view_size: [f32;2], duration: Duration) {
+        self.animate_to(self.target_position(), view_size, duration);
+    }
+
+    /// Zooms in by `factor`, or out for factors below 1.
+    pub fn zoom_by(&mut self, factor: f32, duration: Duration) {
+        let view_size = self.target_view_size();
+        self.zoom_to([view_size[0] / factor, view_size[1] / factor], duration);
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Plays the animation `elapsed` further, returns whether the camera moved.
+    pub fn update(&mut self, elapsed: Duration) -> bool {
+        let Some(mut animation) = self.animation else {
+            return false;
+        };
+        animation.elapsed = animation.elapsed.saturating_add(elapsed);
+
+        let t = match animation.duration.is_zero() {
+            true => 1.,
+            false => (animation.elapsed.as_secs_f32() / animation.duration.as_secs_f32()).min(1.),
+        };
+        // eases in and out, so pans don't start or stop with a jerk
+        let t = t * t * (3. - 2. * t);
+
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        self.position = Point {
+            x: lerp(animation.from_position.x, animation.to_position.x),
+            y: lerp(animation.from_position.y, animation.to_position.y),
+            z: 0.,
+        };
+        // zooming through the sizes' logarithm keeps the speed of a zoom even however far out it is
+        let log_lerp = |from: f32, to: f32| (from.ln() + (to.ln() - from.ln()) * t).exp();
+        self.view_size = [
+            log_lerp(animation.from_view_size[0], animation.to_view_size[0]),
+            log_lerp(animation.from_view_size[1], animation.to_view_size[1]),
+        ];
+
+        self.animation = match animation.elapsed >= animation.duration {
+            true => {
+                self.position = animation.to_position;
+                self.view_size = animation.to_view_size;
+                None
+            },
+            false => Some(animation),
+        };
+        return true;
+    }
+
+    /// The matrix the shader moves world space vertices into clip space with, column by column.
+    /// Depth is left as is.
+    pub fn view_proj(&self) -> [[f32;4];4] {
+        let sx = 2. / self.view_size[0];
+        let sy = 2. / self.view_size[1];
+        return [
+            [sx, 0., 0., 0.],
+            [0., sy, 0., 0.],
+            [0., 0., 1., 0.],
+            [-self.position.x * sx, -self.position.y * sy, 0., 1.],
+        ];
+    }
+
+    pub fn world_to_clip<P: Into<Point>>(&self, world: P) -> Point {
+        let world: Point = world.into();
+        Point {
+            x: (world.x - self.position.x) * 2. / self.view_size[0],
+            y: (world.y - self.position.y) * 2. / self.view_size[1],
+            z: world.z,
+        }
+    }
+
+    pub fn clip_to_world<P: Into<Point>>(&self, clip: P) -> Point {
+        let clip: Point = clip.into();
+        Point {
+            x: clip.x * self.view_size[0] / 2. + self.position.x,
+            y: clip.y * self.view_size[1] / 2. + self.position.y,
+            z: clip.z,
+        }
+    }
+
+    /// Converts a position in pixels from the top left of the window, like winit's cursor positions, to clip space.
+    pub fn screen_to_clip(&self, screen: [f64;2]) -> Point {
+        Point {
+            x: (screen[0] / (self.viewport.width as f64 / 2.) - 1.) as f32,
+            y: (-screen[1] / (self.viewport.height as f64 / 2.) + 1.) as f32,
+            z: 0.,
+        }
+    }
+
+    pub fn clip_to_screen<P: Into<Point>>(&self, clip: P) -> [f64;2] {
+        let clip: Point = clip.into();
+        [
+            (clip.x as f64 + 1.) * (self.viewport.width as f64 / 2.),
+            (1. - clip.y as f64) * (self.viewport.height as f64 / 2.),
+        ]
+    }
+
+    pub fn screen_to_world(&self, screen: [f64;2]) -> Point {
+        self.clip_to_world(self.screen_to_clip(screen))
+    }
+
+    pub fn world_to_screen<P: Into<Point>>(&self, world: P) -> [f64;2] {
+        self.clip_to_screen(self.world_to_clip(world))
+    }
+
+    /// The coord of the tile covering `world`, saturating at the edges of the grid.
+    pub fn world_to_grid<P: Into<Point>>(&self, world: P) -> Coord {
+        let world: Point = world.into();
+        // float to int casts saturate
+        Coord::new(world.x.round() as i32, world.y.round() as i32)
+    }
+
+    /// The center of the tile at `coord`.
+    pub fn grid_to_world(&self, coord: Coord) -> Point {
+        Point { x: coord.x() as f32, y: coord.y() as f32, z: 0. }
+    }
+
+    pub fn screen_to_grid(&self, screen: [f64;2]) -> Coord {
+        self.world_to_grid(self.screen_to_world(screen))
+    }
+
+    /// The top left and bottom right corners of what the camera sees, in world space.
+    pub fn visible_rect(&self) -> [Point;2] {
+        [self.clip_to_world([-1., 1.]), self.clip_to_world([1., -1.])]
+    }
+
+    /// The bottom left and top right coords of the tiles the camera sees, even partly.
+    pub fn visible_tiles(&self) -> (Coord, Coord) {
+        let [top_left, bottom_right] = self.visible_rect();
+        (self.world_to_grid([top_left.x, bottom_right.y]), self.world_to_grid([bottom_right.x, top_left.y]))
+    }
+}
+
+fn clamp_view_size(view_size: [f32;2]) -> [f32;2] {
+    view_size.map(|size| match size.is_finite() {
+        true => size.max(MIN_VIEW_SIZE),
+        false => 2.,
+    })
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----- HELPER FUNCTIONS -----
+    fn camera() -> Camera {
+        let mut camera = Camera::new(winit::dpi::PhysicalSize::new(800, 400));
+        camera.set_position([10., -4.]);
+        camera.set_view_size([8., 4.]);
+        camera
+    }
+
+    fn assert_close(a: Point, b: Point) {
+        assert!((a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4, "ERROR: {:?} should be close to {:?}", a, b);
+    }
+
+    // ----- CONVERSION TESTS -----
+    #[test]
+    fn test_camera_identity() {
+        let camera = Camera::new(winit::dpi::PhysicalSize::new(100, 100));
+        assert_close(camera.world_to_clip([0.3, -0.7]), [0.3, -0.7].into());
+        assert_eq!(camera.view_proj(), [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., 1., 0.], [0., 0., 0., 1.]]);
+    }
+
+    #[test]
+    fn test_camera_world_clip() {
+        let camera = camera();
+        assert_close(camera.world_to_clip([10., -4.]), Point::ZERO);
+        assert_close(camera.world_to_clip([14., -2.]), [1., 1.].into());
+        assert_close(camera.clip_to_world([-1., -1.]), [6., -6.].into());
+
+        // the matrix does the same as world_to_clip
+        let m = camera.view_proj();
+        let world = Point { x: 12., y: -5., z: 0.5 };
+        let clip = Point {
+            x: m[0][0] * world.x + m[1][0] * world.y + m[2][0] * world.z + m[3][0],
+            y: m[0][1] * world.x + m[1][1] * world.y + m[2][1] * world.z + m[3][1],
+            z: m[0][2] * world.x + m[1][2] * world.y + m[2][2] * world.z + m[3][2],
+        };
+        assert_close(clip, camera.world_to_clip(world));
+        assert_eq!(clip.z, 0.5, "ERROR: the camera shouldn't change depth");
+    }
+
+    #[test]
+    fn test_camera_screen() {
+        let camera = camera();
+        assert_close(camera.screen_to_clip([0., 0.]), [-1., 1.].into());
+        assert_close(camera.screen_to_clip([400., 200.]), Point::ZERO);
+        assert_eq!(camera.clip_to_screen([1., -1.]), [800., 400.]);
+        assert_close(camera.screen_to_world([800., 0.]), [14., -2.].into());
+        assert_eq!(camera.world_to_screen([10., -4.]), [400., 200.]);
+    }
+
+    #[test]
+    fn test_camera_grid() {
+        let camera = camera();
+        assert_eq!(camera.world_to_grid([10.4, -4.6]), Coord::new(10, -5));
+        assert_eq!(camera.grid_to_world(Coord::new(3, 2)), [3., 2.].into());
+        assert_eq!(camera.screen_to_grid([400., 200.]), Coord::new(10, -4));
+        assert_eq!(camera.world_to_grid([f32::MAX, f32::MIN]), Coord::new(i32::MAX, i32::MIN));
+
+        assert_eq!(camera.visible_tiles(), (Coord::new(6, -6), Coord::new(14, -2)));
+    }
+
+    // ----- ANIMATION TESTS -----
+    #[test]
+    fn test_camera_animation() {
+        let mut camera = camera();
+        camera.pan_to([20., -4.], Duration::from_millis(100));
+        assert!(camera.is_animating());
+        assert_eq!(camera.position(), [10., -4.].into());
+        assert_eq!(camera.target_position(), [20., -4.].into());
+
+        assert!(camera.update(Duration::from_millis(50)));
+        assert_close(camera.position(), [15., -4.].into());
+        // eased, so the first quarter covers less than a quarter of the way
+        let mut eased = camera.clone();
+        eased.pan_to([30., -4.], Duration::from_millis(100));
+        eased.update(Duration::from_millis(25));
+        assert!(eased.position().x - 15. < 15. / 4.);
+
+        assert!(camera.update(Duration::from_millis(60)));
+        assert_eq!(camera.position(), [20., -4.].into());
+        assert!(!camera.is_animating());
+        assert!(!camera.update(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn test_camera_zoom() {
+        let mut camera = camera();
+        camera.zoom_by(2., Duration::ZERO);
+        assert_eq!(camera.view_size(), [4., 2.]);
+        assert_eq!(camera.zoom(), 1.);
+
+        // halfway through a zoom the view is halfway in scale, not in size
+        camera.zoom_to([16., 8.], Duration::from_millis(100));
+        camera.update(Duration::from_millis(50));
+        assert!((camera.view_size()[1] - 4.).abs() < 1e-4);
+
+        camera.set_view_size([0., f32::NAN]);
+        assert_eq!(camera.view_size(), [MIN_VIEW_SIZE, 2.]);
+        assert!(!camera.is_animating());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use crate::engine::grid::{ Coord, Grid, Tile, CHUNK_SIZE };
+
+use super::{ Mesh, MeshId, Renderer };
+
+/// Keeps the meshes ChunkMeshes tessellates on the gpu, a Renderer for the engine's own meshes.
+pub trait MeshStore {
+    /// What the tiles of a chunk are tessellated into.
+    type Mesh: Default;
+    type Id: Copy;
+
+    fn create_mesh(&mut self, mesh: &Self::Mesh) -> Self::Id;
+    fn update_mesh(&mut self, id: Self::Id, mesh: &Self::Mesh);
+    fn remove_mesh(&mut self, id: Self::Id);
+    /// Draws the mesh this frame.
+    fn draw_mesh(&mut self, id: Self::Id);
+}
+impl MeshStore for Renderer {
+    type Mesh = Mesh;
+    type Id = MeshId;
+
+    fn create_mesh(&mut self, mesh: &Mesh) -> MeshId {
+        return Renderer::create_mesh(self, mesh);
+    }
+
+    fn update_mesh(&mut self, id: MeshId, mesh: &Mesh) {
+        Renderer::update_mesh(self, id, mesh);
+    }
+
+    fn remove_mesh(&mut self, id: MeshId) {
+        Renderer::remove_mesh(self, id);
+    }
+
+    fn draw_mesh(&mut self, id: MeshId) {
+        Renderer::draw_mesh(self, id);
+    }
+}
+
+// the retained mesh of one chunk and the version of the chunk it was tessellated from
+struct ChunkMesh<I> {
+    id: I,
+    version: Option<u64>,
+}
+
+/// Retained meshes for the chunks of a grid, so drawing it every frame only tessellates and uploads
+/// the chunks that changed since the last frame instead of every visible tile.
+///
+/// Chunks are rebuilt when their `Grid::chunk_version` moves on. Anything else the tiles are drawn from,
+/// like their size or colors, isn't tracked, call `invalidate` or `invalidate_at` when it changes.
+pub struct ChunkMeshes<S: MeshStore = Renderer> {
+    chunks: HashMap<Coord, ChunkMesh<S::Id>>,
+}
+impl<S: MeshStore> Default for ChunkMeshes<S> {
+    fn default() -> Self {
+        ChunkMeshes::new()
+    }
+}
+impl<S: MeshStore> ChunkMeshes<S> {
+    pub fn new() -> Self {
+        ChunkMeshes { chunks: HashMap::new() }
+    }
+
+    /// Has every chunk tessellated again the next time it's drawn.
+    pub fn invalidate(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            chunk.version = None;
+        }
+    }
+
+    /// Has the chunk holding a coord tessellated again the next time it's drawn.
+    pub fn invalidate_at<C: Into<Coord>>(&mut self, coord_like: C) {
+        if let Some(chunk) = self.chunks.get_mut(&Grid::chunk_origin(coord_like)) {
+            chunk.version = None;
+        }
+    }
+
+    /// Draws the chunks overlapping the rectangle between two corners, both included, in the renderer's current space.
+    /// Chunks that changed since they were last drawn are tessellated again first, by calling `tessellate`
+    /// for each of their tiles. Returns how many chunks were tessellated.
+    pub fn draw<C, F>(&mut self, renderer: &mut S, grid: &Grid, corner_a: C, corner_b: C, mut tessellate: F) -> usize
+    where
+        C: Into<Coord>,
+        F: FnMut(&mut S::Mesh, Coord, &Tile),
+    {
+        let mut tessellated = 0;
+
+        for origin in grid.chunks_in(corner_a, corner_b) {
+            let version = grid.chunk_version(origin);
+            let cached = self.chunks.get(&origin).filter(|chunk| chunk.version.is_some() && chunk.version == version);
+
+            let id = match cached {
+                Some(chunk) => chunk.id,
+                None => {
+                    let mut mesh = S::Mesh::default();
+                    let far_corner = origin + Coord::new(CHUNK_SIZE - 1, CHUNK_SIZE - 1);
+                    for (coord, tile) in grid.iter_region(origin, far_corner) {
+                        tessellate(&mut mesh, coord, tile);
+                    }
+                    tessellated += 1;
+
+                    match self.chunks.get_mut(&origin) {
+                        Some(chunk) => {
+                            renderer.update_mesh(chunk.id, &mesh);
+                            chunk.version = version;
+                            chunk.id
+                        },
+                        None => {
+                            let id = renderer.create_mesh(&mesh);
+                            self.chunks.insert(origin, ChunkMesh { id, version });
+                            id
+                        },
+                    }
+                },
+            };
+            renderer.draw_mesh(id);
+        }
+
+        return tessellated;
+    }
+
+    /// Frees the meshes of every chunk, for when the grid is replaced by another one.
+    pub fn clear(&mut self, renderer: &mut S) {
+        for (_, chunk) in self.chunks.drain() {
+            renderer.remove_mesh(chunk.id);
+        }
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::draw::Color;
+    use crate::engine::draw::snapshot::headless;
+
+    // ----- HELPER FUNCTIONS -----
+    fn tessellate(mesh: &mut Mesh, coord: Coord, tile: &Tile) {
+        let color = match tile {
+            Tile::Ground => Color::RED,
+            _ => Color::GREEN,
+        };
+        let (x, y) = (coord.x() as f32, coord.y() as f32);
+        mesh.draw_rect([[x - 0.5, y + 0.5], [x + 0.5, y - 0.5]], color);
+    }
+
+    #[test]
+    fn test_chunk_meshes_rebuild() {
+        let Some(mut renderer) = headless(32, 32) else { return };
+        let mut grid = Grid::new(vec![((0, 0), Tile::Ground), ((-1, 0), Tile::Ground), ((100, 100), Tile::Ground)]);
+        let mut chunks = ChunkMeshes::new();
+        let draw = |renderer: &mut Renderer, grid: &Grid, chunks: &mut ChunkMeshes| {
+            chunks.draw(renderer, grid, (-1, -1), (1, 1), tessellate)
+        };
+
+        assert_eq!(draw(&mut renderer, &grid, &mut chunks), 2, "ERROR: only the visible chunks should be tessellated");
+        assert_eq!(renderer.mesh_count(), 2);
+        renderer.render().unwrap();
+
+        assert_eq!(draw(&mut renderer, &grid, &mut chunks), 0, "ERROR: unchanged chunks were tessellated again");
+
+        grid.insert((1, 1), Tile::Air);
+        assert_eq!(draw(&mut renderer, &grid, &mut chunks), 1, "ERROR: only the changed chunk should be tessellated");
+
+        chunks.invalidate_at((-1, 0));
+        assert_eq!(draw(&mut renderer, &grid, &mut chunks), 1);
+        chunks.invalidate();
+        assert_eq!(draw(&mut renderer, &grid, &mut chunks), 2);
+
+        chunks.clear(&mut renderer);
+        assert_eq!(renderer.mesh_count(), 0);
+    }
+
+    #[test]
+    fn test_chunk_meshes_draw() {
+        let Some(mut renderer) = headless(40, 40) else { return };
+        let mut grid = Grid::new(vec![((0, 0), Tile::Ground), ((1, 0), Tile::Ground)]);
+        let mut chunks = ChunkMeshes::new();
+        renderer.camera.set_position([0.5, 0.]);
+        renderer.set_world_space(true);
+
+        chunks.draw(&mut renderer, &grid, (-1, -1), (1, 1), tessellate);
+        renderer.render().unwrap();
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(10, 20), [255, 0, 0, 255]);
+        assert_eq!(frame.pixel(30, 20), [255, 0, 0, 255]);
+
+        grid.insert((1, 0), Tile::Air);
+        chunks.draw(&mut renderer, &grid, (-1, -1), (1, 1), tessellate);
+        renderer.render().unwrap();
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!(frame.pixel(10, 20), [255, 0, 0, 255]);
+        assert_eq!(frame.pixel(30, 20), [0, 255, 0, 255], "ERROR: changed tile wasn't drawn again");
+    }
+}
@@ -0,0 +1,100 @@
+use super::{ Color, Point, Vertex };
+
+// what the fragment shaders fill with, the FILL_ constants in fill.wgsl
+const SOLID: u32 = 0;
+const LINEAR: u32 = 1;
+const RADIAL: u32 = 2;
+const HATCH: u32 = 3;
+const CROSSHATCH: u32 = 4;
+
+/// What the inside of shapes is painted with, see `Mesh::with_fill`. Gradients and hatches are worked out
+/// for every pixel on the gpu, in the space the shapes are drawn in, so they stay sharp at any zoom.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fill {
+    Solid(Color),
+    /// From `colors[0]` at `start` to `colors[1]` at `end`, keeping the nearest one's color past them.
+    Linear { start: Point, end: Point, colors: [Color;2] },
+    /// From `colors[0]` at `center` to `colors[1]` at `radius` from it and further out.
+    Radial { center: Point, radius: f32, colors: [Color;2] },
+    /// Lines `width` thick and `spacing` apart at `angle` radians from the x axis, in `colors[0]` over `colors[1]`.
+    /// Crossed hatches have lines at a right angle to those too.
+    Hatch { angle: f32, spacing: f32, width: f32, crossed: bool, colors: [Color;2] },
+}
+impl Fill {
+    pub fn linear<C: Into<Color>, P: Into<Point>>(start: P, end: P, from: C, to: C) -> Self {
+        Fill::Linear { start: start.into(), end: end.into(), colors: [from.into(), to.into()] }
+    }
+
+    pub fn radial<C: Into<Color>, P: Into<Point>>(center: P, radius: f32, from: C, to: C) -> Self {
+        Fill::Radial { center: center.into(), radius, colors: [from.into(), to.into()] }
+    }
+
+    /// Like the lines of `draw_lined_box`, at any angle and for shapes of any size.
+    pub fn hatch<C: Into<Color>>(angle: f32, spacing: f32, width: f32, lines: C, background: C) -> Self {
+        Fill::Hatch { angle, spacing, width, crossed: false, colors: [lines.into(), background.into()] }
+    }
+
+    /// Like the lines of `draw_crossed_box`, at any angle and for shapes of any size.
+    pub fn crosshatch<C: Into<Color>>(angle: f32, spacing: f32, width: f32, lines: C, background: C) -> Self {
+        Fill::Hatch { angle, spacing, width, crossed: true, colors: [lines.into(), background.into()] }
+    }
+
+    /// The same fill with the alpha of its colors multiplied by `opacity`.
+    pub fn with_opacity(self, opacity: f32) -> Self {
+        let fade = |colors: [Color;2]| colors.map(|color| color.with_alpha(color.a * opacity));
+        match self {
+            Fill::Solid(color) => Fill::Solid(color.with_alpha(color.a * opacity)),
+            Fill::Linear { start, end, colors } => Fill::Linear { start, end, colors: fade(colors) },
+            Fill::Radial { center, radius, colors } => Fill::Radial { center, radius, colors: fade(colors) },
+            Fill::Hatch { angle, spacing, width, crossed, colors } => Fill::Hatch { angle, spacing, width, crossed, colors: fade(colors) },
+        }
+    }
+
+    // what the shaders need to fill a vertex's shape, packed into the vertex
+    pub(super) fn apply(&self, vertex: &mut Vertex) {
+        let (kind, colors, params) = match *self {
+            Fill::Solid(color) => (SOLID, [color, color], [0.;4]),
+            Fill::Linear { start, end, colors } => (LINEAR, colors, [start.x, start.y, end.x, end.y]),
+            Fill::Radial { center, radius, colors } => (RADIAL, colors, [center.x, center.y, radius, 0.]),
+            Fill::Hatch { angle, spacing, width, crossed, colors } => {
+                let kind = if crossed { CROSSHATCH } else { HATCH };
+                (kind, colors, [angle.cos(), angle.sin(), spacing, width])
+            },
+        };
+        vertex.color = colors[0].into();
+        vertex.fill_color = colors[1].into();
+        vertex.fill = params;
+        vertex.fill_kind = kind;
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_apply() {
+        let mut vertex = Vertex::new([0., 0., 0.], Color::WHITE.into());
+        assert_eq!(vertex.fill_kind, SOLID, "ERROR: vertices should be filled with their own color by default");
+
+        Fill::linear([0., 0.], [1., 2.], Color::RED, Color::BLUE).apply(&mut vertex);
+        assert_eq!((vertex.fill_kind, vertex.fill), (LINEAR, [0., 0., 1., 2.]));
+        assert_eq!((vertex.color, vertex.fill_color), ([1., 0., 0., 1.], [0., 0., 1., 1.]));
+
+        Fill::crosshatch(0., 0.2, 0.05, Color::GREEN, Color::BLACK).apply(&mut vertex);
+        assert_eq!((vertex.fill_kind, vertex.fill), (CROSSHATCH, [1., 0., 0.2, 0.05]));
+
+        Fill::Solid(Color::RED).apply(&mut vertex);
+        assert_eq!((vertex.fill_kind, vertex.color), (SOLID, [1., 0., 0., 1.]));
+    }
+
+    #[test]
+    fn test_fill_opacity() {
+        assert_eq!(Fill::Solid(Color::RED).with_opacity(0.5), Fill::Solid(Color::RED.with_alpha(0.5)));
+        let Fill::Radial { colors, .. } = Fill::radial([0., 0.], 1., Color::RED.with_alpha(0.5), Color::BLUE).with_opacity(0.5) else {
+            panic!("ERROR: opacity changed the kind of fill");
+        };
+        assert_eq!(colors, [Color::RED.with_alpha(0.25), Color::BLUE.with_alpha(0.5)]);
+    }
+}
@@ -0,0 +1,79 @@
+use super::Point;
+
+/// What a Renderer draws into, see `Renderer::set_layer`. Layers are drawn in the order they're declared in, each on
+/// top of everything in the ones before it whatever its depth, so z only orders what is drawn within a layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Layer {
+    /// The grid and everything else in the game's world, tiles are always drawn in it.
+    #[default]
+    World,
+    /// Highlights, previews of buildings to be placed and other things drawn over the world.
+    Overlay,
+    Ui,
+    Debug,
+}
+impl Layer {
+    /// Every layer, in the order they're drawn in.
+    pub const ALL: [Layer;4] = [Layer::World, Layer::Overlay, Layer::Ui, Layer::Debug];
+
+    pub(super) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The pixels of a target of `size` between two corners in clip space, as x, y, width and height from its top left,
+/// clamped to the target. Empty if none of them are in it.
+pub(super) fn scissor(corners: [Point;2], size: winit::dpi::PhysicalSize<u32>) -> [u32;4] {
+    let (width, height) = (size.width as f32, size.height as f32);
+    let to_pixels = |point: Point| [
+        ((point.x + 1.) / 2. * width).round().clamp(0., width),
+        ((1. - point.y) / 2. * height).round().clamp(0., height),
+    ];
+    let [ax, ay] = to_pixels(corners[0]);
+    let [bx, by] = to_pixels(corners[1]);
+    return [ax.min(bx), ay.min(by), (ax - bx).abs(), (ay - by).abs()].map(|n| n as u32);
+}
+
+/// The pixels in both of two scissors.
+pub(super) fn intersect(a: [u32;4], b: [u32;4]) -> [u32;4] {
+    let (x, y) = (a[0].max(b[0]), a[1].max(b[1]));
+    let right = (a[0] + a[2]).min(b[0] + b[2]);
+    let bottom = (a[1] + a[3]).min(b[1] + b[3]);
+    return [x, y, right.saturating_sub(x), bottom.saturating_sub(y)];
+}
+
+pub(super) fn is_empty(scissor: [u32;4]) -> bool {
+    scissor[2] == 0 || scissor[3] == 0
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_order() {
+        assert!(Layer::ALL.windows(2).all(|pair| pair[0] < pair[1]), "ERROR: layers should be listed in the order they're drawn in");
+        assert_eq!(Layer::ALL.map(Layer::index), [0, 1, 2, 3]);
+        assert_eq!(Layer::default(), Layer::World);
+    }
+
+    #[test]
+    fn test_scissor() {
+        let size = winit::dpi::PhysicalSize::new(200, 100);
+        assert_eq!(scissor([[-1., 1.].into(), [1., -1.].into()], size), [0, 0, 200, 100]);
+        // corners in any order, the top right quarter
+        assert_eq!(scissor([[1., 0.].into(), [0., 1.].into()], size), [100, 0, 100, 50]);
+        assert_eq!(scissor([[-2., 0.5].into(), [-0.5, -3.].into()], size), [0, 25, 50, 75], "ERROR: scissor should be clamped to the target");
+        assert!(is_empty(scissor([[0., 0.].into(), [0., 1.].into()], size)));
+        assert!(is_empty(scissor([[1.5, 1.].into(), [2., -1.].into()], size)), "ERROR: scissor outside of the target should be empty");
+    }
+
+    #[test]
+    fn test_intersect() {
+        assert_eq!(intersect([0, 0, 100, 50], [50, 25, 100, 100]), [50, 25, 50, 25]);
+        assert_eq!(intersect([10, 10, 20, 20], [0, 0, 100, 100]), [10, 10, 20, 20]);
+        assert!(is_empty(intersect([0, 0, 10, 10], [20, 0, 10, 10])), "ERROR: scissors that don't overlap should have an empty intersection");
+        assert!(is_empty(intersect([0, 0, 10, 10], [10, 0, 10, 10])));
+    }
+}
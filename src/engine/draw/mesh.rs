@@ -0,0 +1,412 @@
+use std::f32::consts::SQRT_2;
+use std::ops::Range;
+
+use super::{ path, stroke, Color, Fill, FillRule, Path, Point, Stroke, Vertex };
+
+/// Shapes tessellated into triangles, ready to be drawn by a Renderer.
+///
+/// The Renderer keeps one for the shapes drawn each frame, others can be uploaded once with
+/// `Renderer::create_mesh` and drawn every frame from then on without being tessellated or uploaded again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+impl Mesh {
+    pub fn new() -> Self {
+        Mesh::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    // the index the next vertex gets, a mesh can't hold more vertices than u32 indices reach
+    fn next_index(&self) -> u32 {
+        u32::try_from(self.vertices.len()).expect("ERROR: mesh has more vertices than u32 indices can reach")
+    }
+
+    /// Adds another mesh's shapes after this one's.
+    pub fn append(&mut self, other: &Mesh) {
+        let offset = self.next_index();
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend(other.indices.iter().map(|i| i + offset));
+    }
+
+    /// Fills the shapes `draw` draws with `fill` instead of their own colors, whatever shapes they are.
+    ///
+    /// e.g. `mesh.with_fill(Fill::hatch(PI / 4., 0.1, 0.02, Color::WHITE, Color::BLACK), |mesh| mesh.draw_circle([0., 0.], 0.5, Color::WHITE))`
+    pub fn with_fill<F: FnOnce(&mut Mesh)>(&mut self, fill: Fill, draw: F) {
+        let start = self.vertices.len();
+        draw(self);
+        self.fill_from(start, fill);
+    }
+
+    /// Multiplies the alpha of the shapes `draw` draws by `opacity`, whatever they are filled with.
+    pub fn with_opacity<F: FnOnce(&mut Mesh)>(&mut self, opacity: f32, draw: F) {
+        let start = self.vertices.len();
+        draw(self);
+        self.fade_from(start, opacity);
+    }
+
+    pub(super) fn fill_from(&mut self, start: usize, fill: Fill) {
+        for vertex in &mut self.vertices[start..] {
+            fill.apply(vertex);
+        }
+    }
+
+    pub(super) fn fade_from(&mut self, start: usize, opacity: f32) {
+        for vertex in &mut self.vertices[start..] {
+            vertex.color[3] *= opacity;
+            vertex.fill_color[3] *= opacity;
+        }
+    }
+
+    // stable sorts the triangles of a range of indices back to front by the depth of their frontmost corner, so translucent
+    // ones are blended over what is behind them even if that was drawn after them. Triangles at the same depth keep the order
+    // they were drawn in, and ranges without translucent triangles are left alone, the depth test orders those already
+    pub(super) fn sort_back_to_front(&mut self, range: Range<usize>) {
+        let vertices = &self.vertices;
+        let triangles = &self.indices[range.clone()];
+        let translucent = triangles.iter().any(|&i| vertices[i as usize].color[3] < 1. || vertices[i as usize].fill_color[3] < 1.);
+        let depth = |triangle: &[u32]| triangle.iter().map(|&i| vertices[i as usize].position[2]).fold(f32::MIN, f32::max);
+        if !translucent || triangles.chunks(3).map(depth).is_sorted_by(|a, b| a <= b) {
+            return;
+        }
+
+        let mut sorted: Vec<&[u32]> = triangles.chunks(3).collect();
+        sorted.sort_by(|a, b| depth(a).total_cmp(&depth(b)));
+        let sorted: Vec<u32> = sorted.concat();
+        self.indices[range].copy_from_slice(&sorted);
+    }
+
+    pub fn draw_triangle<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;3], color: C) {
+        let color: Color = color.into();
+        let color: [f32;4] = color.into();
+        let points: [Point;3] = points.map(|p| p.into());
+        let offset = self.next_index();
+
+        self.vertices.push(Vertex::new(points[0].into(), color));
+        self.vertices.push(Vertex::new(points[1].into(), color));
+        self.vertices.push(Vertex::new(points[2].into(), color));
+
+        self.indices.push(offset + 0);
+        self.indices.push(offset + 1);
+        self.indices.push(offset + 2);
+    }
+
+    pub fn draw_rect<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;2], color: C) {
+        let color: Color = color.into();
+        let color: [f32;4] = color.into();
+        let points: [Point;2] = points.map(|p| p.into());
+        let offset = self.next_index();
+
+        self.vertices.push(Vertex::new(points[0].into(), color));
+        self.vertices.push(Vertex::new([points[0].x, points[1].y, 0.], color));
+        self.vertices.push(Vertex::new([points[1].x, points[0].y, 0.], color));
+        self.vertices.push(Vertex::new(points[1].into(), color));
+
+        self.indices.push(offset + 0);
+        self.indices.push(offset + 1);
+        self.indices.push(offset + 2);
+
+        self.indices.push(offset + 2);
+        self.indices.push(offset + 1);
+        self.indices.push(offset + 3);
+    }
+
+    /// Fills a convex polygon, fanned out from its first point. Use `draw_polygon` for any other.
+    pub fn draw_poly<C: Into<Color>, P: Into<Point>>(&mut self, points: Vec<P>, color: C) {
+        let color: Color = color.into();
+        let color: [f32;4] = color.into();
+        let points: Vec<Point> = points.into_iter().map(|p| p.into()).collect();
+        let offset = self.next_index();
+
+        // add indices first because it is non-consuming
+        for i in 0..(points.len() - 2) as u32 {
+            self.indices.push(offset + 0);
+            self.indices.push(offset + i + 1);
+            self.indices.push(offset + i + 2);
+        }
+
+        for point in points {
+            self.vertices.push(Vertex::new(point.into(), color));
+        }
+    }
+
+    /// Fills a polygon of any shape, concave or crossing itself, with `rule` deciding which parts of it are inside.
+    pub fn draw_polygon<C: Into<Color>, P: Into<Point>>(&mut self, points: Vec<P>, rule: FillRule, color: C) {
+        let points: Vec<Point> = points.into_iter().map(|p| p.into()).collect();
+        path::fill_outlines(self, &[points], rule, color.into());
+    }
+
+    pub fn draw_circle<C: Into<Color>, P: Into<Point>>(&mut self, center: P, radius: f32, color: C) {
+        self.draw_ellipse(center, [radius, radius], color);
+    }
+
+    pub fn draw_ellipse<C: Into<Color>, P: Into<Point>>(&mut self, center: P, radii: [f32;2], color: C) {
+        let color: Color = color.into();
+        // ellipses are convex, so they don't need to go through fill_path
+        for (points, _) in Path::ellipse(center, radii).flatten() {
+            self.draw_poly(points, color);
+        }
+    }
+
+    /// Strokes the arc of a circle from the angle `angles[0]` to `angles[1]`, in radians counter clockwise from the positive x axis.
+    pub fn draw_arc<P: Into<Point>>(&mut self, center: P, radius: f32, angles: [f32;2], stroke: &Stroke) {
+        self.stroke_path(&Path::new().arc(center, [radius, radius], angles[0], angles[1]), stroke);
+    }
+
+    /// Fills every outline of a path, with `rule` deciding which parts of them are inside.
+    pub fn fill_path<C: Into<Color>>(&mut self, path: &Path, rule: FillRule, color: C) {
+        let outlines: Vec<Vec<Point>> = path.flatten().into_iter().map(|(points, _)| points).collect();
+        path::fill_outlines(self, &outlines, rule, color.into());
+    }
+
+    pub fn stroke_path(&mut self, path: &Path, stroke: &Stroke) {
+        for (points, closed) in path.flatten() {
+            stroke::stroke_path(self, &points, closed, stroke);
+        }
+    }
+
+    pub fn draw_line<C: Into<Color>, P: Into<Point>>(&mut self, points: [P;2], thickness: f32, color: C) {
+        let points: Vec<Point> = points.into_iter().map(|p| p.into()).collect();
+        stroke::stroke_path(self, &points, false, &Stroke::new(thickness, color));
+    }
+
+    /// Draws a stroke along the points, back to the first one too if `closed`, with the joins, caps and dashes of `stroke`.
+    pub fn draw_polyline<P: Into<Point>>(&mut self, points: Vec<P>, closed: bool, stroke: &Stroke) {
+        let points: Vec<Point> = points.into_iter().map(|p| p.into()).collect();
+        stroke::stroke_path(self, &points, closed, stroke);
+    }
+
+    pub fn draw_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C) {
+        let color: Color = color.into();
+        let color: [f32;4] = color.into();
+        let corners: [Point;2] = corners.map(|p| p.into());
+        let offset = self.next_index();
+
+        let vertices = vec![
+            corners[0],
+            Point::from([corners[0].x, corners[1].y]),
+            corners[1],
+            Point::from([corners[1].x, corners[0].y]),
+
+            corners[0] + Point::from([thickness, thickness * -1.]),
+            Point::from([corners[0].x, corners[1].y]).add_f32(thickness),
+            corners[1] + Point::from([thickness * -1., thickness]),
+            Point::from([corners[1].x, corners[0].y]).sub_f32(thickness),
+        ];
+        let mut vertices = vertices.into_iter().map(|p| Vertex::new(p.into(), color)).collect();
+        self.vertices.append(&mut vertices);
+
+        let indices = vec![
+            0, 1, 4,
+            1, 2, 5,
+            2, 3, 6,
+            3, 0, 7,
+
+            4, 1, 5,
+            5, 2, 6,
+            6, 3, 7,
+            7, 0, 4,
+        ];
+        let mut indices = indices.into_iter().map(|i| i + offset).collect();
+        self.indices.append(&mut indices);
+    }
+
+    pub fn draw_lined_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C, lines: u8, mirrored: bool) {
+        let color: Color = color.into();
+        let color: [f32;4] = color.into();
+        let corners: [Point;2] = corners.map(|p| p.into());
+        self.draw_box(corners, thickness, color);
+
+        let width = corners[1].x - corners[0].x;
+        let height = corners[0].y - corners[1].y;
+        let base_offset_x = (width * SQRT_2).powi(2) / (lines as f32 + 1.);
+        let base_offset_y = (height * SQRT_2).powi(2) / (lines as f32 + 1.);
+        let t = thickness / 2.;
+
+        let mut x = [0, 1];
+        let mut flip = 1.;
+
+        if mirrored {
+            x = [1, 0];
+            flip *= -1.;
+        }
+
+        for i in 0..lines {
+            let p;
+            if i + 1 <= (lines / 2) {
+                let offset_x = base_offset_x * (i + 1) as f32;
+                let offset_y = base_offset_y * (i + 1) as f32;
+                p = [
+                    [corners[x[0]].x + t * flip, corners[0].y + t - offset_y], 
+                    [corners[x[0]].x - t * flip + offset_x * flip, corners[0].y - t]
+                ];
+            } else {
+                let offset_x = base_offset_x * (i + 1 - lines / 2) as f32;
+                let offset_y = base_offset_y * (i + 1 - lines / 2) as f32;
+                p = [
+                    [corners[x[1]].x + t * flip - offset_x * flip, corners[1].y + t], 
+                    [corners[x[1]].x - t * flip, corners[1].y - t + offset_y]
+                ];
+            }
+            self.draw_poly([
+                [p[0][0] + t, p[0][1]], 
+                [p[0][0] - t, p[0][1]], 
+                [p[1][0] - t, p[1][1]], 
+                [p[1][0] + t, p[1][1]], 
+            ].into(), color);
+        }
+    }
+
+    pub fn draw_crossed_box<C: Into<Color>, P: Into<Point>>(&mut self, corners: [P;2], thickness: f32, color: C, lines: u8) {
+        let color: Color = color.into();
+        let color: [f32;4] = color.into();
+        let corners: [Point;2] = corners.map(|p| p.into());
+        self.draw_box(corners, thickness, color);
+
+        let width = corners[1].x - corners[0].x;
+        let height = corners[0].y - corners[1].y;
+        let base_offset_x = (width * SQRT_2).powi(2) / (lines as f32 + 1.);
+        let base_offset_y = (height * SQRT_2).powi(2) / (lines as f32 + 1.);
+        let t = thickness / 2.;
+
+        let mut x = [0, 1];
+        let mut flip = 1.;
+
+        for i in 0..lines {
+            let p;
+            if i + 1 <= (lines / 2) {
+                let offset_x = base_offset_x * (i + 1) as f32;
+                let offset_y = base_offset_y * (i + 1) as f32;
+                p = [
+                    [corners[x[0]].x + t * flip, corners[0].y + t - offset_y], 
+                    [corners[x[0]].x - t * flip + offset_x * flip, corners[0].y - t]
+                ];
+            } else {
+                let offset_x = base_offset_x * (i + 1 - lines / 2) as f32;
+                let offset_y = base_offset_y * (i + 1 - lines / 2) as f32;
+                p = [
+                    [corners[x[1]].x + t * flip - offset_x * flip, corners[1].y + t], 
+                    [corners[x[1]].x - t * flip, corners[1].y - t + offset_y]
+                ];
+            }
+            self.draw_poly([
+                [p[0][0] + t, p[0][1]], 
+                [p[0][0] - t, p[0][1]], 
+                [p[1][0] - t, p[1][1]], 
+                [p[1][0] + t, p[1][1]], 
+            ].into(), color);
+        }
+
+        x = [1, 0];
+        flip *= -1.;
+
+        for i in 0..lines {
+            let p;
+            if i + 1 <= (lines / 2) {
+                let offset_x = base_offset_x * (i + 1) as f32;
+                let offset_y = base_offset_y * (i + 1) as f32;
+                p = [
+                    [corners[x[0]].x + t * flip, corners[0].y + t - offset_y], 
+                    [corners[x[0]].x - t * flip + offset_x * flip, corners[0].y - t]
+                ];
+            } else {
+                let offset_x = base_offset_x * (i + 1 - lines / 2) as f32;
+                let offset_y = base_offset_y * (i + 1 - lines / 2) as f32;
+                p = [
+                    [corners[x[1]].x + t * flip - offset_x * flip, corners[1].y + t], 
+                    [corners[x[1]].x - t * flip, corners[1].y - t + offset_y]
+                ];
+            }
+            self.draw_poly([
+                [p[0][0] + t, p[0][1]], 
+                [p[0][0] - t, p[0][1]], 
+                [p[1][0] - t, p[1][1]], 
+                [p[1][0] + t, p[1][1]], 
+            ].into(), color);
+        }
+    }
+
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mesh_append() {
+        let mut mesh = Mesh::new();
+        assert!(mesh.is_empty());
+        mesh.draw_triangle([[0., 0.], [1., 0.], [0., 1.]], Color::RED);
+
+        let mut other = Mesh::new();
+        other.draw_rect([[0., 1.], [1., 0.]], Color::GREEN);
+        mesh.append(&other);
+
+        assert_eq!(mesh.vertices.len(), 7);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5, 5, 4, 6], "ERROR: appended indices weren't offset past the existing vertices");
+
+        mesh.clear();
+        assert!(mesh.is_empty());
+        assert!(mesh.vertices.is_empty());
+    }
+
+    #[test]
+    fn test_mesh_draw_line() {
+        // every corner of a line is half its thickness away from it, at any angle
+        for i in 0..24 {
+            let angle = (i as f32 * 15.).to_radians();
+            let (x, y) = (angle.cos(), angle.sin());
+            let mut mesh = Mesh::new();
+            mesh.draw_line([[0., 0.], [x, y]], 0.2, Color::RED);
+
+            for vertex in &mesh.vertices {
+                let [px, py, _] = vertex.position;
+                let distance = (px * y - py * x).abs();
+                assert!((distance - 0.1).abs() < 1e-5, "ERROR: line at {} degrees is {} thick instead of 0.2", i * 15, distance * 2.);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mesh_sort_back_to_front() {
+        let mut mesh = Mesh::new();
+        mesh.draw_triangle([[0., 0., 0.5], [1., 0., 0.5], [0., 1., 0.5]], Color::RED);
+        mesh.draw_triangle([[0., 0., 0.25], [1., 0., 0.25], [0., 1., 0.25]], Color::GREEN);
+        mesh.draw_triangle([[0., 0., 0.25], [1., 0., 0.25], [0., 1., 0.25]], Color::BLUE);
+        let unsorted = mesh.indices.clone();
+
+        // opaque triangles are ordered by the depth test
+        mesh.sort_back_to_front(0..mesh.indices.len());
+        assert_eq!(mesh.indices, unsorted);
+
+        mesh.fade_from(0, 0.5);
+        mesh.sort_back_to_front(0..mesh.indices.len());
+        assert_eq!(mesh.indices, vec![3, 4, 5, 6, 7, 8, 0, 1, 2], "ERROR: translucent triangles should be sorted back to front, keeping their order at the same depth");
+
+        // only within the range
+        mesh.draw_triangle([[0., 0., 0.]; 3], Color::RED.with_alpha(0.5));
+        mesh.sort_back_to_front(0..9);
+        assert_eq!(&mesh.indices[9..], &[9, 10, 11]);
+    }
+
+    #[test]
+    fn test_mesh_u32_indices() {
+        let mut mesh = Mesh::new();
+        for _ in 0..(u16::MAX as usize + 1) / 4 {
+            mesh.draw_rect([[0., 0.], [0., 0.]], Color::RED);
+        }
+        mesh.draw_triangle([[0., 0.], [1., 0.], [0., 1.]], Color::GREEN);
+
+        assert_eq!(&mesh.indices[mesh.indices.len() - 3..], &[65536, 65537, 65538], "ERROR: indices past u16::MAX wrapped around");
+    }
+}
@@ -0,0 +1,400 @@
+use std::f32::consts::{ PI, TAU };
+
+use super::{ Color, Mesh, Point };
+
+/// How far flattened curves may stray from the real ones by default, in the units they are drawn in.
+/// Clip space is 2 across the window, so this is well under a pixel for windows of any common size.
+pub const DEFAULT_TOLERANCE: f32 = 0.001;
+// curves never get more segments than this, however small the tolerance
+const MAX_SEGMENTS: usize = 1024;
+// band edges and crossings closer than this are merged when filling
+const EPSILON: f32 = 1e-6;
+
+/// Which parts of a path with overlapping or nested outlines are inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillRule {
+    /// Inside where a ray crosses the outlines an odd number of times, so holes cut out of shapes whichever way they wind.
+    #[default]
+    EvenOdd,
+    /// Inside where the outlines wind around a point at all, holes have to wind the other way.
+    NonZero,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo(Point, Point),
+    CubicTo(Point, Point, Point),
+    // angles in radians, counter clockwise from the positive x axis
+    Arc { center: Point, radii: [f32;2], start: f32, sweep: f32 },
+    Close,
+}
+
+/// An outline made of lines, arcs and Bézier curves, to be filled or stroked by a Mesh.
+/// Curves are flattened into lines when drawn, straying at most `tolerance` from the real ones.
+///
+/// e.g. `Path::new().move_to([0., 0.]).quad_to([0.5, 1.], [1., 0.]).close()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    segments: Vec<Segment>,
+    pub tolerance: f32,
+}
+impl Default for Path {
+    fn default() -> Self {
+        Path::new()
+    }
+}
+impl Path {
+    pub fn new() -> Self {
+        Path { segments: Vec::new(), tolerance: DEFAULT_TOLERANCE }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Starts a new outline at `to`.
+    pub fn move_to<P: Into<Point>>(mut self, to: P) -> Self {
+        self.segments.push(Segment::MoveTo(to.into()));
+        self
+    }
+
+    pub fn line_to<P: Into<Point>>(mut self, to: P) -> Self {
+        self.segments.push(Segment::LineTo(to.into()));
+        self
+    }
+
+    pub fn quad_to<P: Into<Point>>(mut self, control: P, to: P) -> Self {
+        self.segments.push(Segment::QuadTo(control.into(), to.into()));
+        self
+    }
+
+    pub fn cubic_to<P: Into<Point>>(mut self, control_a: P, control_b: P, to: P) -> Self {
+        self.segments.push(Segment::CubicTo(control_a.into(), control_b.into(), to.into()));
+        self
+    }
+
+    /// An arc of the ellipse with the given radii around `center`, from the angle `start` to `end` in radians,
+    /// counter clockwise from the positive x axis when `end` is larger. It's joined to the outline with a line,
+    /// or starts a new one if there is none.
+    pub fn arc<P: Into<Point>>(mut self, center: P, radii: [f32;2], start: f32, end: f32) -> Self {
+        self.segments.push(Segment::Arc { center: center.into(), radii, start, sweep: end - start });
+        self
+    }
+
+    /// Joins the outline back to where it started.
+    pub fn close(mut self) -> Self {
+        self.segments.push(Segment::Close);
+        self
+    }
+
+    pub fn circle<P: Into<Point>>(center: P, radius: f32) -> Self {
+        Path::ellipse(center, [radius, radius])
+    }
+
+    pub fn ellipse<P: Into<Point>>(center: P, radii: [f32;2]) -> Self {
+        Path::new().arc(center, radii, 0., TAU).close()
+    }
+
+    /// A rectangle between two corners with its corners rounded off, for UI widgets.
+    pub fn rounded_rect<P: Into<Point>>(corners: [P;2], radius: f32) -> Self {
+        let [a, b] = corners.map(|p| p.into());
+        let (left, right) = (a.x.min(b.x), a.x.max(b.x));
+        let (bottom, top) = (a.y.min(b.y), a.y.max(b.y));
+        let r = radius.min((right - left) / 2.).min((top - bottom) / 2.).max(0.);
+        let corner = |x: f32, y: f32| Point { x, y, z: a.z };
+
+        Path::new()
+            .arc(corner(right - r, top - r), [r, r], 0., PI / 2.)
+            .arc(corner(left + r, top - r), [r, r], PI / 2., PI)
+            .arc(corner(left + r, bottom + r), [r, r], PI, PI * 1.5)
+            .arc(corner(right - r, bottom + r), [r, r], PI * 1.5, TAU)
+            .close()
+    }
+
+    /// The outlines as lines, each with whether it's closed.
+    pub fn flatten(&self) -> Vec<(Vec<Point>, bool)> {
+        let mut outlines = Vec::new();
+        let mut points: Vec<Point> = Vec::new();
+        let tolerance = self.tolerance.max(EPSILON);
+
+        for segment in &self.segments {
+            let from = points.last().copied();
+            match segment {
+                Segment::MoveTo(to) => {
+                    if points.len() > 1 {
+                        outlines.push((std::mem::take(&mut points), false));
+                    }
+                    points = vec![*to];
+                },
+                Segment::LineTo(to) => points.push(*to),
+                Segment::QuadTo(control, to) => {
+                    let from = from.unwrap_or(*control);
+                    if points.is_empty() {
+                        points.push(from);
+                    }
+                    let deviation = length(from - scale(*control, 2.) + *to);
+                    let n = segments((deviation / (4. * tolerance)).sqrt());
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let u = 1. - t;
+                        points.push(scale(from, u * u) + scale(*control, 2. * u * t) + scale(*to, t * t));
+                    }
+                },
+                Segment::CubicTo(control_a, control_b, to) => {
+                    let from = from.unwrap_or(*control_a);
+                    if points.is_empty() {
+                        points.push(from);
+                    }
+                    let deviation = length(from - scale(*control_a, 2.) + *control_b).max(length(*control_a - scale(*control_b, 2.) + *to));
+                    let n = segments((3. * deviation / (4. * tolerance)).sqrt());
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let u = 1. - t;
+                        points.push(
+                            scale(from, u * u * u) + scale(*control_a, 3. * u * u * t) + scale(*control_b, 3. * u * t * t) + scale(*to, t * t * t)
+                        );
+                    }
+                },
+                Segment::Arc { center, radii, start, sweep } => {
+                    let radius = radii[0].abs().max(radii[1].abs());
+                    // each segment's chord may only cut tolerance into the arc
+                    let step = 2. * (1. - (tolerance / radius).min(1.)).acos();
+                    let n = segments(sweep.abs() / step.max(EPSILON));
+                    for i in 0..=n {
+                        let angle = start + sweep * i as f32 / n as f32;
+                        points.push(Point { x: center.x + radii[0] * angle.cos(), y: center.y + radii[1] * angle.sin(), z: center.z });
+                    }
+                },
+                Segment::Close => {
+                    if points.len() > 1 {
+                        let start = points[0];
+                        outlines.push((std::mem::take(&mut points), true));
+                        points.push(start);
+                    }
+                },
+            }
+        }
+        if points.len() > 1 {
+            outlines.push((points, false));
+        }
+
+        return outlines;
+    }
+}
+
+fn length(p: Point) -> f32 {
+    (p.x * p.x + p.y * p.y).sqrt()
+}
+
+fn scale(p: Point, s: f32) -> Point {
+    Point { x: p.x * s, y: p.y * s, z: p.z }
+}
+
+fn segments(n: f32) -> usize {
+    (n.ceil() as usize).clamp(1, MAX_SEGMENTS)
+}
+
+// an outline's edge going from y0 up to y1, winding says which way the outline went along it
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+impl Edge {
+    fn x_at(&self, y: f32) -> f32 {
+        self.x0 + (self.x1 - self.x0) * (y - self.y0) / (self.y1 - self.y0)
+    }
+}
+
+// where two edges cross strictly between their ends, if they do
+fn crossing(a: &Edge, b: &Edge) -> Option<f32> {
+    let low = a.y0.max(b.y0);
+    let high = a.y1.min(b.y1);
+    if high - low <= EPSILON {
+        return None;
+    }
+    let low_gap = a.x_at(low) - b.x_at(low);
+    let high_gap = a.x_at(high) - b.x_at(high);
+    if low_gap * high_gap >= 0. {
+        return None;
+    }
+    return Some(low + (high - low) * low_gap / (low_gap - high_gap));
+}
+
+// the geometry behind `Mesh::fill_path`, the outlines are cut into horizontal bands at every corner and crossing,
+// where no edges cross, so the inside of each band is a row of trapezoids between pairs of edges
+pub(super) fn fill_outlines(mesh: &mut Mesh, outlines: &[Vec<Point>], rule: FillRule, color: Color) {
+    let Some(z) = outlines.iter().flatten().next().map(|p| p.z) else {
+        return;
+    };
+
+    let mut edges = Vec::new();
+    for outline in outlines {
+        let ends = outline.iter().skip(1).chain(outline.first());
+        for (a, b) in outline.iter().zip(ends) {
+            if (a.y - b.y).abs() <= EPSILON {
+                continue;
+            }
+            let (low, high, winding) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+            edges.push(Edge { x0: low.x, y0: low.y, x1: high.x, y1: high.y, winding });
+        }
+    }
+
+    let mut ys: Vec<f32> = edges.iter().flat_map(|e| [e.y0, e.y1]).collect();
+    for (i, a) in edges.iter().enumerate() {
+        ys.extend(edges[i + 1..].iter().filter_map(|b| crossing(a, b)));
+    }
+    ys.sort_by(f32::total_cmp);
+    ys.dedup_by(|a, b| *a - *b <= EPSILON);
+
+    let mut active: Vec<(f32, f32, f32, i32)> = Vec::new();
+    for band in ys.windows(2) {
+        let (low, high) = (band[0], band[1]);
+        let middle = (low + high) / 2.;
+        active.clear();
+        active.extend(edges.iter()
+            .filter(|e| e.y0 <= middle && e.y1 >= middle)
+            .map(|e| (e.x_at(middle), e.x_at(low), e.x_at(high), e.winding)));
+        active.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        let mut left = None;
+        for &(_, x_low, x_high, edge_winding) in &active {
+            winding += edge_winding;
+            let inside = match rule {
+                FillRule::EvenOdd => winding % 2 != 0,
+                FillRule::NonZero => winding != 0,
+            };
+            match (inside, left) {
+                (true, None) => left = Some((x_low, x_high)),
+                (false, Some((left_low, left_high))) => {
+                    mesh.draw_poly(vec![
+                        [left_low, low, z],
+                        [x_low, low, z],
+                        [x_high, high, z],
+                        [left_high, high, z],
+                    ], color);
+                    left = None;
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----- HELPER FUNCTIONS -----
+    // the area covered by a mesh's triangles, overlapping ones counted twice
+    fn area(mesh: &Mesh) -> f32 {
+        mesh.indices.chunks(3).map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].position);
+            ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.
+        }).sum()
+    }
+
+    fn fill(outlines: &[Vec<Point>], rule: FillRule) -> Mesh {
+        let mut mesh = Mesh::new();
+        fill_outlines(&mut mesh, outlines, rule, Color::RED);
+        mesh
+    }
+
+    fn outline(points: &[[f32;2]]) -> Vec<Point> {
+        points.iter().map(|&p| p.into()).collect()
+    }
+
+    // ----- FLATTEN TESTS -----
+    #[test]
+    fn test_path_flatten() {
+        let outlines = Path::new().move_to([0., 0.]).line_to([1., 0.]).move_to([0., 1.]).line_to([1., 1.]).line_to([1., 2.]).close().flatten();
+        assert_eq!(outlines.len(), 2);
+        assert_eq!(outlines[0], (outline(&[[0., 0.], [1., 0.]]), false));
+        assert_eq!(outlines[1], (outline(&[[0., 1.], [1., 1.], [1., 2.]]), true));
+
+        // curves end where they're told to and get more segments the tighter the tolerance
+        let curve = |tolerance: f32| Path::new().move_to([0., 0.]).cubic_to([0., 1.], [1., 1.], [1., 0.]).with_tolerance(tolerance).flatten();
+        let (coarse, fine) = (curve(0.01), curve(0.0001));
+        assert!(fine[0].0.len() > coarse[0].0.len());
+        assert_eq!(*fine[0].0.last().unwrap(), Point::from([1., 0.]));
+
+        // the middle of the quadratic curve is halfway to its control point
+        let quad = Path::new().move_to([0., 0.]).quad_to([1., 2.], [2., 0.]).flatten();
+        assert!(quad[0].0.iter().all(|p| p.y <= 1. + EPSILON));
+        assert!(quad[0].0.iter().any(|p| (p.y - 1.).abs() < 0.001), "ERROR: quadratic curve doesn't reach its middle");
+    }
+
+    #[test]
+    fn test_path_circle() {
+        for tolerance in [0.1, 0.01, 0.001] {
+            let circle = Path::circle([1., 1.], 0.5).with_tolerance(tolerance).flatten();
+            let (points, closed) = &circle[0];
+            assert!(closed);
+            assert!(points.iter().all(|p| (length(*p - Point::from([1., 1.])) - 0.5).abs() < 1e-5), "ERROR: circle points aren't on the circle");
+            // the middle of every chord is within the tolerance of the circle
+            for pair in points.windows(2) {
+                let middle = scale(pair[0] + pair[1], 0.5);
+                assert!(0.5 - length(middle - Point::from([1., 1.])) <= tolerance + 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_rounded_rect() {
+        let outlines = Path::rounded_rect([[0., 1.], [2., 0.]], 0.25).flatten();
+        let points = &outlines[0].0;
+        assert!(points.iter().all(|p| p.x >= -EPSILON && p.x <= 2. + EPSILON && p.y >= -EPSILON && p.y <= 1. + EPSILON));
+        assert!(!points.iter().any(|p| length(*p - Point::from([0., 0.])) < 0.05), "ERROR: corners weren't rounded off");
+
+        // radii larger than the rect make it a stadium instead of inverting it
+        let outlines = Path::rounded_rect([[0., 1.], [2., 0.]], 5.).flatten();
+        assert!(outlines[0].0.iter().all(|p| p.y >= -EPSILON && p.y <= 1. + EPSILON));
+    }
+
+    // ----- FILL TESTS -----
+    #[test]
+    fn test_fill_concave() {
+        // an L covering three of four unit squares
+        let l = outline(&[[0., 0.], [2., 0.], [2., 1.], [1., 1.], [1., 2.], [0., 2.]]);
+        let mesh = fill(&[l], FillRule::EvenOdd);
+        assert!((area(&mesh) - 3.).abs() < 1e-5, "ERROR: concave polygon should cover its area exactly once, covered {}", area(&mesh));
+    }
+
+    #[test]
+    fn test_fill_rules() {
+        let outer = outline(&[[0., 0.], [4., 0.], [4., 4.], [0., 4.]]);
+        let inner = outline(&[[1., 1.], [3., 1.], [3., 3.], [1., 3.]]);
+        let inner_reversed: Vec<Point> = inner.iter().rev().copied().collect();
+
+        assert!((area(&fill(&[outer.clone(), inner.clone()], FillRule::EvenOdd)) - 12.).abs() < 1e-5, "ERROR: even odd should cut the hole");
+        assert!((area(&fill(&[outer.clone(), inner.clone()], FillRule::NonZero)) - 16.).abs() < 1e-5, "ERROR: non zero should fill a hole winding the same way");
+        assert!((area(&fill(&[outer, inner_reversed], FillRule::NonZero)) - 12.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_fill_self_intersecting() {
+        // a bowtie, its two triangles meet where its edges cross
+        let bowtie = outline(&[[0., 0.], [2., 2.], [2., 0.], [0., 2.]]);
+        assert!((area(&fill(&[bowtie], FillRule::NonZero)) - 2.).abs() < 1e-5);
+
+        // a pentagram, the pentagon in its middle is wound twice
+        let star: Vec<Point> = (0..5).map(|i| {
+            let angle = PI / 2. + i as f32 * 4. * PI / 5.;
+            Point::from([angle.cos(), angle.sin()])
+        }).collect();
+        let even_odd = area(&fill(std::slice::from_ref(&star), FillRule::EvenOdd));
+        let non_zero = area(&fill(&[star], FillRule::NonZero));
+        assert!(non_zero > even_odd, "ERROR: non zero should fill the middle of the star, even odd shouldn't");
+    }
+}
@@ -0,0 +1,166 @@
+// golden image testing for the renderer, scenes are drawn with a headless Renderer and compared against PNGs in `src/engine/draw/snapshots`
+// running the tests with UPDATE_SNAPSHOTS=1 writes the current frames as the new golden images
+use std::fs::File;
+use std::io::{ self, BufReader };
+use std::path::{ Path, PathBuf };
+
+use super::{ Frame, Renderer };
+
+const SNAPSHOT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/engine/draw/snapshots");
+const DIFF_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/snapshot_diffs");
+
+// matching pixels are faded in the diff image so the mismatched ones stand out
+const MISMATCH_COLOR: [u8;4] = [255, 0, 255, 255];
+
+/// A headless Renderer to test with, machines without any adapter, not even a software one, skip these tests instead of failing.
+pub fn headless(width: u32, height: u32) -> Option<Renderer> {
+    let renderer = pollster::block_on(Renderer::new_headless(width, height));
+    if renderer.is_none() {
+        // a skipped test would let regressions through unnoticed where they're meant to be caught
+        assert!(std::env::var_os("CI").is_none(), "ERROR: no adapter available to run headless renderer tests on in CI");
+        eprintln!("no adapter available, skipping headless renderer test");
+    }
+    renderer
+}
+
+pub fn read_png<P: AsRef<Path>>(path: P) -> io::Result<Frame> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(io::Error::other)?;
+
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "snapshots have to be 8 bit RGBA"));
+    }
+    pixels.truncate(info.buffer_size());
+
+    return Ok(Frame { width: info.width, height: info.height, pixels });
+}
+
+/// Compares two frames of the same size, pixels match if none of their channels differ by more than `tolerance`.
+/// Returns how many pixels didn't match and an image marking them.
+pub fn compare(expected: &Frame, actual: &Frame, tolerance: u8) -> (usize, Frame) {
+    assert_eq!((expected.width, expected.height), (actual.width, actual.height), "ERROR: compared frames differ in size");
+
+    let mut mismatched = 0;
+    let mut pixels = Vec::with_capacity(actual.pixels.len());
+    for (expected, actual) in expected.pixels.chunks(4).zip(actual.pixels.chunks(4)) {
+        let matches = expected.iter().zip(actual).all(|(e, a)| e.abs_diff(*a) <= tolerance);
+        if matches {
+            pixels.extend(actual[..3].iter().map(|channel| channel / 4));
+            pixels.push(255);
+        } else {
+            mismatched += 1;
+            pixels.extend(MISMATCH_COLOR);
+        }
+    }
+
+    return (mismatched, Frame { width: actual.width, height: actual.height, pixels });
+}
+
+/// Lays frames of the same height out next to each other, so scenes that need a renderer each share one golden image.
+pub fn side_by_side(frames: &[Frame]) -> Frame {
+    let height = frames[0].height;
+    assert!(frames.iter().all(|frame| frame.height == height), "ERROR: frames laid out side by side differ in height");
+
+    let width = frames.iter().map(|frame| frame.width).sum();
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height as usize {
+        for frame in frames {
+            let row_bytes = frame.width as usize * 4;
+            pixels.extend_from_slice(&frame.pixels[y * row_bytes..(y + 1) * row_bytes]);
+        }
+    }
+
+    return Frame { width, height, pixels };
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(SNAPSHOT_DIR).join(format!("{}.png", name))
+}
+
+/// Panics if `frame` doesn't match the golden image `name`, after writing it and a diff image to `target/snapshot_diffs`.
+pub fn assert_snapshot(name: &str, frame: &Frame, tolerance: u8) {
+    let golden = golden_path(name);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        frame.write_png(&golden).unwrap();
+        return;
+    }
+
+    let expected = match read_png(&golden) {
+        Ok(expected) => expected,
+        Err(err) => panic!(
+            "ERROR: couldn't read golden image {}: {}, run with UPDATE_SNAPSHOTS=1 to create it",
+            golden.display(), err
+        ),
+    };
+
+    let actual_path = Path::new(DIFF_DIR).join(format!("{}.actual.png", name));
+    if (expected.width, expected.height) != (frame.width, frame.height) {
+        frame.write_png(&actual_path).unwrap();
+        panic!(
+            "ERROR: snapshot {} is {}x{} but the golden image is {}x{}, wrote it to {}",
+            name, frame.width, frame.height, expected.width, expected.height, actual_path.display()
+        );
+    }
+
+    let (mismatched, diff) = compare(&expected, frame, tolerance);
+    if mismatched > 0 {
+        let diff_path = Path::new(DIFF_DIR).join(format!("{}.diff.png", name));
+        frame.write_png(&actual_path).unwrap();
+        diff.write_png(&diff_path).unwrap();
+        panic!(
+            "ERROR: snapshot {} differs from its golden image in {} pixels, see {} and {}",
+            name, mismatched, actual_path.display(), diff_path.display()
+        );
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(pixels: &[[u8;4]]) -> Frame {
+        Frame { width: pixels.len() as u32, height: 1, pixels: pixels.concat() }
+    }
+
+    #[test]
+    fn test_snapshot_compare() {
+        let expected = frame(&[[0, 0, 0, 255], [200, 100, 0, 255]]);
+
+        let (mismatched, _) = compare(&expected, &frame(&[[2, 0, 0, 255], [198, 100, 0, 255]]), 2);
+        assert_eq!(mismatched, 0, "ERROR: differences within the tolerance should match");
+
+        let (mismatched, diff) = compare(&expected, &frame(&[[0, 0, 0, 255], [200, 100, 3, 255]]), 2);
+        assert_eq!(mismatched, 1);
+        assert_eq!(diff.pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(diff.pixel(1, 0), MISMATCH_COLOR);
+    }
+
+    #[test]
+    fn test_snapshot_side_by_side() {
+        let left = Frame { width: 1, height: 2, pixels: [[1, 1, 1, 1], [2, 2, 2, 2]].concat() };
+        let right = Frame { width: 2, height: 2, pixels: [[3, 3, 3, 3], [4, 4, 4, 4], [5, 5, 5, 5], [6, 6, 6, 6]].concat() };
+
+        let frame = side_by_side(&[left, right]);
+        assert_eq!((frame.width, frame.height), (3, 2));
+        assert_eq!(frame.pixel(0, 1), [2, 2, 2, 2]);
+        assert_eq!(frame.pixel(1, 0), [3, 3, 3, 3]);
+        assert_eq!(frame.pixel(2, 1), [6, 6, 6, 6]);
+    }
+
+    #[test]
+    fn test_snapshot_png() {
+        let path = std::env::temp_dir().join("poliosis_test_snapshot.png");
+        let frame = Frame { width: 2, height: 2, pixels: vec![
+            255, 0, 0, 255,   0, 255, 0, 255,
+            0, 0, 255, 255,   255, 255, 255, 0,
+        ] };
+
+        frame.write_png(&path).unwrap();
+        assert_eq!(read_png(&path).unwrap(), frame);
+        std::fs::remove_file(path).unwrap();
+    }
+}
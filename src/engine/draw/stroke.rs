@@ -0,0 +1,358 @@
+use std::f32::consts::PI;
+
+use super::{ Color, Mesh, Point };
+
+// the largest angle a single triangle of a round join or cap spans
+const ROUND_STEP: f32 = PI / 16.;
+// shorter segments are dropped, their direction can't be told
+const MIN_SEGMENT: f32 = 1e-6;
+
+/// How the segments of a stroke are joined where they meet at an angle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// The outer edges are extended until they meet, bevelled instead past the stroke's miter limit.
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// How the ends of an open stroke and of its dashes are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// Ends exactly at the end points.
+    #[default]
+    Butt,
+    Round,
+    /// Extends half the thickness past the end points.
+    Square,
+}
+
+/// How a line is drawn along a path, see `Mesh::draw_polyline`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub thickness: f32,
+    pub color: Color,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// How many times half the thickness a miter can reach out from its corner before it's bevelled.
+    pub miter_limit: f32,
+    /// Lengths of the dashes and the gaps between them, alternating and starting with a dash. Empty for solid strokes.
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern the stroke starts.
+    pub dash_offset: f32,
+}
+impl Stroke {
+    pub fn new<C: Into<Color>>(thickness: f32, color: C) -> Self {
+        Stroke {
+            thickness,
+            color: color.into(),
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            miter_limit: 4.,
+            dashes: Vec::new(),
+            dash_offset: 0.,
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    pub fn with_dashes(mut self, dashes: &[f32], offset: f32) -> Self {
+        self.dashes = dashes.to_vec();
+        self.dash_offset = offset;
+        self
+    }
+}
+
+fn length(p: Point) -> f32 {
+    (p.x * p.x + p.y * p.y).sqrt()
+}
+
+fn scale(p: Point, s: f32) -> Point {
+    Point { x: p.x * s, y: p.y * s, z: p.z }
+}
+
+fn direction(from: Point, to: Point) -> Point {
+    let delta = to - from;
+    scale(delta, 1. / length(delta))
+}
+
+// the normal on the left of a direction
+fn left(d: Point) -> Point {
+    Point { x: -d.y, y: d.x, z: 0. }
+}
+
+fn rotate(p: Point, angle: f32) -> Point {
+    let (sin, cos) = angle.sin_cos();
+    Point { x: p.x * cos - p.y * sin, y: p.x * sin + p.y * cos, z: 0. }
+}
+
+// a fan around center, from center + from turning by sweep radians, counter clockwise for positive ones
+fn fan(mesh: &mut Mesh, center: Point, from: Point, sweep: f32, color: Color) {
+    let steps = ((sweep.abs() / ROUND_STEP).ceil() as usize).max(1);
+    let mut points = Vec::with_capacity(steps + 2);
+    points.push(center);
+    for step in 0..=steps {
+        points.push(center + rotate(from, sweep * step as f32 / steps as f32));
+    }
+    mesh.draw_poly(points, color);
+}
+
+// the wedge on the outer side of the corner at p, between the ends of the segments coming in along d0 and leaving along d1
+fn join(mesh: &mut Mesh, p: Point, d0: Point, d1: Point, half: f32, stroke: &Stroke) {
+    let cross = d0.x * d1.y - d0.y * d1.x;
+    let dot = d0.x * d1.x + d0.y * d1.y;
+    if cross.abs() < MIN_SEGMENT && dot > 0. {
+        return;
+    }
+    // left turns have their outer side on the right
+    let side = if cross > 0. { -1. } else { 1. };
+    let n0 = scale(left(d0), side * half);
+    let n1 = scale(left(d1), side * half);
+
+    match stroke.join {
+        LineJoin::Round => {
+            let turn = cross.atan2(dot).abs();
+            fan(mesh, p, n0, -side * turn, stroke.color);
+        },
+        LineJoin::Miter | LineJoin::Bevel => {
+            let bisector = n0 + n1;
+            let bisector_length = length(bisector);
+            // the miter reaches half / cos(angle / 2) out, with cos(angle / 2) = |n0 + n1| / (2 * half)
+            let miter = 2. * half * half / bisector_length;
+            if stroke.join == LineJoin::Miter && bisector_length > MIN_SEGMENT && miter <= stroke.miter_limit * half {
+                let tip = p + scale(bisector, miter / bisector_length);
+                mesh.draw_poly(vec![p, p + n0, tip, p + n1], stroke.color);
+            } else {
+                mesh.draw_poly(vec![p, p + n0, p + n1], stroke.color);
+            }
+        },
+    }
+}
+
+// a single open or closed run of points without repeated ones, at least two of them
+fn stroke_run(mesh: &mut Mesh, points: &[Point], closed: bool, stroke: &Stroke) {
+    let half = stroke.thickness / 2.;
+    let count = points.len();
+    let segments = if closed { count } else { count - 1 };
+    let directions: Vec<Point> = (0..segments).map(|i| direction(points[i], points[(i + 1) % count])).collect();
+
+    for (i, &d) in directions.iter().enumerate() {
+        let mut start = points[i];
+        let mut end = points[(i + 1) % count];
+        if !closed && stroke.cap == LineCap::Square {
+            if i == 0 {
+                start = start - scale(d, half);
+            }
+            if i == segments - 1 {
+                end = end + scale(d, half);
+            }
+        }
+        let normal = scale(left(d), half);
+        mesh.draw_poly(vec![start + normal, start - normal, end - normal, end + normal], stroke.color);
+    }
+
+    if closed {
+        for i in 0..count {
+            join(mesh, points[i], directions[(i + segments - 1) % segments], directions[i], half, stroke);
+        }
+    } else {
+        for i in 1..count - 1 {
+            join(mesh, points[i], directions[i - 1], directions[i], half, stroke);
+        }
+        if stroke.cap == LineCap::Round {
+            let first = scale(directions[0], -1.);
+            let last = directions[segments - 1];
+            fan(mesh, points[0], scale(left(first), half), -PI, stroke.color);
+            fan(mesh, points[count - 1], scale(left(last), half), -PI, stroke.color);
+        }
+    }
+}
+
+// splits a path into its dashes, closed paths are walked back to their first point
+fn dash(points: &[Point], closed: bool, dashes: &[f32], offset: f32) -> Vec<Vec<Point>> {
+    let period: f32 = dashes.iter().sum();
+
+    let mut index = 0;
+    let mut remaining = dashes[0];
+    let mut skip = offset.rem_euclid(period);
+    while skip >= remaining {
+        skip -= remaining;
+        index = (index + 1) % dashes.len();
+        remaining = dashes[index];
+    }
+    remaining -= skip;
+
+    let mut runs = Vec::new();
+    let mut run = vec![points[0]];
+    let ends = points.iter().skip(1).chain(closed.then_some(&points[0]));
+    for (&a, &b) in points.iter().zip(ends) {
+        let d = direction(a, b);
+        let segment = length(b - a);
+        let mut walked = 0.;
+        while segment - walked > remaining {
+            walked += remaining;
+            let at = a + scale(d, walked);
+            if index % 2 == 0 {
+                run.push(at);
+                runs.push(std::mem::take(&mut run));
+            } else {
+                run = vec![at];
+            }
+            index = (index + 1) % dashes.len();
+            remaining = dashes[index];
+        }
+        remaining -= segment - walked;
+        if index % 2 == 0 {
+            run.push(b);
+        }
+    }
+    if index % 2 == 0 {
+        runs.push(run);
+    }
+
+    return runs;
+}
+
+// the geometry behind `Mesh::draw_polyline`
+pub(super) fn stroke_path(mesh: &mut Mesh, points: &[Point], closed: bool, stroke: &Stroke) {
+    let mut path: Vec<Point> = Vec::with_capacity(points.len());
+    for &point in points {
+        let repeated = path.last().is_some_and(|&last| length(point - last) <= MIN_SEGMENT);
+        if !repeated {
+            path.push(point);
+        }
+    }
+    let closed = closed && path.len() > 2;
+    if closed && length(path[0] - path[path.len() - 1]) <= MIN_SEGMENT {
+        path.pop();
+    }
+    if path.len() < 2 {
+        return;
+    }
+
+    let dashed = !stroke.dashes.is_empty() && stroke.dashes.iter().all(|&length| length >= 0.) && stroke.dashes.iter().sum::<f32>() > 0.;
+    if !dashed {
+        stroke_run(mesh, &path, closed, stroke);
+        return;
+    }
+    // odd patterns repeat twice, so every dash is a gap the next time around like in svg
+    let dashes = match stroke.dashes.len() % 2 {
+        0 => stroke.dashes.clone(),
+        _ => stroke.dashes.repeat(2),
+    };
+    for mut run in dash(&path, closed, &dashes, stroke.dash_offset) {
+        run.dedup_by(|a, b| length(*a - *b) <= MIN_SEGMENT);
+        if run.len() >= 2 {
+            stroke_run(mesh, &run, false, stroke);
+        }
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----- HELPER FUNCTIONS -----
+    fn points(coords: &[[f32;2]]) -> Vec<Point> {
+        coords.iter().map(|&p| p.into()).collect()
+    }
+
+    fn close(a: Point, b: Point) -> bool {
+        length(a - b) < 1e-5
+    }
+
+    // ----- DASH TESTS -----
+    #[test]
+    fn test_dash() {
+        let runs = dash(&points(&[[0., 0.], [10., 0.]]), false, &[2., 1.], 0.);
+        assert_eq!(runs.len(), 4, "ERROR: a path of 10 should have 4 dashes of 2 with gaps of 1");
+        assert!(close(runs[1][0], [3., 0.].into()) && close(runs[1][1], [5., 0.].into()));
+        assert!(close(runs[3][0], [9., 0.].into()) && close(runs[3][1], [10., 0.].into()), "ERROR: the last dash should be cut off at the end");
+
+        // dashes go around corners
+        let runs = dash(&points(&[[0., 0.], [1., 0.], [1., 1.]]), false, &[1.5, 1.], 0.);
+        assert_eq!(runs[0].len(), 3);
+        assert!(close(runs[0][2], [1., 0.5].into()));
+
+        // an offset starts the pattern part of the way in
+        let runs = dash(&points(&[[0., 0.], [4., 0.]]), false, &[2., 1.], 2.5);
+        assert!(close(runs[0][0], [0.5, 0.].into()));
+
+        // closed paths are dashed back to their first point
+        let runs = dash(&points(&[[0., 0.], [1., 0.], [1., 1.], [0., 1.]]), true, &[3.5, 1.], 0.);
+        assert!(close(*runs[0].last().unwrap(), [0., 0.5].into()));
+    }
+
+    // ----- STROKE TESTS -----
+    #[test]
+    fn test_stroke_joins() {
+        let corner = points(&[[0., 0.], [1., 0.], [1., 1.]]);
+        let count = |join: LineJoin| {
+            let mut mesh = Mesh::new();
+            stroke_path(&mut mesh, &corner, false, &Stroke::new(0.2, Color::RED).with_join(join));
+            mesh.vertices.len()
+        };
+        // two segment quads, then a bevel triangle, a miter quad or a fan
+        assert_eq!(count(LineJoin::Bevel), 8 + 3);
+        assert_eq!(count(LineJoin::Miter), 8 + 4);
+        assert!(count(LineJoin::Round) > count(LineJoin::Miter));
+
+        // the miter of a right angle reaches sqrt(2) half thicknesses out, past a limit of 1 it's bevelled
+        let mut mesh = Mesh::new();
+        stroke_path(&mut mesh, &corner, false, &Stroke::new(0.2, Color::RED).with_miter_limit(1.));
+        assert_eq!(mesh.vertices.len(), 8 + 3, "ERROR: miter past the limit wasn't bevelled");
+
+        let mut mesh = Mesh::new();
+        stroke_path(&mut mesh, &corner, false, &Stroke::new(0.2, Color::RED));
+        assert!(mesh.vertices.iter().any(|v| (v.position[0] - 1.1).abs() < 1e-5 && (v.position[1] + 0.1).abs() < 1e-5), "ERROR: miter tip is missing");
+    }
+
+    #[test]
+    fn test_stroke_caps() {
+        let line = points(&[[0., 0.], [1., 0.]]);
+        let bounds = |cap: LineCap| {
+            let mut mesh = Mesh::new();
+            stroke_path(&mut mesh, &line, false, &Stroke::new(0.2, Color::RED).with_cap(cap));
+            let xs = mesh.vertices.iter().map(|v| v.position[0]);
+            (xs.clone().fold(f32::MAX, f32::min), xs.fold(f32::MIN, f32::max))
+        };
+        assert_eq!(bounds(LineCap::Butt), (0., 1.));
+        let (min, max) = bounds(LineCap::Square);
+        assert!((min + 0.1).abs() < 1e-5 && (max - 1.1).abs() < 1e-5);
+        // round caps only come close to half the thickness out, between the corners of their fans
+        let (min, max) = bounds(LineCap::Round);
+        assert!((-0.1..-0.099).contains(&min) && (1.099..=1.1).contains(&max));
+    }
+
+    #[test]
+    fn test_stroke_degenerate() {
+        let mut mesh = Mesh::new();
+        stroke_path(&mut mesh, &points(&[[0., 0.], [0., 0.]]), false, &Stroke::new(0.2, Color::RED));
+        stroke_path(&mut mesh, &points(&[[0., 0.]]), true, &Stroke::new(0.2, Color::RED));
+        assert!(mesh.is_empty(), "ERROR: paths without any length shouldn't be drawn");
+
+        // repeated points and a last point back at the first one don't add joins
+        stroke_path(&mut mesh, &points(&[[0., 0.], [1., 0.], [1., 0.], [1., 1.], [0., 0.]]), true, &Stroke::new(0.2, Color::RED));
+        assert_eq!(mesh.vertices.len(), 3 * 4 + 3 * 4);
+
+        // patterns without any length are drawn solid instead of looping forever
+        let mut mesh = Mesh::new();
+        stroke_path(&mut mesh, &points(&[[0., 0.], [1., 0.]]), false, &Stroke::new(0.2, Color::RED).with_dashes(&[0., 0.], 0.));
+        assert_eq!(mesh.vertices.len(), 4);
+    }
+}
@@ -0,0 +1,236 @@
+use wgpu_glyph::ab_glyph::{ self, Font, FontArc, ScaleFont };
+use wgpu_glyph::{ HorizontalAlign, Layout, OwnedSection, OwnedText, VerticalAlign };
+
+use super::{ Color, Point };
+
+// every Renderer starts with it, as FontId::DEFAULT
+pub(super) const INCONSOLATA: &[u8] = include_bytes!("../../Inconsolata-Regular.ttf");
+
+/// A font loaded into a Renderer, see `Renderer::load_font`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FontId(pub(super) usize);
+impl FontId {
+    /// Inconsolata, which every Renderer has.
+    pub const DEFAULT: FontId = FontId(0);
+}
+
+/// Where text is laid out horizontally from its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Where text is laid out vertically from its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VAlign {
+    /// The top of the first line is at the position.
+    #[default]
+    Top,
+    Center,
+    /// The baseline of the first line is at the position, so text of different sizes lines up.
+    Baseline,
+    /// The bottom of the last line is at the position.
+    Bottom,
+}
+
+/// A stretch of a RichText styled apart from the rest of it, anything left as None is the RichText's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
+    pub scale: Option<f32>,
+    pub font: Option<FontId>,
+}
+impl Span {
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Span {
+            text: text.into(),
+            color: None,
+            scale: None,
+            font: None,
+        }
+    }
+
+    pub fn with_color<C: Into<Color>>(mut self, color: C) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    pub fn with_font(mut self, font: FontId) -> Self {
+        self.font = Some(font);
+        self
+    }
+}
+
+/// Spans of text laid out one after the other, see `Renderer::draw_rich_text` and `Renderer::measure_rich_text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+    pub color: Color,
+    /// The height of a line in clip space, as in `Renderer::draw_text`.
+    pub scale: f32,
+    pub font: FontId,
+    pub h_align: HAlign,
+    pub v_align: VAlign,
+    /// How wide lines get in clip space before they're wrapped between words, None to only break them at newlines.
+    pub wrap_width: Option<f32>,
+}
+impl RichText {
+    pub fn new<C: Into<Color>>(color: C, scale: f32) -> Self {
+        RichText {
+            spans: Vec::new(),
+            color: color.into(),
+            scale,
+            font: FontId::DEFAULT,
+            h_align: HAlign::default(),
+            v_align: VAlign::default(),
+            wrap_width: None,
+        }
+    }
+
+    /// Adds text in the RichText's own style.
+    pub fn with_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.spans.push(Span::new(text));
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn with_font(mut self, font: FontId) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn with_align(mut self, h_align: HAlign, v_align: VAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    pub fn with_wrap(mut self, width: f32) -> Self {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    /// The section glyphs are laid out from for a target of `size`, fonts the Renderer doesn't have are
+    /// swapped for the default one.
+    pub(super) fn section(&self, position: Point, size: winit::dpi::PhysicalSize<u32>, fonts: &[FontArc]) -> OwnedSection {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let font_id = |font: FontId| wgpu_glyph::FontId(if font.0 < fonts.len() { font.0 } else { 0 });
+        let px_scale = |scale: f32| px_scale(scale, size);
+
+        let text = self.spans.iter().map(|span| {
+            let color: [f32;4] = span.color.unwrap_or(self.color).into();
+            OwnedText::new(span.text.as_str())
+                .with_color(color)
+                .with_scale(px_scale(span.scale.unwrap_or(self.scale)))
+                .with_font_id(font_id(span.font.unwrap_or(self.font)))
+                .with_z(position.z)
+        }).collect();
+
+        let mut screen_position = ((width / 2.) + (position.x / 2.) * width + 1., (height / 2.) + (-position.y / 2.) * height);
+        // laid out from the top, raised by how far the default style's glyphs reach above the baseline
+        if self.v_align == VAlign::Baseline {
+            screen_position.1 -= fonts.get(font_id(self.font).0).map_or(0., |font| font.as_scaled(px_scale(self.scale)).ascent());
+        }
+        let h_align = match self.h_align {
+            HAlign::Left => HorizontalAlign::Left,
+            HAlign::Center => HorizontalAlign::Center,
+            HAlign::Right => HorizontalAlign::Right,
+        };
+        let v_align = match self.v_align {
+            VAlign::Top | VAlign::Baseline => VerticalAlign::Top,
+            VAlign::Center => VerticalAlign::Center,
+            VAlign::Bottom => VerticalAlign::Bottom,
+        };
+
+        return OwnedSection {
+            screen_position,
+            bounds: (self.wrap_width.map_or(f32::INFINITY, |wrap_width| (wrap_width / 2.) * width), f32::INFINITY),
+            layout: Layout::default_wrap().h_align(h_align).v_align(v_align),
+            text,
+        };
+    }
+}
+
+/// The size in pixels glyphs of text `scale` high in clip space are drawn at on a target of `size`.
+/// They're as wide as they're high, so they aren't stretched on targets that aren't square.
+pub fn px_scale(scale: f32, size: winit::dpi::PhysicalSize<u32>) -> ab_glyph::PxScale {
+    return ab_glyph::PxScale::from((scale / 2.) * size.height as f32);
+}
+
+/// The corners of a rectangle of pixels from the top left of a target of `size` in clip space, top left first.
+pub(super) fn to_clip(rect: ab_glyph::Rect, size: winit::dpi::PhysicalSize<u32>) -> [Point;2] {
+    let (width, height) = (size.width as f32 / 2., size.height as f32 / 2.);
+    let to_clip = |point: ab_glyph::Point| Point { x: point.x / width - 1., y: 1. - point.y / height, z: 0. };
+    return [to_clip(rect.min), to_clip(rect.max)];
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_spans() {
+        let fonts = [FontArc::try_from_slice(INCONSOLATA).unwrap()];
+        let text = RichText::new(Color::WHITE, 0.5)
+            .with_text("Money: ")
+            .with_span(Span::new("-20").with_color(Color::RED).with_scale(1.).with_font(FontId(3)));
+        let section = text.section([0., 0., 0.5].into(), winit::dpi::PhysicalSize::new(200, 100), &fonts);
+
+        assert_eq!(section.screen_position, (101., 50.));
+        assert_eq!(section.bounds, (f32::INFINITY, f32::INFINITY));
+        assert_eq!(section.text[0].extra.color, [1., 1., 1., 1.]);
+        assert_eq!(section.text[0].scale, ab_glyph::PxScale { x: 25., y: 25. }, "ERROR: glyphs shouldn't be stretched to the target's aspect ratio");
+        assert_eq!(section.text[1].extra.color, [1., 0., 0., 1.], "ERROR: spans should keep their own style");
+        assert_eq!(section.text[1].scale, ab_glyph::PxScale { x: 50., y: 50. });
+        assert_eq!(section.text[1].font_id, wgpu_glyph::FontId(0), "ERROR: unknown fonts should be swapped for the default one");
+        assert!(section.text.iter().all(|text| text.extra.z == 0.5));
+    }
+
+    #[test]
+    fn test_section_layout() {
+        let fonts = [FontArc::try_from_slice(INCONSOLATA).unwrap()];
+        let size = winit::dpi::PhysicalSize::new(200, 100);
+        let text = RichText::new(Color::WHITE, 0.5).with_text("Hi").with_align(HAlign::Right, VAlign::Bottom).with_wrap(0.5);
+        let section = text.section([0., 0.].into(), size, &fonts);
+        assert_eq!(section.bounds.0, 50.);
+        assert_eq!(section.layout, Layout::default_wrap().h_align(HorizontalAlign::Right).v_align(VerticalAlign::Bottom));
+
+        let section = text.with_align(HAlign::Left, VAlign::Baseline).section([0., 0.].into(), size, &fonts);
+        let ascent = fonts[0].as_scaled(ab_glyph::PxScale { x: 25., y: 25. }).ascent();
+        assert!(ascent > 0.);
+        assert_eq!(section.screen_position.1, 50. - ascent, "ERROR: baseline aligned text should be raised by its ascent");
+        assert_eq!(section.layout, Layout::default_wrap().v_align(VerticalAlign::Top));
+    }
+
+    #[test]
+    fn test_px_scale() {
+        let font = FontArc::try_from_slice(INCONSOLATA).unwrap();
+        let glyph = font.glyph_id('W');
+        let square = font.as_scaled(px_scale(0.5, winit::dpi::PhysicalSize::new(100, 100))).h_advance(glyph);
+        let wide = font.as_scaled(px_scale(0.5, winit::dpi::PhysicalSize::new(300, 100))).h_advance(glyph);
+        assert!(square > 0.);
+        assert_eq!(square, wide, "ERROR: text should be as wide in pixels whatever the aspect ratio");
+        assert_eq!(px_scale(0.5, winit::dpi::PhysicalSize::new(100, 200)), ab_glyph::PxScale { x: 50., y: 50. });
+    }
+
+    #[test]
+    fn test_to_clip() {
+        let rect = ab_glyph::Rect { min: ab_glyph::point(0., 25.), max: ab_glyph::point(150., 100.) };
+        let [a, b] = to_clip(rect, winit::dpi::PhysicalSize::new(200, 100));
+        assert_eq!([a.x, a.y, b.x, b.y], [-1., 0.5, 0.5, -1.]);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::engine::grid::{ Coord, Grid, Tile };
+
+use super::{ Color, Mesh, Renderer };
+
+/// The variant of air tiles in `grid_templates`.
+pub const AIR: usize = 0;
+/// The variant of ground tiles in `grid_templates`.
+pub const GROUND: usize = 1;
+// buildings of tier 0 come right after ground, the rest follow in order
+const BUILDING: usize = 2;
+
+// how many lines are drawn across ground tiles
+const GROUND_LINES: u8 = 3;
+
+/// The templates of every kind of grid tile, for `Renderer::set_tile_templates`: air is a box, ground a crossed box
+/// and buildings a lined box with as many lines as their tier, up to `max_tier`.
+/// They are drawn in white, so tiles get the color they are drawn with.
+pub fn grid_templates(thickness: f32, max_tier: u8) -> Vec<Mesh> {
+    let corners = [[-0.5, 0.5], [0.5, -0.5]];
+    let mut templates = Vec::with_capacity(BUILDING + max_tier as usize + 1);
+
+    let mut air = Mesh::new();
+    air.draw_box(corners, thickness, Color::WHITE);
+    templates.push(air);
+
+    let mut ground = Mesh::new();
+    ground.draw_crossed_box(corners, thickness, Color::WHITE, GROUND_LINES);
+    templates.push(ground);
+
+    for tier in 0..=max_tier {
+        let mut building = Mesh::new();
+        building.draw_lined_box(corners, thickness, Color::WHITE, tier, false);
+        templates.push(building);
+    }
+
+    return templates;
+}
+
+/// The variant a tile is drawn with from `grid_templates`, buildings above `max_tier` look like ones of `max_tier`.
+pub fn grid_variant(tile: &Tile, max_tier: u8) -> usize {
+    match tile {
+        Tile::Air => AIR,
+        Tile::Ground => GROUND,
+        // float to int casts saturate, negative tiers are tier 0
+        Tile::Building { tier, .. } => BUILDING + (*tier as u8).min(max_tier) as usize,
+    }
+}
+
+/// Draws the tiles in the rectangle between two corners, both included, with the renderer's tile pipeline,
+/// in the colors `color` picks for them. The renderer needs `grid_templates` of the same `max_tier` as its tile templates.
+pub fn draw_grid<C, F>(renderer: &mut Renderer, grid: &Grid, corner_a: C, corner_b: C, max_tier: u8, mut color: F)
+where
+    C: Into<Coord>,
+    F: FnMut(Coord, &Tile) -> Color,
+{
+    for (coord, tile) in grid.iter_region(corner_a, corner_b) {
+        renderer.draw_tile([coord.x() as f32, coord.y() as f32], grid_variant(tile, max_tier), color(coord, tile));
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----- TEMPLATE TESTS -----
+    #[test]
+    fn test_grid_templates() {
+        let templates = grid_templates(0.03, 4);
+        assert_eq!(templates.len(), 7, "ERROR: there should be a template for air, ground and every tier up to the max");
+        assert!(templates.iter().all(|template| !template.is_empty()));
+
+        let mut ground = Mesh::new();
+        ground.draw_crossed_box([[-0.5, 0.5], [0.5, -0.5]], 0.03, Color::WHITE, GROUND_LINES);
+        assert_eq!(templates[GROUND], ground);
+
+        // every tier has more lines than the one before
+        for tier in BUILDING + 1..templates.len() {
+            assert!(templates[tier].vertices.len() > templates[tier - 1].vertices.len());
+        }
+    }
+
+    #[test]
+    fn test_grid_variant() {
+        let building = |tier: f32| Tile::Building { health: 100., tier, pressure: 0. };
+        assert_eq!(grid_variant(&Tile::Air, 4), AIR);
+        assert_eq!(grid_variant(&Tile::Ground, 4), GROUND);
+        assert_eq!(grid_variant(&building(0.), 4), BUILDING);
+        assert_eq!(grid_variant(&building(3.), 4), BUILDING + 3);
+        assert_eq!(grid_variant(&building(9.), 4), BUILDING + 4, "ERROR: tiers above the max should use the max's template");
+        assert_eq!(grid_variant(&building(-1.), 4), BUILDING);
+        assert!(grid_variant(&building(9.), 4) < grid_templates(0.03, 4).len());
+    }
+}
@@ -0,0 +1,40 @@
+// how shapes are filled, prepended to the shaders drawing them, see Fill in draw/fill.rs
+const FILL_SOLID: u32 = 0u;
+const FILL_LINEAR: u32 = 1u;
+const FILL_RADIAL: u32 = 2u;
+const FILL_HATCH: u32 = 3u;
+const FILL_CROSSHATCH: u32 = 4u;
+
+// how much of a pixel the nearest line of a hatch covers, with lines centered on every multiple of spacing
+fn hatch(distance: f32, pixel: f32, spacing: f32, width: f32) -> f32 {
+    let from_line = abs(fract(distance / spacing + 0.5) - 0.5) * spacing;
+    return clamp((width / 2.0 - from_line) / max(pixel, 0.000001) + 0.5, 0.0, 1.0);
+}
+
+// how far a position is across and along the lines of a hatch, the fragment shaders take their fwidth for fill,
+// which can't be done in functions the vertex shaders could see too
+fn hatch_distances(position: vec2<f32>, params: vec4<f32>) -> vec2<f32> {
+    return vec2<f32>(dot(position, vec2<f32>(-params.y, params.x)), dot(position, params.xy));
+}
+
+// the color of a shape at a position in the space it was drawn in
+fn fill(kind: u32, position: vec2<f32>, color: vec4<f32>, fill_color: vec4<f32>, params: vec4<f32>, pixel: vec2<f32>) -> vec4<f32> {
+    if kind == FILL_LINEAR {
+        let direction = params.zw - params.xy;
+        let t = clamp(dot(position - params.xy, direction) / max(dot(direction, direction), 0.000001), 0.0, 1.0);
+        return mix(color, fill_color, t);
+    }
+    if kind == FILL_RADIAL {
+        let t = clamp(distance(position, params.xy) / max(params.z, 0.000001), 0.0, 1.0);
+        return mix(color, fill_color, t);
+    }
+    if kind == FILL_HATCH || kind == FILL_CROSSHATCH {
+        let distances = hatch_distances(position, params);
+        var coverage = hatch(distances.x, pixel.x, params.z, params.w);
+        if kind == FILL_CROSSHATCH {
+            coverage = max(coverage, hatch(distances.y, pixel.y, params.z, params.w));
+        }
+        return mix(fill_color, color, coverage);
+    }
+    return color;
+}
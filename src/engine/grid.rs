@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::fmt;
 #[allow(unused_imports)]
 use std::cmp::{ Ord, Ordering };
+use std::ops::{ Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign };
 
 #[allow(dead_code)]
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -31,149 +32,665 @@ impl From<(i32, i32)> for Coord {
         return Coord{ x: tup.0, y: tup.1};
     }
 }
+impl From<Coord> for [i32;2] {
+    fn from(coord: Coord) -> Self {
+        return [coord.x, coord.y];
+    }
+}
+impl Add for Coord {
+    type Output = Coord;
+
+    fn add(self, other: Coord) -> Coord {
+        return Coord { x: self.x + other.x, y: self.y + other.y };
+    }
+}
+impl Sub for Coord {
+    type Output = Coord;
+
+    fn sub(self, other: Coord) -> Coord {
+        return Coord { x: self.x - other.x, y: self.y - other.y };
+    }
+}
+impl Mul<i32> for Coord {
+    type Output = Coord;
+
+    fn mul(self, scalar: i32) -> Coord {
+        return Coord { x: self.x * scalar, y: self.y * scalar };
+    }
+}
+impl Div<i32> for Coord {
+    type Output = Coord;
+
+    /// Rounds towards zero, like dividing an i32.
+    fn div(self, scalar: i32) -> Coord {
+        return Coord { x: self.x / scalar, y: self.y / scalar };
+    }
+}
+impl Neg for Coord {
+    type Output = Coord;
+
+    fn neg(self) -> Coord {
+        return Coord { x: -self.x, y: -self.y };
+    }
+}
+impl AddAssign for Coord {
+    fn add_assign(&mut self, other: Coord) {
+        *self = *self + other;
+    }
+}
+impl SubAssign for Coord {
+    fn sub_assign(&mut self, other: Coord) {
+        *self = *self - other;
+    }
+}
+impl MulAssign<i32> for Coord {
+    fn mul_assign(&mut self, scalar: i32) {
+        *self = *self * scalar;
+    }
+}
+impl DivAssign<i32> for Coord {
+    fn div_assign(&mut self, scalar: i32) {
+        *self = *self / scalar;
+    }
+}
 #[allow(dead_code)]
 impl Coord {
-    const ORIGIN: Coord = Coord{ x: 0, y: 0 };
+    pub const ORIGIN: Coord = Coord{ x: 0, y: 0 };
+
+    pub const fn new(x: i32, y: i32) -> Coord {
+        return Coord { x, y };
+    }
+
+    pub fn x(&self) -> i32 {
+        return self.x;
+    }
+
+    pub fn y(&self) -> i32 {
+        return self.y;
+    }
+
+    /// The coord next to this one in the given direction.
+    pub fn step(&self, direction: Direction) -> Coord {
+        return *self + direction.offset();
+    }
+
+    /// Like `step`, but None instead of overflowing past the edge of the i32 range.
+    pub fn checked_step(&self, direction: Direction) -> Option<Coord> {
+        return self.checked_add(direction.offset());
+    }
+
+    pub fn checked_add(&self, other: Coord) -> Option<Coord> {
+        return Some(Coord { x: self.x.checked_add(other.x)?, y: self.y.checked_add(other.y)? });
+    }
+
+    pub fn checked_sub(&self, other: Coord) -> Option<Coord> {
+        return Some(Coord { x: self.x.checked_sub(other.x)?, y: self.y.checked_sub(other.y)? });
+    }
+
+    /// Steps needed to reach other moving only up, down, left and right.
+    pub fn manhattan_distance(&self, other: Coord) -> u64 {
+        return u64::from(self.x.abs_diff(other.x)) + u64::from(self.y.abs_diff(other.y));
+    }
+
+    /// Steps needed to reach other when diagonal steps are allowed too.
+    pub fn chebyshev_distance(&self, other: Coord) -> u32 {
+        return self.x.abs_diff(other.x).max(self.y.abs_diff(other.y));
+    }
+
+    /// The coords on a straight line from this coord to other, both included.
+    /// Consecutive coords are always next to each other, possibly diagonally.
+    pub fn line(&self, other: Coord) -> Line {
+        let dx = i64::from(other.x) - i64::from(self.x);
+        let dy = i64::from(other.y) - i64::from(self.y);
+        return Line {
+            current: Some(*self),
+            end: other,
+            delta: [dx.abs(), -dy.abs()],
+            sign: [dx.signum() as i32, dy.signum() as i32],
+            error: dx.abs() - dy.abs(),
+        };
+    }
 
     fn spread(&self, other: Coord) -> Vec<Coord> {
-        let mut output: Vec<Coord> = Vec::new();
+        return self.spread_iter(other).collect();
+    }
 
-        for x in self.x..=other.x {
-            for y in self.y..=other.y {
-                output.push(Coord { x, y })
-            }
+    /// Iterates the same coords as `spread`, column by column, without collecting them.
+    pub fn spread_iter(&self, other: Coord) -> Spread {
+        let next = if self.x <= other.x && self.y <= other.y { Some(*self) } else { None };
+        return Spread { min_y: self.y, max: other, next };
+    }
+}
+
+/// The eight directions a coord has neighbours in, up being towards positive y.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+#[allow(dead_code)]
+impl Direction {
+    /// Up, right, down and left, clockwise.
+    pub const CARDINAL: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+    /// Every direction, clockwise starting from up.
+    pub const ALL: [Direction; 8] = [
+        Direction::Up,
+        Direction::UpRight,
+        Direction::Right,
+        Direction::DownRight,
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::Left,
+        Direction::UpLeft,
+    ];
+
+    /// The step one tile in this direction.
+    pub fn offset(&self) -> Coord {
+        let (x, y) = match self {
+            Direction::Up => (0, 1),
+            Direction::UpRight => (1, 1),
+            Direction::Right => (1, 0),
+            Direction::DownRight => (1, -1),
+            Direction::Down => (0, -1),
+            Direction::DownLeft => (-1, -1),
+            Direction::Left => (-1, 0),
+            Direction::UpLeft => (-1, 1),
+        };
+        return Coord { x, y };
+    }
+
+    pub fn opposite(&self) -> Direction {
+        return Direction::ALL[(*self as usize + 4) % 8];
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        return *self as usize % 2 == 1;
+    }
+}
+
+/// Iterator over the coords of a line, see `Coord::line`.
+#[derive(Debug, Clone)]
+pub struct Line {
+    current: Option<Coord>,
+    end: Coord,
+    delta: [i64; 2],
+    sign: [i32; 2],
+    error: i64,
+}
+impl Iterator for Line {
+    type Item = Coord;
+
+    // bresenham's, in i64 so lines spanning the whole i32 range don't overflow
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current?;
+        if current == self.end {
+            self.current = None;
+            return Some(current);
         }
 
-        return output;
+        let mut next = current;
+        let doubled = self.error * 2;
+        if doubled >= self.delta[1] {
+            self.error += self.delta[1];
+            next.x += self.sign[0];
+        }
+        if doubled <= self.delta[0] {
+            self.error += self.delta[0];
+            next.y += self.sign[1];
+        }
+        self.current = Some(next);
+        return Some(current);
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Iterator over the coords of a rectangle, see `Coord::spread_iter`.
+#[derive(Debug, Clone)]
+pub struct Spread {
+    min_y: i32,
+    max: Coord,
+    next: Option<Coord>,
+}
+impl Iterator for Spread {
+    type Item = Coord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+
+        // compared before stepping, so rects reaching the edge of the i32 range end instead of overflowing
+        self.next = if current.y < self.max.y {
+            Some(Coord { x: current.x, y: current.y + 1 })
+        } else if current.x < self.max.x {
+            Some(Coord { x: current.x + 1, y: self.min_y })
+        } else {
+            None
+        };
+        return Some(current);
+    }
+}
+
+/// Width and height of the blocks the grid is stored in.
+pub const CHUNK_SIZE: i32 = 32;
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+// a CHUNK_SIZE x CHUNK_SIZE block of the grid, stored column by column so that columns are contiguous
+#[derive(Clone)]
+struct Chunk {
+    origin: Coord,
+    tiles: Box<[Option<Tile>; CHUNK_AREA]>,
+    // counts the times the chunk was borrowed mutably, see Grid::chunk_version
+    version: u64,
+}
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        return self.origin == other.origin && self.tiles == other.tiles;
+    }
+}
+impl Chunk {
+    fn new(origin: Coord) -> Chunk {
+        return Chunk { origin, tiles: Box::new([None; CHUNK_AREA]), version: 0 };
+    }
+
+    fn touch(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+
+    fn coord(origin: Coord, index: usize) -> Coord {
+        return Coord {
+            x: origin.x + (index / CHUNK_SIZE as usize) as i32,
+            y: origin.y + (index % CHUNK_SIZE as usize) as i32,
+        };
+    }
+
+    fn overlaps(&self, region: &Option<(Coord, Coord)>) -> bool {
+        match region {
+            Some((min, max)) => {
+                // the far edge is computed inclusively, so chunks at the very end of the i32 range don't overflow
+                let far_x = self.origin.x + (CHUNK_SIZE - 1);
+                let far_y = self.origin.y + (CHUNK_SIZE - 1);
+                return self.origin.x <= max.x && far_x >= min.x && self.origin.y <= max.y && far_y >= min.y;
+            },
+            None => return true,
+        }
+    }
+}
+
+// the chunk a coord falls in, as the coord of its origin, and the coord's index inside it
+fn chunk_index(coord: Coord) -> (Coord, usize) {
+    let origin = Coord {
+        x: coord.x.div_euclid(CHUNK_SIZE) * CHUNK_SIZE,
+        y: coord.y.div_euclid(CHUNK_SIZE) * CHUNK_SIZE,
+    };
+    let index = ((coord.x - origin.x) * CHUNK_SIZE + (coord.y - origin.y)) as usize;
+    return (origin, index);
+}
+
+fn in_region(coord: Coord, region: &Option<(Coord, Coord)>) -> bool {
+    match region {
+        Some((min, max)) => return min.x <= coord.x && coord.x <= max.x && min.y <= coord.y && coord.y <= max.y,
+        None => return true,
+    }
+}
+
+// the bottom left and top right corners of the rectangle between two corners
+fn region(corner_a: Coord, corner_b: Coord) -> (Coord, Coord) {
+    return (
+        Coord { x: corner_a.x.min(corner_b.x), y: corner_a.y.min(corner_b.y) },
+        Coord { x: corner_a.x.max(corner_b.x), y: corner_a.y.max(corner_b.y) },
+    );
+}
+
+/// Tiles stored in dense CHUNK_SIZE x CHUNK_SIZE chunks, so lookups are a hash of the chunk plus an array index
+/// and iterating doesn't allocate.
+///
+/// Iteration goes chunk by chunk in order of their coordinates, and column by column inside each chunk,
+/// so it doesn't depend on the order tiles were inserted in. Use `to_ref_vec` for tiles fully sorted by coord.
+#[derive(Clone)]
 pub struct Grid {
-    grid: HashMap<i32, HashMap<i32, Tile>>,
+    // sorted by origin
+    chunks: Vec<Chunk>,
+    // chunk origin to its position in chunks
+    index: HashMap<Coord, usize>,
+    len: usize,
+}
+impl PartialEq for Grid {
+    fn eq(&self, other: &Self) -> bool {
+        // chunks are never removed, so grids with the same tiles have the same chunks
+        return self.len == other.len && self.chunks == other.chunks;
+    }
+}
+impl fmt::Debug for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.debug_map().entries(self.iter()).finish();
+    }
+}
+
+/// Iterator over the tiles of a grid or a region of it, see `Grid::iter` and `Grid::iter_region`.
+pub struct Iter<'a> {
+    chunks: std::slice::Iter<'a, Chunk>,
+    current: Option<(Coord, std::iter::Enumerate<std::slice::Iter<'a, Option<Tile>>>)>,
+    region: Option<(Coord, Coord)>,
 }
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Coord, &'a Tile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((origin, tiles)) = &mut self.current {
+                for (index, tile) in tiles {
+                    if let Some(tile) = tile {
+                        let coord = Chunk::coord(*origin, index);
+                        if in_region(coord, &self.region) {
+                            return Some((coord, tile));
+                        }
+                    }
+                }
+            }
+            let region = &self.region;
+            let chunk = self.chunks.find(|chunk| chunk.overlaps(region))?;
+            self.current = Some((chunk.origin, chunk.tiles.iter().enumerate()));
+        }
+    }
+}
+
+/// Mutable iterator over the tiles of a grid or a region of it, see `Grid::iter_mut` and `Grid::iter_region_mut`.
+pub struct IterMut<'a> {
+    chunks: std::slice::IterMut<'a, Chunk>,
+    current: Option<(Coord, std::iter::Enumerate<std::slice::IterMut<'a, Option<Tile>>>)>,
+    region: Option<(Coord, Coord)>,
+}
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (Coord, &'a mut Tile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((origin, tiles)) = &mut self.current {
+                for (index, tile) in tiles {
+                    if let Some(tile) = tile {
+                        let coord = Chunk::coord(*origin, index);
+                        if in_region(coord, &self.region) {
+                            return Some((coord, tile));
+                        }
+                    }
+                }
+            }
+            let region = &self.region;
+            let chunk = self.chunks.find(|chunk| chunk.overlaps(region))?;
+            chunk.touch();
+            self.current = Some((chunk.origin, chunk.tiles.iter_mut().enumerate()));
+        }
+    }
+}
+
+/// Owning iterator over the tiles of a grid.
+pub struct IntoIter {
+    chunks: std::vec::IntoIter<Chunk>,
+    current: Option<(Coord, std::iter::Enumerate<std::array::IntoIter<Option<Tile>, CHUNK_AREA>>)>,
+}
+impl Iterator for IntoIter {
+    type Item = (Coord, Tile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((origin, tiles)) = &mut self.current {
+                for (index, tile) in tiles {
+                    if let Some(tile) = tile {
+                        return Some((Chunk::coord(*origin, index), tile));
+                    }
+                }
+            }
+            let chunk = self.chunks.next()?;
+            self.current = Some((chunk.origin, (*chunk.tiles).into_iter().enumerate()));
+        }
+    }
+}
+
+/// Iterator over the tiles in a rectangle of a grid in coord order, see `Grid::iter_rect`.
+pub struct Rect<'a> {
+    grid: &'a Grid,
+    coords: Spread,
+}
+impl<'a> Iterator for Rect<'a> {
+    type Item = (Coord, &'a Tile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let grid = self.grid;
+        return self.coords.find_map(|coord| Some((coord, grid.get(coord)?)));
+    }
+}
+
+/// Iterator over the tiles around a coord, see `Grid::neighbours` and `Grid::surrounding`.
+pub struct Neighbours<'a> {
+    grid: &'a Grid,
+    center: Coord,
+    directions: std::slice::Iter<'static, Direction>,
+}
+impl<'a> Iterator for Neighbours<'a> {
+    type Item = (Coord, &'a Tile);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (grid, center) = (self.grid, self.center);
+        return self.directions.find_map(|direction| {
+            // there is nothing past the edge of the i32 range
+            let coord = center.checked_step(*direction)?;
+            Some((coord, grid.get(coord)?))
+        });
+    }
+}
+
 impl IntoIterator for Grid {
     type Item = (Coord, Tile);
-    type IntoIter = std::collections::hash_map::IntoIter<Coord, Tile>;
+    type IntoIter = IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        return self.flatten().into_iter();
+        return IntoIter { chunks: self.chunks.into_iter(), current: None };
     }
 }
 impl<'a> IntoIterator for &'a Grid {
     type Item = (Coord, &'a Tile);
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = Iter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        return self.to_ref_vec().into_iter();
+        return self.iter();
     }
 }
 impl<'a> IntoIterator for &'a mut Grid {
     type Item = (Coord, &'a mut Tile);
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = IterMut<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        return self.to_mut_ref_vec().into_iter();
+        return self.iter_mut();
+    }
+}
+impl From<Grid> for HashMap<i32, HashMap<i32, Tile>> {
+    fn from(grid: Grid) -> Self {
+        let mut map: HashMap<i32, HashMap<i32, Tile>> = HashMap::new();
+        for (coord, tile) in grid {
+            map.entry(coord.x).or_default().insert(coord.y, tile);
+        }
+        return map;
+    }
+}
+impl From<HashMap<i32, HashMap<i32, Tile>>> for Grid {
+    fn from(map: HashMap<i32, HashMap<i32, Tile>>) -> Self {
+        let mut grid = Grid::new::<Coord>(Vec::new());
+        for (x, col) in map {
+            for (y, tile) in col {
+                grid.insert((x, y), tile);
+            }
+        }
+        return grid;
     }
 }
 #[allow(dead_code)]
 impl Grid {
     pub fn new<C: Into<Coord>>(tiles: Vec<(C, Tile)>) -> Grid {
-        let mut grid = HashMap::new();
+        let mut grid = Grid {
+            chunks: Vec::new(),
+            index: HashMap::new(),
+            len: 0,
+        };
 
         for (coord_like, tile) in tiles {
-            let coord: Coord = coord_like.into();
-            let x = coord.x;
-            let y = coord.y;
-
-            if !grid.contains_key(&x) {
-                grid.insert(x, HashMap::from([
-                    (y, tile)
-                ]));
-            } else{
-                grid.get_mut(&x)
-                    .unwrap()
-                    .insert(y, tile);
-            }
+            grid.insert(coord_like, tile);
         }
         
-        return Grid{grid};
+        return grid;
     }
 
-    pub fn flatten(&self) -> HashMap<Coord, Tile> {
-        let mut flat_grid: HashMap<Coord, Tile> = HashMap::new();
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
 
-        for (x, col) in &self.grid {
-            for (y, tile) in col {
-                flat_grid.insert(Coord { x: *x, y: *y }, *tile);
-            }
-        }
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
 
-        return flat_grid;
+    pub fn flatten(&self) -> HashMap<Coord, Tile> {
+        return self.iter().map(|(coord, tile)| (coord, *tile)).collect();
     }
 
     pub fn get<C: Into<Coord>>(&self, coord_like: C) -> Option<&Tile> {
-        let coord: Coord = coord_like.into();
+        let (origin, index) = chunk_index(coord_like.into());
 
-        if let Some(col) = self.grid.get(&coord.x) {
-            return col.get(&coord.y);
+        if let Some(&chunk) = self.index.get(&origin) {
+            return self.chunks[chunk].tiles[index].as_ref();
         } else {
             return None;
         }
     }
     pub fn get_mut<C: Into<Coord>>(&mut self, coord_like: C) -> Option<&mut Tile> {
-        let coord: Coord = coord_like.into();
+        let (origin, index) = chunk_index(coord_like.into());
 
-        if let Some(col) = self.grid.get_mut(&coord.x) {
-            return col.get_mut(&coord.y);
+        if let Some(&chunk) = self.index.get(&origin) {
+            let chunk = &mut self.chunks[chunk];
+            chunk.touch();
+            return chunk.tiles[index].as_mut();
         } else {
             return None;
         }
     }
 
     pub fn insert<C: Into<Coord>>(&mut self, coord_like: C, tile: Tile) {
-        let coord: Coord = coord_like.into();
-        let x = coord.x;
-        let y = coord.y;
-
-        if !self.grid.contains_key(&x) {
-            self.grid.insert(x, HashMap::from([
-                (y, tile)
-            ]));
-        } else{
-            self.grid.get_mut(&x)
-                .unwrap()
-                .insert(y, tile);
+        let (origin, index) = chunk_index(coord_like.into());
+
+        let chunk = match self.index.get(&origin) {
+            Some(&chunk) => chunk,
+            None => {
+                // new chunks are rare, so keeping them sorted by shifting the ones after is cheap enough
+                let chunk = self.chunks.partition_point(|chunk| chunk.origin < origin);
+                self.chunks.insert(chunk, Chunk::new(origin));
+                for (i, shifted) in self.chunks.iter().enumerate().skip(chunk) {
+                    self.index.insert(shifted.origin, i);
+                }
+                chunk
+            },
+        };
+
+        self.chunks[chunk].touch();
+        if self.chunks[chunk].tiles[index].replace(tile).is_none() {
+            self.len += 1;
         }
     }
 
-    pub fn to_ref_vec(&self) -> Vec<(Coord, &Tile)> {
-        let mut ref_vec: Vec<(Coord, &Tile)> = Vec::new();
+    /// The bottom left corner of the chunk a coord is stored in.
+    pub fn chunk_origin<C: Into<Coord>>(coord_like: C) -> Coord {
+        return chunk_index(coord_like.into()).0;
+    }
 
-        for (x, col) in &self.grid {
-            for (y, tile) in col {
-                ref_vec.push((Coord { x: *x, y: *y }, tile));
-            }
-        }
+    /// A number that changes whenever the chunk holding a coord may have changed, None if there is no chunk there yet.
+    /// Every mutable borrow of a tile counts as a change, whether it's written or not, so caches built from
+    /// chunks can be rebuilt only for the chunks whose version moved on since.
+    pub fn chunk_version<C: Into<Coord>>(&self, coord_like: C) -> Option<u64> {
+        let origin = Grid::chunk_origin(coord_like);
+        return self.index.get(&origin).map(|&chunk| self.chunks[chunk].version);
+    }
+
+    /// The origins of the chunks overlapping the rectangle between two corners, both included, in order.
+    pub fn chunks_in<C: Into<Coord>>(&self, corner_a: C, corner_b: C) -> Vec<Coord> {
+        let region = Some(region(corner_a.into(), corner_b.into()));
+        return self.chunks.iter()
+            .filter(|chunk| chunk.overlaps(&region))
+            .map(|chunk| chunk.origin)
+            .collect();
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        return Iter { chunks: self.chunks.iter(), current: None, region: None };
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        return IterMut { chunks: self.chunks.iter_mut(), current: None, region: None };
+    }
+
+    /// Iterates the tiles in the rectangle between two corners, both included.
+    pub fn iter_region<C: Into<Coord>>(&self, corner_a: C, corner_b: C) -> Iter<'_> {
+        return Iter { chunks: self.chunks.iter(), current: None, region: Some(region(corner_a.into(), corner_b.into())) };
+    }
+
+    /// Mutably iterates the tiles in the rectangle between two corners, both included.
+    pub fn iter_region_mut<C: Into<Coord>>(&mut self, corner_a: C, corner_b: C) -> IterMut<'_> {
+        return IterMut { chunks: self.chunks.iter_mut(), current: None, region: Some(region(corner_a.into(), corner_b.into())) };
+    }
+
+    /// Iterates the tiles in the rectangle between two corners, both included, in the same order as `to_ref_vec`.
+    ///
+    /// Looks up every coord in the rectangle, so it's best for small ones like the visible part of the grid,
+    /// use `iter_region` for large or mostly empty ones.
+    pub fn iter_rect<C: Into<Coord>>(&self, corner_a: C, corner_b: C) -> Rect<'_> {
+        let (min, max) = region(corner_a.into(), corner_b.into());
+        return Rect { grid: self, coords: min.spread_iter(max) };
+    }
+
+    /// The tiles above, right of, below and left of a coord, in that order.
+    pub fn neighbours<C: Into<Coord>>(&self, coord_like: C) -> Neighbours<'_> {
+        return Neighbours { grid: self, center: coord_like.into(), directions: Direction::CARDINAL.iter() };
+    }
+
+    /// The eight tiles around a coord including diagonals, clockwise starting from the one above it.
+    pub fn surrounding<C: Into<Coord>>(&self, coord_like: C) -> Neighbours<'_> {
+        return Neighbours { grid: self, center: coord_like.into(), directions: Direction::ALL.iter() };
+    }
+
+    /// The tiles with the given x, from the bottom up.
+    pub fn column(&self, x: i32) -> Iter<'_> {
+        return self.iter_region(Coord { x, y: i32::MIN }, Coord { x, y: i32::MAX });
+    }
+
+    /// The tiles with the given y, from left to right.
+    pub fn row(&self, y: i32) -> Iter<'_> {
+        return self.iter_region(Coord { x: i32::MIN, y }, Coord { x: i32::MAX, y });
+    }
+
+    pub fn to_ref_vec(&self) -> Vec<(Coord, &Tile)> {
+        let mut ref_vec: Vec<(Coord, &Tile)> = self.iter().collect();
 
         ref_vec.sort_unstable_by( |a, b| a.0.cmp(&b.0) );
         return ref_vec;
     }
 
     pub fn to_mut_ref_vec(&mut self) -> Vec<(Coord, &mut Tile)> {
-        let mut ref_vec: Vec<(Coord, &mut Tile)> = Vec::new();
-
-        for (&x, col) in &mut self.grid {
-            for (&y, tile) in col {
-                ref_vec.push((Coord { x, y }, tile));
-            }
-        }
+        let mut ref_vec: Vec<(Coord, &mut Tile)> = self.iter_mut().collect();
 
         ref_vec.sort_unstable_by( |a, b| a.0.cmp(&b.0) );
         return ref_vec;
     }
 }
 
+/// Like `get_matching!`, but lazily filters any of the grid's iterators instead of collecting a Vec,
+/// e.g. `iter_matching!(grid.iter_rect(a, b), (_, Tile::Building { .. }))`.
+#[allow(unused_macros)]
+macro_rules! iter_matching {
+    ($iter: expr, $pattern: pat) => {
+        $iter.filter(|item| matches!(item, $pattern))
+    };
+}
+
 #[allow(unused_macros)]
 macro_rules! get_matching {
     ($collection: expr, $pattern: pat) => {
@@ -227,6 +744,7 @@ mod tests {
     fn test_coord_from() {
         assert_eq!(Coord::from([3, 5]), Coord{ x: 3, y: 5 }, "ERROR: Failed assertion while converting from [i32;2] to Coord.");
         assert_eq!(Coord::from((3, 5)), Coord{ x: 3, y: 5 }, "ERROR: Failed assertion while converting from (i32, i32) to Coord.");
+        assert_eq!(<[i32;2]>::from(Coord{ x: 3, y: 5 }), [3, 5], "ERROR: Failed assertion while converting from Coord to [i32;2].");
     }
 
     #[test]
@@ -254,6 +772,107 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_coord_spread_iter() {
+        assert_eq!(Coord { x: 1, y: 0 }.spread_iter(Coord::ORIGIN).count(), 0);
+        assert_eq!(Coord::ORIGIN.spread_iter(Coord::ORIGIN).collect::<Vec<Coord>>(), vec![Coord::ORIGIN]);
+
+        // the edge of the i32 range doesn't overflow
+        let corner = Coord { x: i32::MAX - 1, y: i32::MAX - 1 };
+        let max = Coord { x: i32::MAX, y: i32::MAX };
+        assert_eq!(corner.spread_iter(max).collect::<Vec<Coord>>(), vec![
+            Coord { x: i32::MAX - 1, y: i32::MAX - 1 },
+            Coord { x: i32::MAX - 1, y: i32::MAX },
+            Coord { x: i32::MAX, y: i32::MAX - 1 },
+            Coord { x: i32::MAX, y: i32::MAX },
+        ]);
+    }
+
+
+    #[test]
+    fn test_coord_ops() {
+        let a = Coord::new(3, -5);
+        let b = Coord::new(-1, 2);
+
+        assert_eq!(a + b, Coord::new(2, -3));
+        assert_eq!(a - b, Coord::new(4, -7));
+        assert_eq!(a * 2, Coord::new(6, -10));
+        assert_eq!(a / 2, Coord::new(1, -2), "ERROR: division didn't round towards zero");
+        assert_eq!(-a, Coord::new(-3, 5));
+        assert_eq!((a.x(), a.y()), (3, -5));
+
+        let mut c = a;
+        c += b;
+        c -= Coord::new(1, 1);
+        c *= 3;
+        c /= 2;
+        assert_eq!(c, Coord::new(1, -6));
+
+        assert_eq!(Coord::new(i32::MAX, 0).checked_add(Coord::new(1, 0)), None);
+        assert_eq!(Coord::new(i32::MIN, 0).checked_sub(Coord::new(0, 1)), Some(Coord::new(i32::MIN, -1)));
+    }
+
+    #[test]
+    fn test_coord_step() {
+        for direction in Direction::ALL {
+            let stepped = Coord::ORIGIN.step(direction);
+            assert_eq!(stepped.step(direction.opposite()), Coord::ORIGIN, "ERROR: {:?} isn't the opposite of {:?}", direction.opposite(), direction);
+            assert_eq!(Coord::ORIGIN.chebyshev_distance(stepped), 1);
+            assert_eq!(Coord::ORIGIN.manhattan_distance(stepped), if direction.is_diagonal() { 2 } else { 1 });
+        }
+        for direction in Direction::CARDINAL {
+            assert!(!direction.is_diagonal());
+        }
+
+        assert_eq!(Coord::new(2, 2).step(Direction::UpLeft), Coord::new(1, 3));
+        assert_eq!(Coord::new(2, 2).step(Direction::Down), Coord::new(2, 1));
+        assert_eq!(Coord::new(i32::MAX, 0).checked_step(Direction::Right), None);
+        assert_eq!(Coord::new(i32::MAX, 0).checked_step(Direction::Left), Some(Coord::new(i32::MAX - 1, 0)));
+    }
+
+    #[test]
+    fn test_coord_distance() {
+        let a = Coord::new(1, -2);
+        let b = Coord::new(-3, 5);
+        assert_eq!(a.manhattan_distance(b), 11);
+        assert_eq!(a.chebyshev_distance(b), 7);
+        assert_eq!(b.manhattan_distance(a), 11);
+
+        let min = Coord::new(i32::MIN, i32::MIN);
+        let max = Coord::new(i32::MAX, i32::MAX);
+        assert_eq!(min.manhattan_distance(max), 2 * u64::from(u32::MAX));
+        assert_eq!(min.chebyshev_distance(max), u32::MAX);
+    }
+
+    #[test]
+    fn test_coord_line() {
+        assert_eq!(Coord::ORIGIN.line(Coord::new(4, 2)).collect::<Vec<Coord>>(), vec![
+            Coord::new(0, 0),
+            Coord::new(1, 1),
+            Coord::new(2, 1),
+            Coord::new(3, 2),
+            Coord::new(4, 2),
+        ]);
+        assert_eq!(Coord::new(2, 2).line(Coord::new(2, 2)).collect::<Vec<Coord>>(), vec![Coord::new(2, 2)]);
+        assert_eq!(Coord::new(1, 1).line(Coord::new(-2, -2)).count(), 4);
+
+        let ends = [(-7, 3), (5, 12), (0, -9), (13, -13), (i32::MIN, 0)];
+        for (x, y) in ends {
+            let end = Coord::new(x, y);
+            if end.chebyshev_distance(Coord::ORIGIN) > 100 {
+                // too long to walk, but mustn't overflow while starting off
+                assert_eq!(Coord::new(i32::MAX, 0).line(end).nth(2), Some(Coord::new(i32::MAX - 2, 0)));
+                continue;
+            }
+
+            let line: Vec<Coord> = Coord::ORIGIN.line(end).collect();
+            assert_eq!(line.len() as u32, Coord::ORIGIN.chebyshev_distance(end) + 1);
+            assert_eq!(line.last(), Some(&end));
+            for pair in line.windows(2) {
+                assert_eq!(pair[0].chebyshev_distance(pair[1]), 1, "ERROR: line from origin to {:?} has a gap", end);
+            }
+        }
+    }
 
     // ----- GRID TESTS -----
     #[test]
@@ -300,7 +919,7 @@ mod tests {
     fn test_grid_new() {
         let test_grid = create_test_grid();
 
-        assert_eq!(test_grid.grid, HashMap::from([
+        assert_eq!(HashMap::from(test_grid), HashMap::from([
             (0, HashMap::from([
                 (0, Tile::Air),
                 (1, Tile::Air),
@@ -377,6 +996,209 @@ mod tests {
         ]))
     }
 
+    // ----- CHUNK TESTS -----
+    #[test]
+    fn test_grid_chunk_edges() {
+        let coords = [
+            (-1, -1), (0, 0), (31, 31), (32, 32), (-32, -32), (-33, 31),
+            (i32::MIN, i32::MIN), (i32::MAX, i32::MAX), (i32::MIN, i32::MAX),
+        ];
+        let mut test_grid = Grid::new::<Coord>(Vec::new());
+        for (i, &coord) in coords.iter().enumerate() {
+            test_grid.insert(coord, Tile::Building { health: i as f32, tier: 1., pressure: 1. });
+        }
+
+        assert_eq!(test_grid.len(), coords.len());
+        for (i, &coord) in coords.iter().enumerate() {
+            assert_eq!(
+                test_grid.get(coord), Some(&Tile::Building { health: i as f32, tier: 1., pressure: 1. }),
+                "ERROR: tile at {:?} was lost", coord
+            );
+        }
+        assert_eq!(test_grid.get((-1, 0)), None);
+        assert_eq!(test_grid.get((i32::MAX, i32::MIN)), None);
+
+        // overwriting doesn't count the tile twice
+        test_grid.insert((31, 31), Tile::Air);
+        assert_eq!(test_grid.len(), coords.len());
+        assert_eq!(test_grid.iter().count(), coords.len());
+    }
+
+    #[test]
+    fn test_grid_iter_order() {
+        let mut coords = Vec::new();
+        for x in -40..40 {
+            for y in -40..40 {
+                coords.push(Coord { x, y });
+            }
+        }
+
+        let mut reversed = coords.clone();
+        reversed.reverse();
+        let forward_grid = Grid::new(coords.iter().map(|&coord| (coord, Tile::Air)).collect());
+        let backward_grid = Grid::new(reversed.iter().map(|&coord| (coord, Tile::Air)).collect());
+
+        assert_eq!(forward_grid, backward_grid);
+        assert!(forward_grid.iter().map(|(coord, _)| coord).eq(backward_grid.iter().map(|(coord, _)| coord)),
+            "ERROR: iteration order depends on insertion order");
+        assert_eq!(forward_grid.iter().count(), coords.len());
+
+        let sorted: Vec<Coord> = forward_grid.to_ref_vec().into_iter().map(|(coord, _)| coord).collect();
+        assert_eq!(sorted, coords);
+    }
+
+    #[test]
+    fn test_grid_iter_region() {
+        let mut test_grid = Grid::new::<Coord>(Vec::new());
+        for x in -50..50 {
+            for y in -50..50 {
+                test_grid.insert((x, y), Tile::Air);
+            }
+        }
+
+        let mut region: Vec<Coord> = test_grid.iter_region((40, -33), (-2, 31)).map(|(coord, _)| coord).collect();
+        region.sort();
+        let mut expected = Vec::new();
+        for x in -2..=40 {
+            for y in -33..=31 {
+                expected.push(Coord { x, y });
+            }
+        }
+        assert_eq!(region, expected);
+
+        for (_, tile) in test_grid.iter_region_mut((0, 0), (0, 1)) {
+            *tile = Tile::Ground;
+        }
+        assert_eq!(test_grid.iter().filter(|(_, tile)| **tile == Tile::Ground).count(), 2);
+        assert_eq!(test_grid.get((0, 1)), Some(&Tile::Ground));
+
+        assert_eq!(test_grid.iter_region((100, 100), (200, 200)).count(), 0);
+        assert_eq!(test_grid.iter_region((i32::MIN, i32::MIN), (i32::MAX, i32::MAX)).count(), test_grid.len());
+    }
+
+    #[test]
+    fn test_grid_hashmap_conversion() {
+        let test_grid = Grid::new(vec![((-100, 5), Tile::Ground), ((7, 1000), Tile::Air)]);
+        let map = HashMap::from(test_grid.clone());
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&-100][&5], Tile::Ground);
+        assert_eq!(Grid::from(map), test_grid);
+    }
+
+    #[test]
+    fn test_grid_chunk_version() {
+        let mut test_grid = Grid::new(vec![((0, 0), Tile::Air), ((40, 0), Tile::Air)]);
+        assert_eq!(Grid::chunk_origin((40, -1)), Coord { x: 32, y: -32 });
+        assert_eq!(test_grid.chunk_version((5, 5)), test_grid.chunk_version((0, 0)));
+        assert_eq!(test_grid.chunk_version((-1, 0)), None);
+
+        let first = test_grid.chunk_version((0, 0)).unwrap();
+        let second = test_grid.chunk_version((40, 0)).unwrap();
+
+        // reading doesn't change the version
+        test_grid.get((0, 0));
+        test_grid.iter().count();
+        assert_eq!(test_grid.chunk_version((0, 0)), Some(first));
+
+        test_grid.insert((1, 0), Tile::Ground);
+        assert_ne!(test_grid.chunk_version((0, 0)), Some(first), "ERROR: inserting didn't change the chunk's version");
+        assert_eq!(test_grid.chunk_version((40, 0)), Some(second), "ERROR: inserting changed another chunk's version");
+
+        let first = test_grid.chunk_version((0, 0)).unwrap();
+        test_grid.get_mut((40, 0));
+        assert_ne!(test_grid.chunk_version((40, 0)), Some(second));
+        assert_eq!(test_grid.chunk_version((0, 0)), Some(first));
+
+        let second = test_grid.chunk_version((40, 0)).unwrap();
+        test_grid.iter_region_mut((0, 0), (1, 1)).count();
+        assert_ne!(test_grid.chunk_version((0, 0)), Some(first));
+        assert_eq!(test_grid.chunk_version((40, 0)), Some(second), "ERROR: iterating a region changed a chunk outside of it");
+
+        // versions don't make grids with the same tiles unequal
+        assert_eq!(test_grid, Grid::new(vec![((0, 0), Tile::Air), ((1, 0), Tile::Ground), ((40, 0), Tile::Air)]));
+    }
+
+    #[test]
+    fn test_grid_chunks_in() {
+        let test_grid = Grid::new(vec![((0, 0), Tile::Air), ((40, 0), Tile::Air), ((-1, -1), Tile::Air), ((100, 100), Tile::Air)]);
+
+        assert_eq!(test_grid.chunks_in((-5, -5), (35, 5)), vec![
+            Coord { x: -32, y: -32 },
+            Coord { x: 0, y: 0 },
+            Coord { x: 32, y: 0 },
+        ]);
+        assert!(test_grid.chunks_in((200, 200), (300, 300)).is_empty());
+    }
+
+    // ----- REGION TESTS -----
+    #[test]
+    fn test_grid_iter_rect() {
+        let mut test_grid = create_test_grid();
+        test_grid.insert((-1, 5), Tile::Ground);
+        test_grid.insert((40, 1), Tile::Ground);
+
+        // corners can be given in any order, and empty coords are skipped
+        assert_eq!(test_grid.iter_rect((1, 5), (-1, 1)).collect::<Vec<(Coord, &Tile)>>(), vec![
+            (Coord{x: -1, y: 5}, &Tile::Ground),
+            (Coord{x: 0, y: 1}, &Tile::Air),
+            (Coord{x: 1, y: 1}, &Tile::Air),
+        ]);
+        assert!(test_grid.iter_rect((-100, -100), (100, 100)).eq(test_grid.to_ref_vec()),
+            "ERROR: iter_rect isn't in coord order");
+    }
+
+    #[test]
+    fn test_grid_neighbours() {
+        let test_grid = Grid::new(vec![
+            ((0, 1), Tile::Air),
+            ((1, 1), Tile::Ground),
+            ((-1, 0), Tile::Ground),
+            ((0, -1), Tile::Air),
+            ((5, 5), Tile::Ground),
+        ]);
+
+        assert_eq!(test_grid.neighbours((0, 0)).collect::<Vec<(Coord, &Tile)>>(), vec![
+            (Coord{x: 0, y: 1}, &Tile::Air),
+            (Coord{x: 0, y: -1}, &Tile::Air),
+            (Coord{x: -1, y: 0}, &Tile::Ground),
+        ]);
+        assert_eq!(test_grid.surrounding((0, 0)).collect::<Vec<(Coord, &Tile)>>(), vec![
+            (Coord{x: 0, y: 1}, &Tile::Air),
+            (Coord{x: 1, y: 1}, &Tile::Ground),
+            (Coord{x: 0, y: -1}, &Tile::Air),
+            (Coord{x: -1, y: 0}, &Tile::Ground),
+        ]);
+        assert_eq!(test_grid.surrounding((5, 5)).count(), 0);
+
+        let edge_grid = Grid::new(vec![((i32::MAX, i32::MIN), Tile::Air), ((i32::MAX - 1, i32::MIN), Tile::Ground)]);
+        assert_eq!(edge_grid.surrounding((i32::MAX, i32::MIN)).collect::<Vec<(Coord, &Tile)>>(), vec![
+            (Coord{x: i32::MAX - 1, y: i32::MIN}, &Tile::Ground),
+        ]);
+    }
+
+    #[test]
+    fn test_grid_column_row() {
+        let mut test_grid = Grid::new::<Coord>(Vec::new());
+        for i in -40..40 {
+            test_grid.insert((i, 3), Tile::Ground);
+            test_grid.insert((3, i), Tile::Ground);
+        }
+        test_grid.insert((4, 4), Tile::Air);
+
+        let column: Vec<Coord> = test_grid.column(3).map(|(coord, _)| coord).collect();
+        assert_eq!(column, (-40..40).map(|y| Coord { x: 3, y }).collect::<Vec<Coord>>(), "ERROR: column isn't in order");
+
+        let row: Vec<Coord> = test_grid.row(3).map(|(coord, _)| coord).collect();
+        assert_eq!(row, (-40..40).map(|x| Coord { x, y: 3 }).collect::<Vec<Coord>>(), "ERROR: row isn't in order");
+
+        assert_eq!(test_grid.column(4).collect::<Vec<(Coord, &Tile)>>(), vec![
+            (Coord{x: 4, y: 3}, &Tile::Ground),
+            (Coord{x: 4, y: 4}, &Tile::Air),
+        ]);
+        assert_eq!(test_grid.row(100).count(), 0);
+    }
+
     // ----- TEST MACROS -----
     #[test]
     fn test_get_matching() {
@@ -412,4 +1234,25 @@ mod tests {
             (Coord{x: 2, y: 1}, Tile::Ground),
         ]));
     }
+
+    #[test]
+    fn test_iter_matching() {
+        let test_grid = Grid::new(Vec::from([
+            (Coord{x: 0, y: 0}, Tile::Ground),
+            (Coord{x: 0, y: 1}, Tile::Air),
+            (Coord{x: 1, y: 0}, Tile::Ground),
+            (Coord{x: 1, y: 1}, Tile::Ground),
+            (Coord{x: 2, y: 0}, Tile::Ground),
+        ]));
+
+        let matched: Vec<(Coord, &Tile)> = iter_matching!(test_grid.iter_rect((0, 0), (1, 1)), (_, Tile::Ground)).collect();
+        assert_eq!(matched, Vec::from([
+            (Coord{x: 0, y: 0}, &Tile::Ground),
+            (Coord{x: 1, y: 0}, &Tile::Ground),
+            (Coord{x: 1, y: 1}, &Tile::Ground),
+        ]));
+
+        assert_eq!(iter_matching!(test_grid.row(0), (Coord { x: 1, .. }, _)).count(), 1);
+        assert_eq!(iter_matching!(test_grid.neighbours((0, 0)), (_, Tile::Air)).count(), 1);
+    }
 }
\ No newline at end of file
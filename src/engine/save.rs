@@ -0,0 +1,1530 @@
+use std::fmt;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::engine::grid::{ Grid, Coord, Direction, Tile };
+use crate::engine::settings::{ Settings, Setting };
+
+pub mod autosave;
+pub mod binary;
+pub mod slots;
+pub mod storage;
+pub mod thumbnail;
+
+pub use autosave::Autosave;
+pub use slots::{ SaveSlots, SaveSlot, SlotOrder };
+use thumbnail::{ Thumbnail, ThumbnailRect };
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Version { major, minor, patch }
+    }
+}
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+impl FromStr for Version {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        match parts[..] {
+            [major, minor, patch] => Ok(Version {
+                major: major.parse().map_err(|_| ())?,
+                minor: minor.parse().map_err(|_| ())?,
+                patch: patch.parse().map_err(|_| ())?,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Version of the save format written by this build.
+/// Bump it whenever the format changes and register a migration from the previous version below.
+pub const SAVE_VERSION: Version = Version::new(0, 3, 0);
+
+/// Saves without a `[Version: ...]` header predate it and are treated as the oldest format.
+const FIRST_VERSION: Version = Version::new(0, 1, 0);
+
+/// How deep setting lists can be nested, so a malformed save can't overflow the stack.
+const MAX_SETTING_DEPTH: usize = 16;
+
+/// Longest run of repeated tiles a save can hold, so a single corrupt count can't fill up memory.
+const MAX_RUN_LEN: u32 = 1 << 20;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    UnexpectedChar { found: char, position: Position },
+    UnexpectedToken { expected: &'static str, found: String, position: Position },
+    UnexpectedEof { expected: &'static str, position: Position },
+    InvalidNumber { text: String, position: Position },
+    UnknownHeader { name: String, position: Position },
+    UnknownBlock { name: String, position: Position },
+    DuplicateBlock { name: String, position: Position },
+    MissingBlock { name: &'static str },
+    UnknownField { block: &'static str, name: String, position: Position },
+    DuplicateField { block: &'static str, name: String, position: Position },
+    MissingField { block: &'static str, name: &'static str, position: Position },
+    UnknownTile { name: String, position: Position },
+    UnknownDirection { name: String, position: Position },
+    CoordOutOfRange { position: Position },
+    InvalidVersion { text: String, position: Position },
+    UnsupportedVersion { found: Version },
+    InvalidSetting { name: &'static str },
+    InvalidMagic,
+    ChecksumMismatch { expected: u32, found: u32 },
+    UnexpectedEndOfData { offset: usize },
+    InvalidTag { tag: u8, offset: usize },
+    InvalidUtf8 { offset: usize },
+    RunOutOfRange { offset: usize },
+    TrailingData { offset: usize },
+    NestedTooDeep { position: Position },
+    InvalidThumbnail,
+    InvalidSlotName { name: String },
+    SlotNotFound { name: String },
+    SlotExists { name: String },
+    DuplicateSlot { name: String },
+}
+impl SaveError {
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            SaveError::Io(_)
+            | SaveError::MissingBlock { .. }
+            | SaveError::UnsupportedVersion { .. }
+            | SaveError::InvalidSetting { .. }
+            | SaveError::InvalidMagic
+            | SaveError::ChecksumMismatch { .. }
+            | SaveError::UnexpectedEndOfData { .. }
+            | SaveError::InvalidTag { .. }
+            | SaveError::InvalidUtf8 { .. }
+            | SaveError::RunOutOfRange { .. }
+            | SaveError::TrailingData { .. }
+            | SaveError::InvalidThumbnail
+            | SaveError::InvalidSlotName { .. }
+            | SaveError::SlotNotFound { .. }
+            | SaveError::SlotExists { .. }
+            | SaveError::DuplicateSlot { .. } => None,
+            SaveError::UnexpectedChar { position, .. }
+            | SaveError::UnexpectedToken { position, .. }
+            | SaveError::UnexpectedEof { position, .. }
+            | SaveError::InvalidNumber { position, .. }
+            | SaveError::UnknownHeader { position, .. }
+            | SaveError::UnknownBlock { position, .. }
+            | SaveError::DuplicateBlock { position, .. }
+            | SaveError::UnknownField { position, .. }
+            | SaveError::DuplicateField { position, .. }
+            | SaveError::MissingField { position, .. }
+            | SaveError::UnknownTile { position, .. }
+            | SaveError::UnknownDirection { position, .. }
+            | SaveError::CoordOutOfRange { position }
+            | SaveError::NestedTooDeep { position }
+            | SaveError::InvalidVersion { position, .. } => Some(*position),
+        }
+    }
+}
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // errors managing save files aren't about their contents
+        match self {
+            SaveError::Io(_)
+            | SaveError::InvalidSlotName { .. }
+            | SaveError::SlotNotFound { .. }
+            | SaveError::SlotExists { .. }
+            | SaveError::DuplicateSlot { .. } => {},
+            _ => write!(f, "An error occurred while parsing the save file: ")?,
+        }
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::UnexpectedChar { found, position } => write!(f, "{}: unexpected character {:?}", position, found),
+            SaveError::UnexpectedToken { expected, found, position } => write!(f, "{}: expected {}, found {}", position, expected, found),
+            SaveError::UnexpectedEof { expected, position } => write!(f, "{}: expected {}, found end of file", position, expected),
+            SaveError::InvalidNumber { text, position } => write!(f, "{}: invalid number {:?}", position, text),
+            SaveError::UnknownHeader { name, position } => write!(f, "{}: unknown header [{}]", position, name),
+            SaveError::UnknownBlock { name, position } => write!(f, "{}: unknown block {:?}", position, name),
+            SaveError::DuplicateBlock { name, position } => write!(f, "{}: block {:?} appears more than once", position, name),
+            SaveError::MissingBlock { name } => write!(f, "missing block {:?}", name),
+            SaveError::UnknownField { block, name, position } => write!(f, "{}: unknown field {:?} in {}", position, name, block),
+            SaveError::DuplicateField { block, name, position } => write!(f, "{}: field {:?} appears more than once in {}", position, name, block),
+            SaveError::MissingField { block, name, position } => write!(f, "{}: missing field {:?} in {}", position, name, block),
+            SaveError::UnknownTile { name, position } => write!(f, "{}: unknown tile {:?}", position, name),
+            SaveError::UnknownDirection { name, position } => write!(f, "{}: unknown repeat direction {:?}, expected up or down", position, name),
+            SaveError::CoordOutOfRange { position } => write!(f, "{}: repeated tile run is too long or runs past the edge of the grid", position),
+            SaveError::InvalidVersion { text, position } => write!(f, "{}: invalid version {:?}, expected major.minor.patch", position, text),
+            SaveError::UnsupportedVersion { found } => write!(f, "the save is from version {} but this build only supports up to {}, update the game to load it", found, SAVE_VERSION),
+            SaveError::InvalidSetting { name } => write!(f, "setting {:?} has an unexpected value", name),
+            SaveError::InvalidMagic => write!(f, "not a binary save file"),
+            SaveError::ChecksumMismatch { expected, found } => write!(f, "checksum mismatch, expected {:08x} but the contents hash to {:08x}, the file is corrupted", expected, found),
+            SaveError::UnexpectedEndOfData { offset } => write!(f, "byte {}: unexpected end of data", offset),
+            SaveError::InvalidTag { tag, offset } => write!(f, "byte {}: invalid tag {}", offset, tag),
+            SaveError::InvalidUtf8 { offset } => write!(f, "byte {}: string is not valid UTF-8", offset),
+            SaveError::RunOutOfRange { offset } => write!(f, "byte {}: tile run is too long or runs past the edge of the grid", offset),
+            SaveError::TrailingData { offset } => write!(f, "byte {}: unexpected data after the end of the save", offset),
+            SaveError::InvalidThumbnail => write!(f, "the thumbnail has rectangles outside of it or is too large"),
+            SaveError::InvalidSlotName { name } => write!(f, "{:?} is not a valid save name, use letters, digits, spaces, '-' and '_'", name),
+            SaveError::SlotNotFound { name } => write!(f, "there is no save called {:?}", name),
+            SaveError::SlotExists { name } => write!(f, "a save called {:?} already exists", name),
+            SaveError::DuplicateSlot { name } => write!(f, "another save file is already called {:?}, rename one of them", name),
+            SaveError::NestedTooDeep { position } => write!(f, "{}: lists can be nested at most {} deep", position, MAX_SETTING_DEPTH),
+        }
+    }
+}
+impl Error for SaveError {}
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+// ----- TOKENIZER -----
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(String),
+    Str(String),
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    Colon,
+    Comma,
+    Eof,
+}
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(ident) => write!(f, "{:?}", ident),
+            TokenKind::Number(number) => write!(f, "{}", number),
+            TokenKind::Str(string) => write!(f, "{:?}", string),
+            TokenKind::OpenBrace => write!(f, "'{{'"),
+            TokenKind::CloseBrace => write!(f, "'}}'"),
+            TokenKind::OpenBracket => write!(f, "'['"),
+            TokenKind::CloseBracket => write!(f, "']'"),
+            TokenKind::OpenParen => write!(f, "'('"),
+            TokenKind::CloseParen => write!(f, "')'"),
+            TokenKind::Colon => write!(f, "':'"),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: Position,
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Lexer {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position { line: self.line, column: self.column }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, text: &mut String, f: F) {
+        while let Some(&c) = self.chars.peek() {
+            if !f(c) {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, SaveError> {
+        self.skip_whitespace();
+        let position = self.position();
+
+        let c = match self.chars.peek() {
+            Some(&c) => c,
+            None => return Ok(Token { kind: TokenKind::Eof, position }),
+        };
+
+        let kind = match c {
+            '{' => { self.bump(); TokenKind::OpenBrace },
+            '}' => { self.bump(); TokenKind::CloseBrace },
+            '[' => { self.bump(); TokenKind::OpenBracket },
+            ']' => { self.bump(); TokenKind::CloseBracket },
+            '(' => { self.bump(); TokenKind::OpenParen },
+            ')' => { self.bump(); TokenKind::CloseParen },
+            ':' => { self.bump(); TokenKind::Colon },
+            ',' => { self.bump(); TokenKind::Comma },
+            '"' => {
+                self.bump();
+                let mut text = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some(escaped) => text.push(escaped),
+                            None => return Err(SaveError::UnexpectedEof { expected: "closing '\"'", position: self.position() }),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(SaveError::UnexpectedEof { expected: "closing '\"'", position: self.position() }),
+                    }
+                }
+                TokenKind::Str(text)
+            },
+            c if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => {
+                let mut text = String::new();
+                text.push(c);
+                self.bump();
+                // letters are taken as well so that malformed numbers like `12a` are reported as a whole
+                while let Some(&c) = self.chars.peek() {
+                    let exponent_sign = (c == '-' || c == '+') && text.ends_with(['e', 'E']);
+                    if !(c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign) {
+                        break;
+                    }
+                    text.push(c);
+                    self.bump();
+                }
+                TokenKind::Number(text)
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let mut text = String::new();
+                self.take_while(&mut text, |c| c.is_alphanumeric() || c == '_');
+                TokenKind::Ident(text)
+            },
+            found => {
+                return Err(SaveError::UnexpectedChar { found, position });
+            }
+        };
+
+        Ok(Token { kind, position })
+    }
+
+    // used for headers such as `[Date: 2022-04-11 23:01:39.45]`, whose values aren't made of tokens
+    fn raw_until(&mut self, end: char) -> Result<String, SaveError> {
+        let mut text = String::new();
+        loop {
+            match self.chars.peek() {
+                Some(&c) if c == end => break,
+                Some('\n') | None => {
+                    return Err(SaveError::UnexpectedEof { expected: "closing ']' of header", position: self.position() });
+                },
+                Some(_) => text.push(self.bump().unwrap()),
+            }
+        }
+        Ok(text.trim().to_owned())
+    }
+}
+
+// ----- PARSER -----
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Token>,
+}
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Parser {
+            lexer: Lexer::new(source),
+            peeked: None,
+        }
+    }
+
+    fn peek(&mut self) -> Result<&Token, SaveError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token()?);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    fn next(&mut self) -> Result<Token, SaveError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    fn unexpected(token: Token, expected: &'static str) -> SaveError {
+        match token.kind {
+            TokenKind::Eof => SaveError::UnexpectedEof { expected, position: token.position },
+            kind => SaveError::UnexpectedToken { expected, found: kind.to_string(), position: token.position },
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &'static str) -> Result<Position, SaveError> {
+        let token = self.next()?;
+        if token.kind == kind {
+            Ok(token.position)
+        } else {
+            Err(Parser::unexpected(token, expected))
+        }
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> Result<bool, SaveError> {
+        if self.peek()?.kind == kind {
+            self.next()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn ident(&mut self, expected: &'static str) -> Result<(String, Position), SaveError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::Ident(ident) => Ok((ident, token.position)),
+            _ => Err(Parser::unexpected(token, expected)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &'static str) -> Result<Position, SaveError> {
+        let token = self.next()?;
+        match &token.kind {
+            TokenKind::Ident(ident) if ident.eq_ignore_ascii_case(keyword) => Ok(token.position),
+            _ => Err(Parser::unexpected(token, keyword)),
+        }
+    }
+
+    fn number<N: std::str::FromStr>(&mut self) -> Result<N, SaveError> {
+        let token = self.next()?;
+        match token.kind {
+            // idents are let through so that the writer's `inf` and `NaN` read back as floats
+            TokenKind::Number(text) | TokenKind::Ident(text) => text.parse().map_err(|_| SaveError::InvalidNumber { text, position: token.position }),
+            _ => Err(Parser::unexpected(token, "a number")),
+        }
+    }
+
+    // parses `{ name: value, ... }`, leaving the value after each colon to `field`
+    fn fields<F>(&mut self, mut field: F) -> Result<Position, SaveError>
+    where
+        F: FnMut(&mut Self, &str, Position) -> Result<(), SaveError>
+    {
+        let open = self.expect(TokenKind::OpenBrace, "'{'")?;
+        loop {
+            if self.eat(TokenKind::CloseBrace)? {
+                return Ok(open);
+            }
+            let (name, position) = self.ident("a field name or '}'")?;
+            self.expect(TokenKind::Colon, "':'")?;
+            field(self, &name.to_lowercase(), position)?;
+            if !self.eat(TokenKind::Comma)? && self.peek()?.kind != TokenKind::CloseBrace {
+                return Err(Parser::unexpected(self.next()?, "',' or '}'"));
+            }
+        }
+    }
+
+    fn coord(&mut self, block: &'static str) -> Result<Coord, SaveError> {
+        let mut x = None;
+        let mut y = None;
+        let open = self.fields(|parser, name, position| {
+            let slot = match name {
+                "x" => &mut x,
+                "y" => &mut y,
+                _ => return Err(SaveError::UnknownField { block, name: name.to_owned(), position }),
+            };
+            if slot.is_some() {
+                return Err(SaveError::DuplicateField { block, name: name.to_owned(), position });
+            }
+            *slot = Some(parser.number::<i32>()?);
+            Ok(())
+        })?;
+
+        let x = x.ok_or(SaveError::MissingField { block, name: "x", position: open })?;
+        let y = y.ok_or(SaveError::MissingField { block, name: "y", position: open })?;
+        Ok(Coord::from((x, y)))
+    }
+
+    fn money(&mut self) -> Result<i128, SaveError> {
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+        let money = self.number()?;
+        self.expect(TokenKind::CloseBrace, "'}'")?;
+        Ok(money)
+    }
+
+    fn setting(&mut self, depth: usize) -> Result<Setting, SaveError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::Number(text) => match text.parse::<f32>() {
+                Ok(number) => Ok(Setting::Float(number)),
+                Err(_) => Err(SaveError::InvalidNumber { text, position: token.position }),
+            },
+            TokenKind::Str(text) => Ok(Setting::String(text)),
+            TokenKind::Ident(ident) if ident.eq_ignore_ascii_case("true") => Ok(Setting::Bool(true)),
+            TokenKind::Ident(ident) if ident.eq_ignore_ascii_case("false") => Ok(Setting::Bool(false)),
+            TokenKind::Ident(ident) if ident.parse::<f32>().is_ok() => Ok(Setting::Float(ident.parse().unwrap())),
+            TokenKind::Ident(ident) => Ok(Setting::String(ident)),
+            TokenKind::OpenBracket if depth >= MAX_SETTING_DEPTH => Err(SaveError::NestedTooDeep { position: token.position }),
+            TokenKind::OpenBracket => {
+                let mut list = Vec::new();
+                loop {
+                    if self.eat(TokenKind::CloseBracket)? {
+                        return Ok(Setting::List(list));
+                    }
+                    list.push(self.setting(depth + 1)?);
+                    if !self.eat(TokenKind::Comma)? && self.peek()?.kind != TokenKind::CloseBracket {
+                        return Err(Parser::unexpected(self.next()?, "',' or ']'"));
+                    }
+                }
+            },
+            kind => Err(Parser::unexpected(Token { kind, position: token.position }, "a setting value")),
+        }
+    }
+
+    fn settings(&mut self) -> Result<Settings, SaveError> {
+        let mut settings = Settings::new();
+        self.fields(|parser, name, position| {
+            if settings.get_setting(name).is_some() {
+                return Err(SaveError::DuplicateField { block: "settings", name: name.to_owned(), position });
+            }
+            let setting = parser.setting(0)?;
+            settings.set(name, setting);
+            Ok(())
+        })?;
+        Ok(settings)
+    }
+
+    fn filling(&mut self) -> Result<Tile, SaveError> {
+        let (name, position) = self.ident("a tile filling")?;
+        match name.to_lowercase().as_str() {
+            "air" => Ok(Tile::Air),
+            "ground" => Ok(Tile::Ground),
+            "building" => {
+                let mut health = None;
+                let mut tier = None;
+                let mut pressure = None;
+                let open = self.fields(|parser, name, position| {
+                    let slot = match name {
+                        "health" => &mut health,
+                        "tier" => &mut tier,
+                        "pressure" => &mut pressure,
+                        _ => return Err(SaveError::UnknownField { block: "building", name: name.to_owned(), position }),
+                    };
+                    if slot.is_some() {
+                        return Err(SaveError::DuplicateField { block: "building", name: name.to_owned(), position });
+                    }
+                    *slot = Some(parser.number::<f32>()?);
+                    Ok(())
+                })?;
+
+                Ok(Tile::Building {
+                    health: health.ok_or(SaveError::MissingField { block: "building", name: "health", position: open })?,
+                    tier: tier.ok_or(SaveError::MissingField { block: "building", name: "tier", position: open })?,
+                    // older saves were written before buildings had pressure, it is recalculated every frame anyway
+                    pressure: pressure.unwrap_or(0.),
+                })
+            },
+            _ => Err(SaveError::UnknownTile { name, position }),
+        }
+    }
+
+    fn tile(&mut self) -> Result<(Coord, Tile, Position), SaveError> {
+        let mut x = None;
+        let mut y = None;
+        let mut filling = None;
+        let open = self.fields(|parser, name, position| {
+            match name {
+                "x" | "y" => {
+                    let slot = if name == "x" { &mut x } else { &mut y };
+                    if slot.is_some() {
+                        return Err(SaveError::DuplicateField { block: "tile", name: name.to_owned(), position });
+                    }
+                    *slot = Some(parser.number::<i32>()?);
+                },
+                "filling" => {
+                    if filling.is_some() {
+                        return Err(SaveError::DuplicateField { block: "tile", name: name.to_owned(), position });
+                    }
+                    filling = Some(parser.filling()?);
+                },
+                _ => return Err(SaveError::UnknownField { block: "tile", name: name.to_owned(), position }),
+            }
+            Ok(())
+        })?;
+
+        let x = x.ok_or(SaveError::MissingField { block: "tile", name: "x", position: open })?;
+        let y = y.ok_or(SaveError::MissingField { block: "tile", name: "y", position: open })?;
+        let filling = filling.ok_or(SaveError::MissingField { block: "tile", name: "filling", position: open })?;
+        Ok((Coord::from((x, y)), filling, open))
+    }
+
+    // parses `[ Repeat (n) times (up) ]`, returning the number of repeats and the step between them
+    fn repeat(&mut self) -> Result<(i32, i32), SaveError> {
+        self.expect(TokenKind::OpenBracket, "'['")?;
+        self.keyword("repeat")?;
+        self.expect(TokenKind::OpenParen, "'('")?;
+        let count: u32 = self.number()?;
+        self.expect(TokenKind::CloseParen, "')'")?;
+        self.keyword("times")?;
+        self.expect(TokenKind::OpenParen, "'('")?;
+        let (direction, position) = self.ident("up or down")?;
+        let step = match direction.to_lowercase().as_str() {
+            "up" => 1,
+            "down" => -1,
+            _ => return Err(SaveError::UnknownDirection { name: direction, position }),
+        };
+        self.expect(TokenKind::CloseParen, "')'")?;
+        self.expect(TokenKind::CloseBracket, "']'")?;
+
+        if count >= MAX_RUN_LEN {
+            return Err(SaveError::CoordOutOfRange { position });
+        }
+        Ok((count as i32, step))
+    }
+
+    fn grid(&mut self) -> Result<Grid, SaveError> {
+        let mut grid = Grid::new::<Coord>(Vec::new());
+        self.expect(TokenKind::OpenBrace, "'{'")?;
+        loop {
+            if self.eat(TokenKind::CloseBrace)? {
+                return Ok(grid);
+            }
+            self.keyword("tile")?;
+            let (coord, filling, position) = self.tile()?;
+            self.eat(TokenKind::Comma)?;
+            grid.insert(coord, filling);
+
+            if self.peek()?.kind == TokenKind::OpenBracket {
+                let (count, step) = self.repeat()?;
+                for i in 1..=count {
+                    let repeated = i.checked_mul(step)
+                        .and_then(|offset| coord.checked_add(Coord::new(0, offset)))
+                        .ok_or(SaveError::CoordOutOfRange { position })?;
+                    grid.insert(repeated, filling);
+                }
+                self.eat(TokenKind::Comma)?;
+            }
+        }
+    }
+
+    // parses `{ width: w, height: h, rects: [ [x, y, width, height, r, g, b], ... ] }`
+    fn thumbnail(&mut self) -> Result<Thumbnail, SaveError> {
+        let mut width = None;
+        let mut height = None;
+        let mut rects = None;
+        let open = self.fields(|parser, name, position| {
+            let duplicate = match name {
+                "width" => width.replace(parser.number::<u32>()?).is_some(),
+                "height" => height.replace(parser.number::<u32>()?).is_some(),
+                "rects" => rects.replace(parser.thumbnail_rects()?).is_some(),
+                _ => return Err(SaveError::UnknownField { block: "thumbnail", name: name.to_owned(), position }),
+            };
+            if duplicate {
+                return Err(SaveError::DuplicateField { block: "thumbnail", name: name.to_owned(), position });
+            }
+            Ok(())
+        })?;
+
+        let thumbnail = Thumbnail {
+            width: width.ok_or(SaveError::MissingField { block: "thumbnail", name: "width", position: open })?,
+            height: height.ok_or(SaveError::MissingField { block: "thumbnail", name: "height", position: open })?,
+            rects: rects.unwrap_or_default(),
+        };
+        if !thumbnail.is_valid() {
+            return Err(SaveError::InvalidThumbnail);
+        }
+        Ok(thumbnail)
+    }
+
+    fn thumbnail_rects(&mut self) -> Result<Vec<ThumbnailRect>, SaveError> {
+        let mut rects = Vec::new();
+        self.expect(TokenKind::OpenBracket, "'['")?;
+        loop {
+            if self.eat(TokenKind::CloseBracket)? {
+                return Ok(rects);
+            }
+            self.expect(TokenKind::OpenBracket, "'[' or ']'")?;
+            let mut numbers = [0u32; 7];
+            for (i, number) in numbers.iter_mut().enumerate() {
+                if i > 0 {
+                    self.expect(TokenKind::Comma, "','")?;
+                }
+                *number = self.number()?;
+            }
+            self.eat(TokenKind::Comma)?;
+            self.expect(TokenKind::CloseBracket, "']'")?;
+
+            let [x, y, width, height, r, g, b] = numbers;
+            let channel = |value: u32| u8::try_from(value).map_err(|_| SaveError::InvalidThumbnail);
+            rects.push(ThumbnailRect { x, y, width, height, color: [channel(r)?, channel(g)?, channel(b)?] });
+
+            if !self.eat(TokenKind::Comma)? && self.peek()?.kind != TokenKind::CloseBracket {
+                return Err(Parser::unexpected(self.next()?, "',' or ']'"));
+            }
+        }
+    }
+
+    fn save(&mut self) -> Result<Save, SaveError> {
+        let mut date = None;
+        let mut version = None;
+        while self.peek()?.kind == TokenKind::OpenBracket {
+            self.next()?;
+            let (name, position) = self.ident("a header name")?;
+            self.expect(TokenKind::Colon, "':'")?;
+            self.lexer.skip_whitespace();
+            let value_position = self.lexer.position();
+            let value = self.lexer.raw_until(']')?;
+            self.expect(TokenKind::CloseBracket, "']'")?;
+
+            match name.to_lowercase().as_str() {
+                "date" => date = Some(value),
+                "version" => match value.parse() {
+                    Ok(parsed) => version = Some(parsed),
+                    Err(_) => return Err(SaveError::InvalidVersion { text: value, position: value_position }),
+                },
+                _ => return Err(SaveError::UnknownHeader { name, position }),
+            }
+        }
+
+        let version = version.unwrap_or(FIRST_VERSION);
+        if version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion { found: version });
+        }
+
+        let mut cam_pos = None;
+        let mut selected = None;
+        let mut money = None;
+        let mut settings = None;
+        let mut thumbnail = None;
+        let mut grid = None;
+        loop {
+            let (name, position) = match self.peek()?.kind {
+                TokenKind::Eof => break,
+                _ => self.ident("a block name")?,
+            };
+            let lowercase = name.to_lowercase();
+            let duplicate = match lowercase.as_str() {
+                "camera_position" => cam_pos.replace(self.coord("camera_position")?).is_some(),
+                "selected" => selected.replace(self.coord("selected")?).is_some(),
+                "money" => money.replace(self.money()?).is_some(),
+                "settings" => settings.replace(self.settings()?).is_some(),
+                "thumbnail" => thumbnail.replace(self.thumbnail()?).is_some(),
+                "grid" => grid.replace(self.grid()?).is_some(),
+                _ => return Err(SaveError::UnknownBlock { name, position }),
+            };
+            if duplicate {
+                return Err(SaveError::DuplicateBlock { name, position });
+            }
+        }
+
+        Ok(Save {
+            date: date.unwrap_or_default(),
+            version,
+            cam_pos: cam_pos.ok_or(SaveError::MissingBlock { name: "camera_position" })?,
+            selected: selected.ok_or(SaveError::MissingBlock { name: "selected" })?,
+            money: money.ok_or(SaveError::MissingBlock { name: "money" })?,
+            settings: settings.ok_or(SaveError::MissingBlock { name: "settings" })?,
+            grid: grid.ok_or(SaveError::MissingBlock { name: "grid" })?,
+            thumbnail,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Save {
+    pub date: String,
+    /// Version the save was written with, it is always upgraded to SAVE_VERSION by the time it's read.
+    pub version: Version,
+    pub cam_pos: Coord,
+    pub selected: Coord,
+    pub money: i128,
+    pub settings: Settings,
+    pub grid: Grid,
+    /// Picture of the visible grid for the save menu, saves from before 0.3.0 don't have one.
+    pub thumbnail: Option<Thumbnail>,
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SaveFormat {
+    Text,
+    Binary,
+}
+impl SaveFormat {
+    pub const BINARY_EXTENSION: &'static str = "bsave";
+
+    /// Binary saves are told apart by their `.bsave` extension, anything else is read as text.
+    pub fn from_path<P: AsRef<Path>>(filepath: P) -> Self {
+        match filepath.as_ref().extension() {
+            Some(extension) if extension == SaveFormat::BINARY_EXTENSION => SaveFormat::Binary,
+            _ => SaveFormat::Text,
+        }
+    }
+}
+
+impl Save {
+    pub fn read<P: AsRef<Path>>(filepath: P) -> Result<Save, SaveError> {
+        match SaveFormat::from_path(&filepath) {
+            SaveFormat::Text => Save::parse(&fs::read_to_string(filepath)?),
+            SaveFormat::Binary => Save::from_bytes(&fs::read(filepath)?),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Save, SaveError> {
+        let mut save = binary::decode(bytes)?;
+        migrate(&mut save)?;
+        Ok(save)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        binary::encode(self)
+    }
+
+    /// Rewrites a save in the format of the destination's extension, e.g. to diff a binary save as text.
+    pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<(), SaveError> {
+        Save::read(from)?.write(to)
+    }
+
+    pub fn parse(source: &str) -> Result<Save, SaveError> {
+        let mut save = Parser::new(source).save()?;
+        migrate(&mut save)?;
+        Ok(save)
+    }
+
+    /// Writes the save atomically, a crash midway leaves the previous contents of the file intact.
+    pub fn write<P: AsRef<Path>>(&self, filepath: P) -> Result<(), SaveError> {
+        match SaveFormat::from_path(&filepath) {
+            SaveFormat::Text => storage::write_atomic(filepath, self.serialize().as_bytes())?,
+            SaveFormat::Binary => storage::write_atomic(filepath, &self.to_bytes())?,
+        }
+        Ok(())
+    }
+
+    /// Like `write`, but first keeps what the file held before as a backup, along with up to `keep - 1` older ones.
+    pub fn write_with_backups<P: AsRef<Path>>(&self, filepath: P, keep: usize) -> Result<(), SaveError> {
+        storage::rotate_backups(&filepath, keep)?;
+        self.write(filepath)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut contents = String::new();
+
+        // writing to a String can't fail, so the fmt::Results below are safe to ignore
+        let _ = writeln!(contents, "[Date: {}]", self.date);
+        // the contents are always in the current format, regardless of what version they were read from
+        let _ = writeln!(contents, "[Version: {}]\n", SAVE_VERSION);
+
+        let _ = writeln!(contents, "camera_position {{\n    x: {},\n    y: {},\n}}\n", self.cam_pos.x(), self.cam_pos.y());
+        let _ = writeln!(contents, "selected {{\n    x: {},\n    y: {},\n}}\n", self.selected.x(), self.selected.y());
+        let _ = writeln!(contents, "money {{ {} }}\n", self.money);
+
+        contents.push_str("settings {\n");
+        let mut settings: Vec<(&String, &Setting)> = self.settings.iter().collect();
+        settings.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        for (name, setting) in settings {
+            // Other settings are opaque trait objects and are left out of the save
+            if let Some(value) = serialize_setting(setting) {
+                let _ = writeln!(contents, "    {}: {},", name, value);
+            }
+        }
+        contents.push_str("}\n\n");
+
+        if let Some(thumbnail) = &self.thumbnail {
+            serialize_thumbnail(thumbnail, &mut contents);
+        }
+        serialize_grid(&self.grid, &mut contents);
+
+        contents
+    }
+}
+
+// ----- MIGRATIONS -----
+type Migration = fn(&mut Save) -> Result<(), SaveError>;
+
+/// Every migration runs on saves older than its version, in the order they are listed.
+const MIGRATIONS: [(Version, Migration); 2] = [
+    (Version::new(0, 2, 0), migrate_building_pressure),
+    (Version::new(0, 2, 0), migrate_tile_ratio),
+];
+
+fn migrate(save: &mut Save) -> Result<(), SaveError> {
+    for (version, migration) in MIGRATIONS {
+        if save.version < version {
+            migration(save)?;
+        }
+    }
+    Ok(())
+}
+
+// buildings used to be saved without pressure, it is the tier weight of the building plus everything stacked on it
+fn migrate_building_pressure(save: &mut Save) -> Result<(), SaveError> {
+    let mut above: Option<(Coord, f32)> = None;
+    for (coord, tile) in save.grid.to_mut_ref_vec().into_iter().rev() {
+        if let Tile::Building { tier, pressure, .. } = tile {
+            let load = match above {
+                Some((above_coord, above_pressure)) if coord.checked_step(Direction::Up) == Some(above_coord) => above_pressure,
+                _ => 0.,
+            };
+            *pressure = *tier * 100. + load;
+            above = Some((coord, *pressure));
+        } else {
+            above = None;
+        }
+    }
+    Ok(())
+}
+
+// `tile_ratio: [width, height]` became the separate visible_width and visible_height settings
+fn migrate_tile_ratio(save: &mut Save) -> Result<(), SaveError> {
+    let tile_ratio = match save.settings.remove("tile_ratio") {
+        Some(tile_ratio) => tile_ratio,
+        None => return Ok(()),
+    };
+    match tile_ratio {
+        Setting::List(ratio) => match ratio[..] {
+            [Setting::Float(width), Setting::Float(height)] => {
+                save.settings.set("visible_width", width);
+                save.settings.set("visible_height", height);
+                Ok(())
+            },
+            _ => Err(SaveError::InvalidSetting { name: "tile_ratio" }),
+        },
+        _ => Err(SaveError::InvalidSetting { name: "tile_ratio" }),
+    }
+}
+
+// ----- WRITER -----
+fn serialize_setting(setting: &Setting) -> Option<String> {
+    match setting {
+        Setting::Bool(value) => Some(value.to_string()),
+        Setting::Float(value) => Some(format!("{:?}", value)),
+        Setting::String(value) => Some(format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))),
+        Setting::List(values) => {
+            let values: Vec<String> = values.iter().filter_map(serialize_setting).collect();
+            Some(format!("[{}]", values.join(", ")))
+        },
+        Setting::Other(_) => None,
+    }
+}
+
+fn serialize_filling(filling: &Tile) -> String {
+    match filling {
+        // {:?} prints the shortest representation that parses back to the exact same f32
+        Tile::Building { health, tier, pressure } => format!("Building {{ health: {:?}, tier: {:?}, pressure: {:?}, }}", health, tier, pressure),
+        _ => filling.to_string(),
+    }
+}
+
+fn serialize_thumbnail(thumbnail: &Thumbnail, contents: &mut String) {
+    let _ = writeln!(contents, "thumbnail {{\n    width: {},\n    height: {},\n    rects: [", thumbnail.width, thumbnail.height);
+    for rect in &thumbnail.rects {
+        let [r, g, b] = rect.color;
+        let _ = writeln!(contents, "        [{}, {}, {}, {}, {}, {}, {}],", rect.x, rect.y, rect.width, rect.height, r, g, b);
+    }
+    contents.push_str("    ],\n}\n\n");
+}
+
+// splits the grid into vertical runs of identical tiles as (coord of the bottom tile, filling, number of repeats above it),
+// sorted column by column, runs are split before they reach MAX_RUN_LEN tiles
+fn grid_runs(grid: &Grid) -> Vec<(Coord, Tile, i32)> {
+    let mut runs: Vec<(Coord, Tile, i32)> = Vec::new();
+    for (coord, filling) in grid.to_ref_vec() {
+        if let Some((start, previous, repeats)) = runs.last_mut() {
+            if previous == filling && start.checked_add(Coord::new(0, *repeats + 1)) == Some(coord) && ((*repeats + 2) as u32) <= MAX_RUN_LEN {
+                *repeats += 1;
+                continue;
+            }
+        }
+        runs.push((coord, *filling, 0));
+    }
+    runs
+}
+
+// writes the grid column by column, collapsing vertical runs of identical tiles into `[ Repeat (n) times (up) ]`
+fn serialize_grid(grid: &Grid, contents: &mut String) {
+    let runs = grid_runs(grid);
+
+    let fillings: Vec<String> = runs.iter().map(|(_, filling, _)| serialize_filling(filling)).collect();
+
+    let mut x_width = 1;
+    let mut y_width = 1;
+    let mut filling_width = 1;
+    let mut repeat_width = 1;
+    for ((coord, filling, repeats), serialized) in runs.iter().zip(&fillings) {
+        x_width = x_width.max(coord.x().to_string().len());
+        y_width = y_width.max(coord.y().to_string().len());
+        repeat_width = repeat_width.max(repeats.to_string().len());
+        // buildings are too long to line up with the rest
+        if !matches!(filling, Tile::Building { .. }) {
+            filling_width = filling_width.max(serialized.len());
+        }
+    }
+
+    contents.push_str("grid {\n");
+    let mut previous_x = None;
+    for ((coord, filling, repeats), serialized) in runs.iter().zip(&fillings) {
+        let (x, y) = (coord.x(), coord.y());
+        if previous_x.is_some() && previous_x != Some(x) {
+            contents.push('\n');
+        }
+        previous_x = Some(x);
+
+        let _ = write!(contents, "    tile {{ x: {:>x_width$}, y: {:>y_width$}, filling: ", x, y);
+        if matches!(filling, Tile::Building { .. }) {
+            contents.push_str(serialized);
+        } else {
+            let _ = write!(contents, "{:>filling_width$}", serialized);
+        }
+        contents.push_str(", },");
+        if *repeats > 0 {
+            let _ = write!(contents, " [ Repeat ({:>repeat_width$}) times (up) ]", repeats);
+        }
+        contents.push('\n');
+    }
+    contents.push_str("}\n");
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::test_dir;
+
+    const BASE_SAVE: &str = include_str!("../../saves/base.save");
+
+    // ----- HELPER FUNCTIONS -----
+    fn minimal_save(grid: &str) -> String {
+        format!("
+            camera_position {{ x: 1, y: -2, }}
+            selected {{ x: 3, y: 4, }}
+            money {{ -50 }}
+            settings {{ tile_ratio: [7, 5], }}
+            grid {{ {} }}
+        ", grid)
+    }
+
+    // xorshift, so that the round trip tests are reproducible from their seed without pulling in a dependency
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn range(&mut self, min: i32, max: i32) -> i32 {
+            min + (self.next() % (max - min) as u64) as i32
+        }
+        fn float(&mut self) -> f32 {
+            const SPECIAL: [f32; 8] = [0., -0., 1., f32::MAX, f32::MIN, f32::MIN_POSITIVE, f32::INFINITY, f32::NEG_INFINITY];
+            match self.next() % 4 {
+                0 => SPECIAL[(self.next() % SPECIAL.len() as u64) as usize],
+                1 => self.range(-1000, 1000) as f32,
+                _ => loop {
+                    // any bit pattern except NaN, which is never equal to itself
+                    let float = f32::from_bits(self.next() as u32);
+                    if !float.is_nan() {
+                        break float;
+                    }
+                },
+            }
+        }
+        fn tile(&mut self) -> Tile {
+            match self.next() % 3 {
+                0 => Tile::Air,
+                1 => Tile::Ground,
+                _ => Tile::Building { health: self.float(), tier: self.float(), pressure: self.float() },
+            }
+        }
+    }
+
+    fn random_grid(rng: &mut Rng) -> Grid {
+        let mut grid = Grid::new::<Coord>(Vec::new());
+        for _ in 0..rng.range(0, 12) {
+            let x = match rng.next() % 8 {
+                0 => i32::MIN + rng.range(0, 3),
+                1 => i32::MAX - rng.range(0, 3),
+                _ => rng.range(-50, 50),
+            };
+            let mut y = match rng.next() % 8 {
+                0 => i32::MIN,
+                1 => i32::MAX - rng.range(0, 40),
+                _ => rng.range(-50, 50),
+            };
+            let mut tile = rng.tile();
+            for _ in 0..rng.range(1, 40) {
+                // runs of identical tiles, broken up by gaps and changes of filling
+                match rng.next() % 10 {
+                    0 => tile = rng.tile(),
+                    1 => y = y.saturating_add(rng.range(1, 4)),
+                    _ => {},
+                }
+                grid.insert((x, y), tile);
+                y = y.saturating_add(1);
+            }
+        }
+        grid
+    }
+
+    fn random_save(rng: &mut Rng) -> Save {
+        let mut settings = Settings::new();
+        settings.set("tile_ratio", vec![rng.range(1, 100) as f32, rng.range(1, 100) as f32]);
+        settings.set("volume", rng.float());
+        settings.set("muted", rng.next() & 1 == 0);
+        settings.set("name", "a \"quoted\" \\ name".to_owned());
+
+        let grid = random_grid(rng);
+        let thumbnail = match rng.next() % 2 {
+            0 => None,
+            _ => {
+                let size = [rng.range(0, 20) as u32, rng.range(0, 20) as u32];
+                let color = |tile: &Tile| match tile {
+                    Tile::Air => None,
+                    Tile::Ground => Some([51, 20, 0]),
+                    Tile::Building { .. } => Some([255, 255, 255]),
+                };
+                Some(Thumbnail::from_grid(&grid, Coord::from((rng.range(-50, 50), rng.range(-50, 50))), size, color))
+            },
+        };
+
+        Save {
+            date: "2023-06-01T12:00:00.000+02:00".to_owned(),
+            version: SAVE_VERSION,
+            cam_pos: Coord::from((rng.range(-1000, 1000), rng.range(-1000, 1000))),
+            selected: Coord::from((rng.range(-1000, 1000), rng.range(-1000, 1000))),
+            money: i128::from(rng.next() as i64) * i128::from(rng.range(-1000, 1000)),
+            settings,
+            grid,
+            thumbnail,
+        }
+    }
+
+    fn assert_round_trip(save: &Save) {
+        let serialized = save.serialize();
+        let read = Save::parse(&serialized).unwrap_or_else(|err| panic!("ERROR: failed to read back written save: {}\n{}", err, serialized));
+        assert_same_save(&read, save, &serialized);
+    }
+
+    fn assert_binary_round_trip(save: &Save) {
+        let read = Save::from_bytes(&save.to_bytes()).unwrap_or_else(|err| panic!("ERROR: failed to read back binary save: {}", err));
+        assert_same_save(&read, save, &save.serialize());
+    }
+
+    fn assert_same_save(read: &Save, save: &Save, serialized: &str) {
+        assert_eq!(read.date, save.date);
+        assert_eq!(read.version, SAVE_VERSION);
+        assert_eq!(read.cam_pos, save.cam_pos);
+        assert_eq!(read.selected, save.selected);
+        assert_eq!(read.money, save.money);
+        assert_eq!(read.grid, save.grid, "ERROR: grid changed after being written and read back:\n{}", serialized);
+        assert_eq!(read.thumbnail, save.thumbnail, "ERROR: thumbnail changed after being written and read back:\n{}", serialized);
+        assert_eq!(
+            serialize_settings_sorted(&read.settings),
+            serialize_settings_sorted(&save.settings),
+            "ERROR: settings changed after being written and read back"
+        );
+    }
+
+    fn serialize_settings_sorted(settings: &Settings) -> Vec<(String, Option<String>)> {
+        let mut settings: Vec<(String, Option<String>)> = settings.iter().map(|(name, setting)| (name.clone(), serialize_setting(setting))).collect();
+        settings.sort();
+        settings
+    }
+
+    // ----- TOKENIZER TESTS -----
+    #[test]
+    fn test_lexer_tokens() {
+        let mut lexer = Lexer::new("tile {\n  x: -12, }");
+        let mut kinds = Vec::new();
+        loop {
+            let token = lexer.next_token().unwrap();
+            if token.kind == TokenKind::Eof {
+                break;
+            }
+            kinds.push(token.kind);
+        }
+
+        assert_eq!(kinds, vec![
+            TokenKind::Ident("tile".to_owned()),
+            TokenKind::OpenBrace,
+            TokenKind::Ident("x".to_owned()),
+            TokenKind::Colon,
+            TokenKind::Number("-12".to_owned()),
+            TokenKind::Comma,
+            TokenKind::CloseBrace,
+        ]);
+    }
+
+    #[test]
+    fn test_lexer_positions() {
+        let mut lexer = Lexer::new("tile {\n  x: -12, }");
+        let positions: Vec<Position> = (0..4).map(|_| lexer.next_token().unwrap().position).collect();
+
+        assert_eq!(positions, vec![
+            Position { line: 1, column: 1 },
+            Position { line: 1, column: 6 },
+            Position { line: 2, column: 3 },
+            Position { line: 2, column: 4 },
+        ]);
+    }
+
+    // ----- PARSER TESTS -----
+    #[test]
+    fn test_parse_base_save() {
+        let save = Save::parse(BASE_SAVE).unwrap();
+
+        assert_eq!(save.date, "2022-04-11 23:01:39.45");
+        assert_eq!(save.version, Version::new(0, 1, 0));
+        assert_eq!(save.cam_pos, Coord::from((0, 0)));
+        assert_eq!(save.selected, Coord::from((-1, 1)));
+        assert_eq!(save.money, 10000);
+        assert_eq!(save.settings.get::<f32>("visible_width"), Some(7.));
+        assert_eq!(save.settings.get::<f32>("visible_height"), Some(5.));
+
+        assert_eq!(save.grid.get((20, 20)), Some(&Tile::Air));
+        assert_eq!(save.grid.get((20, 21)), None);
+        assert_eq!(save.grid.get((-20, -4)), Some(&Tile::Ground));
+        assert_eq!(save.grid.get((-20, -5)), None);
+        assert_eq!(save.grid.get((0, 0)), Some(&Tile::Building { health: 300., tier: 3., pressure: 300. }));
+        assert_eq!(save.grid.get((-1, 1)), Some(&Tile::Building { health: 100., tier: 1., pressure: 100. }));
+        assert_eq!(save.grid.get((-1, 0)), Some(&Tile::Building { health: 200., tier: 2., pressure: 300. }));
+        assert_eq!(save.grid.get((-1, 2)), Some(&Tile::Air));
+        assert_eq!(save.grid.to_ref_vec().len(), 41 * 25);
+    }
+
+    #[test]
+    fn test_parse_blocks() {
+        let save = Save::parse(&minimal_save("")).unwrap();
+
+        assert_eq!(save.date, "");
+        assert_eq!(save.version, FIRST_VERSION);
+        assert_eq!(save.cam_pos, Coord::from((1, -2)));
+        assert_eq!(save.selected, Coord::from((3, 4)));
+        assert_eq!(save.money, -50);
+        assert_eq!(save.grid, Grid::new::<Coord>(Vec::new()));
+    }
+
+    #[test]
+    fn test_parse_tiles() {
+        let save = Save::parse(&minimal_save("
+            TILE { x: 0, y: 0, filling: building { health: 12.5, tier: 2, pressure: 200 } }
+            tile { filling: Ground, y: -1, x: 0 }, [ Repeat (2) times (down) ],
+            tile { x: 1, y: 0, filling: Air, }, [repeat(1)times(up)]
+        ")).unwrap();
+
+        assert_eq!(save.grid, Grid::new(vec![
+            ((0,  0), Tile::Building { health: 12.5, tier: 2., pressure: 200. }),
+            ((0, -1), Tile::Ground),
+            ((0, -2), Tile::Ground),
+            ((0, -3), Tile::Ground),
+            ((1,  0), Tile::Air),
+            ((1,  1), Tile::Air),
+        ]));
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        let save = Save::parse(&minimal_save("").replace(
+            "tile_ratio: [7, 5],",
+            "fullscreen: true, volume: 0.5, name: \"my \\\"city\\\"\", theme: dark, empty: []"
+        )).unwrap();
+
+        assert_eq!(save.settings.get::<bool>("fullscreen"), Some(true));
+        assert_eq!(save.settings.get::<f32>("volume"), Some(0.5));
+        assert_eq!(save.settings.get::<String>("name"), Some("my \"city\"".to_owned()));
+        assert_eq!(save.settings.get::<String>("theme"), Some("dark".to_owned()));
+        assert_eq!(save.settings.get::<Vec<Setting>>("empty").map(|l| l.len()), Some(0));
+    }
+
+    // ----- ERROR TESTS -----
+    #[test]
+    fn test_parse_error_positions() {
+        let err = Save::parse("money { 10 }\nselected { x: 1, y: 2 z: 3 }").unwrap_err();
+        assert!(matches!(err, SaveError::UnexpectedToken { .. }), "ERROR: expected UnexpectedToken, got {:?}", err);
+        assert_eq!(err.position(), Some(Position { line: 2, column: 23 }));
+
+        let err = Save::parse(&minimal_save("tile { x: 0, y: 0, filling: Lava, },")).unwrap_err();
+        assert!(matches!(err, SaveError::UnknownTile { ref name, .. } if name == "Lava"), "ERROR: expected UnknownTile, got {:?}", err);
+        assert_eq!(err.position(), Some(Position { line: 6, column: 48 }));
+
+        let err = Save::parse("money { 1O }").unwrap_err();
+        assert!(matches!(err, SaveError::InvalidNumber { ref text, .. } if text == "1O"), "ERROR: expected InvalidNumber, got {:?}", err);
+        assert_eq!(err.position(), Some(Position { line: 1, column: 9 }));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, filling: Air }")),
+            Err(SaveError::MissingField { block: "tile", name: "y", .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, x: 1, y: 0, filling: Air }")),
+            Err(SaveError::DuplicateField { block: "tile", .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, y: 0, z: 0, filling: Air }")),
+            Err(SaveError::UnknownField { block: "tile", .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, y: 0, filling: Air }, [ Repeat (2) times (left) ]")),
+            Err(SaveError::UnknownDirection { .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, y: 2147483646, filling: Air }, [ Repeat (2) times (up) ]")),
+            Err(SaveError::CoordOutOfRange { .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, y: 0, filling: Air }, [ Repeat (4000000000) times (up) ]")),
+            Err(SaveError::CoordOutOfRange { .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("").replace("settings {", &format!("settings {{ deep: {}", "[".repeat(1000)))),
+            Err(SaveError::NestedTooDeep { .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("tile { x: 0, y: 0, filling: Building { tier: 1 } }")),
+            Err(SaveError::MissingField { block: "building", name: "health", .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("").replace("money { -50 }", "")),
+            Err(SaveError::MissingBlock { name: "money" })
+        ));
+        assert!(matches!(
+            Save::parse(&(minimal_save("") + "money { 1 }")),
+            Err(SaveError::DuplicateBlock { .. })
+        ));
+        assert!(matches!(
+            Save::parse(&minimal_save("").replace("money", "cash")),
+            Err(SaveError::UnknownBlock { .. })
+        ));
+        assert!(matches!(
+            Save::parse(&("[Author: me]\n".to_owned() + &minimal_save(""))),
+            Err(SaveError::UnknownHeader { .. })
+        ));
+        assert!(matches!(
+            Save::parse("money { 10 } #"),
+            Err(SaveError::UnexpectedChar { found: '#', .. })
+        ));
+        assert!(matches!(
+            Save::read("saves/does_not_exist.save"),
+            Err(SaveError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let err = Save::parse("money { 10 } #").unwrap_err();
+        assert_eq!(err.to_string(), "An error occurred while parsing the save file: line 1, column 14: unexpected character '#'");
+        let err = SaveError::SlotNotFound { name: "city".to_owned() };
+        assert_eq!(err.to_string(), "there is no save called \"city\"", "ERROR: errors managing saves shouldn't be blamed on parsing");
+    }
+
+    // ----- VERSION TESTS -----
+    #[test]
+    fn test_version_parse() {
+        assert_eq!("0.1.0".parse(), Ok(Version::new(0, 1, 0)));
+        assert_eq!(" 12.3.45 ".parse(), Ok(Version::new(12, 3, 45)));
+        assert_eq!("0.1".parse::<Version>(), Err(()));
+        assert_eq!("0.1.x".parse::<Version>(), Err(()));
+        assert_eq!(Version::new(1, 2, 3).to_string(), "1.2.3");
+
+        assert!(Version::new(0, 10, 0) > Version::new(0, 9, 9));
+        assert!(Version::new(1, 0, 0) > Version::new(0, 99, 99));
+    }
+
+    #[test]
+    fn test_version_errors() {
+        let err = Save::parse(&("[Version: 99.0.0]\n".to_owned() + &minimal_save(""))).unwrap_err();
+        assert!(matches!(err, SaveError::UnsupportedVersion { found } if found == Version::new(99, 0, 0)), "ERROR: expected UnsupportedVersion, got {:?}", err);
+
+        let err = Save::parse(&("[Version:  zero]\n".to_owned() + &minimal_save(""))).unwrap_err();
+        assert!(matches!(err, SaveError::InvalidVersion { ref text, .. } if text == "zero"), "ERROR: expected InvalidVersion, got {:?}", err);
+        assert_eq!(err.position(), Some(Position { line: 1, column: 12 }));
+    }
+
+    // ----- MIGRATION TESTS -----
+    #[test]
+    fn test_migrate_building_pressure() {
+        let save = Save::parse(&minimal_save("
+            tile { x: 0, y: 0, filling: Building { health: 100, tier: 1 } }, [ Repeat (2) times (up) ]
+            tile { x: 0, y: 3, filling: Air },
+            tile { x: 0, y: 4, filling: Building { health: 100, tier: 2 } },
+            tile { x: 1, y: 1, filling: Building { health: 100, tier: 3 } },
+        ")).unwrap();
+
+        assert_eq!(save.grid.get((0, 2)), Some(&Tile::Building { health: 100., tier: 1., pressure: 100. }));
+        assert_eq!(save.grid.get((0, 1)), Some(&Tile::Building { health: 100., tier: 1., pressure: 200. }));
+        assert_eq!(save.grid.get((0, 0)), Some(&Tile::Building { health: 100., tier: 1., pressure: 300. }));
+        assert_eq!(save.grid.get((0, 4)), Some(&Tile::Building { health: 100., tier: 2., pressure: 200. }));
+        assert_eq!(save.grid.get((1, 1)), Some(&Tile::Building { health: 100., tier: 3., pressure: 300. }));
+    }
+
+    #[test]
+    fn test_migrate_tile_ratio() {
+        let save = Save::parse(&minimal_save("")).unwrap();
+        assert!(save.settings.get_setting("tile_ratio").is_none());
+        assert_eq!(save.settings.get::<f32>("visible_width"), Some(7.));
+        assert_eq!(save.settings.get::<f32>("visible_height"), Some(5.));
+
+        let err = Save::parse(&minimal_save("").replace("[7, 5]", "[7, 5, 3]")).unwrap_err();
+        assert!(matches!(err, SaveError::InvalidSetting { name: "tile_ratio" }), "ERROR: expected InvalidSetting, got {:?}", err);
+    }
+
+    #[test]
+    fn test_current_version_not_migrated() {
+        let source = format!("[Version: {}]\n", SAVE_VERSION) + &minimal_save("
+            tile { x: 0, y: 0, filling: Building { health: 100, tier: 1, pressure: 5 } },
+        ");
+        let save = Save::parse(&source).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.grid.get((0, 0)), Some(&Tile::Building { health: 100., tier: 1., pressure: 5. }));
+        assert!(save.settings.get_setting("tile_ratio").is_some());
+    }
+
+    // ----- WRITER TESTS -----
+    #[test]
+    fn test_write_base_save_round_trip() {
+        let save = Save::parse(BASE_SAVE).unwrap();
+        assert_round_trip(&save);
+    }
+
+    #[test]
+    fn test_write_random_round_trip() {
+        for seed in 1..=500 {
+            let mut rng = Rng(seed);
+            let save = random_save(&mut rng);
+            assert_round_trip(&save);
+        }
+    }
+
+    #[test]
+    fn test_binary_random_round_trip() {
+        for seed in 1..=500 {
+            let mut rng = Rng(seed);
+            let save = random_save(&mut rng);
+            assert_binary_round_trip(&save);
+        }
+    }
+
+    #[test]
+    fn test_write_runs() {
+        let save = Save {
+            grid: Grid::new(vec![
+                ((0, -1), Tile::Ground),
+                ((0,  0), Tile::Air),
+                ((0,  1), Tile::Air),
+                ((0,  2), Tile::Air),
+                ((0,  4), Tile::Air),
+                ((-10, 0), Tile::Building { health: 0.1, tier: 1., pressure: 100. }),
+            ]),
+            ..Save::parse(&minimal_save("")).unwrap()
+        };
+
+        assert!(save.serialize().ends_with("\
+grid {
+    tile { x: -10, y:  0, filling: Building { health: 0.1, tier: 1.0, pressure: 100.0, }, },
+
+    tile { x:   0, y: -1, filling: Ground, },
+    tile { x:   0, y:  0, filling:    Air, }, [ Repeat (2) times (up) ]
+    tile { x:   0, y:  4, filling:    Air, },
+}
+"), "ERROR: unexpected grid layout:\n{}", save.serialize());
+    }
+
+    #[test]
+    fn test_write_file() {
+        let dir = test_dir("save", "write_file");
+        let filepath = dir.join("base.save");
+        let save = Save::parse(BASE_SAVE).unwrap();
+        save.write(&filepath).unwrap();
+
+        assert_eq!(Save::read(&filepath).unwrap().grid, save.grid);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_with_backups() {
+        let dir = test_dir("save", "write_with_backups");
+        let filepath = dir.join("slot.save");
+
+        for money in 0..4 {
+            let save = Save { money, ..Save::parse(&minimal_save("")).unwrap() };
+            save.write_with_backups(&filepath, 2).unwrap();
+        }
+
+        let backups: Vec<i128> = storage::backups(&filepath).unwrap().iter().map(|path| Save::read(path).unwrap().money).collect();
+        assert_eq!(backups, vec![2, 1]);
+        assert_eq!(Save::read(&filepath).unwrap().money, 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_convert_file() {
+        let dir = test_dir("save", "convert_file");
+        let text = dir.join("base.save");
+        let binary = dir.join("base.bsave");
+        let back = dir.join("back.save");
+        let save = Save::parse(BASE_SAVE).unwrap();
+        save.write(&text).unwrap();
+
+        Save::convert(&text, &binary).unwrap();
+        assert!(fs::read(&binary).unwrap().starts_with(&binary::MAGIC), "ERROR: .bsave file was not written in the binary format");
+        Save::convert(&binary, &back).unwrap();
+        assert_eq!(fs::read_to_string(&back).unwrap(), fs::read_to_string(&text).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_truncated_never_panics() {
+        let source = "[Date: 2022-04-11 23:01:39.45]\n".to_owned() + &minimal_save("
+            tile { x: 0, y: 0, filling: Building { health: 1.5e2, tier: 1, pressure: 0, }, },
+            tile { x: 0, y: -1, filling: Ground, }, [ Repeat (3) times (down) ]
+        ");
+
+        // every prefix of a valid save is either valid or an error, but never a panic
+        for (i, _) in source.char_indices() {
+            let _ = Save::parse(&source[..i]);
+        }
+        assert!(Save::parse(&source).is_ok());
+    }
+}
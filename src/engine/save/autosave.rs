@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, SystemTime };
+
+use crate::engine::settings::Settings;
+
+use super::{ Save, SaveError };
+
+/// Setting holding the time between autosaves in simulated minutes, 0 turns autosaving off.
+pub const AUTOSAVE_INTERVAL_SETTING: &str = "autosave_interval";
+pub const DEFAULT_AUTOSAVE_INTERVAL: f32 = 5.;
+/// How many autosaves are kept, each one overwrites the oldest.
+pub const AUTOSAVE_SLOTS: usize = 3;
+/// Autosaves are named `autosave 1` to `autosave 3`, so they show up in the save menu next to the other saves.
+pub const AUTOSAVE_NAME: &str = "autosave";
+
+/// Keeps track of simulated time and writes the game to rotating autosave slots once enough of it has passed.
+///
+/// The game loop feeds it the time each simulation step stands for through `tick`, and writes a save when it returns true.
+#[derive(Debug)]
+pub struct Autosave {
+    dir: PathBuf,
+    interval: Option<Duration>,
+    elapsed: Duration,
+    next_slot: usize,
+}
+impl Autosave {
+    /// Picks up the rotation where the last run left off, by overwriting the oldest autosave in `dir` first.
+    pub fn new<P: AsRef<Path>>(dir: P, settings: &Settings) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+
+        let mut next_slot = 0;
+        let mut oldest: Option<SystemTime> = None;
+        for slot in 0..AUTOSAVE_SLOTS {
+            let modified = match fs::metadata(slot_path(&dir, slot)).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified,
+                // an autosave that was never written is the first to fill
+                Err(_) => {
+                    next_slot = slot;
+                    break;
+                },
+            };
+            if oldest.is_none() || oldest.is_some_and(|oldest| modified < oldest) {
+                oldest = Some(modified);
+                next_slot = slot;
+            }
+        }
+
+        let mut autosave = Autosave {
+            dir,
+            interval: None,
+            elapsed: Duration::ZERO,
+            next_slot,
+        };
+        autosave.load_settings(settings);
+        autosave
+    }
+
+    /// Reads the interval from `settings`, falling back to the default if it's missing or invalid.
+    pub fn load_settings(&mut self, settings: &Settings) {
+        let minutes = settings.get::<f32>(AUTOSAVE_INTERVAL_SETTING)
+            .filter(|minutes| minutes.is_finite())
+            .unwrap_or(DEFAULT_AUTOSAVE_INTERVAL);
+        self.interval = match minutes > 0. {
+            true => Some(Duration::from_secs_f32(minutes.min(u32::MAX as f32) * 60.)),
+            false => None,
+        };
+    }
+
+    /// Stores the interval in `settings`, so it's written along with the save.
+    pub fn store_settings(&self, settings: &mut Settings) {
+        let minutes = self.interval.map_or(0., |interval| interval.as_secs_f32() / 60.);
+        settings.set(AUTOSAVE_INTERVAL_SETTING, minutes);
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// None turns autosaving off.
+    pub fn set_interval(&mut self, interval: Option<Duration>) {
+        self.interval = interval.filter(|interval| !interval.is_zero());
+    }
+
+    /// Adds `simulated` to the time since the last autosave and returns whether it's time for the next one.
+    pub fn tick(&mut self, simulated: Duration) -> bool {
+        self.elapsed = self.elapsed.saturating_add(simulated);
+        match self.interval {
+            Some(interval) => self.elapsed >= interval,
+            None => false,
+        }
+    }
+
+    /// Writes `save` over the oldest autosave and returns its path, restarting the countdown to the next one.
+    pub fn write(&mut self, save: &Save) -> Result<PathBuf, SaveError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = slot_path(&self.dir, self.next_slot);
+        save.write(&path)?;
+
+        self.elapsed = Duration::ZERO;
+        self.next_slot = (self.next_slot + 1) % AUTOSAVE_SLOTS;
+        Ok(path)
+    }
+}
+
+fn slot_path(dir: &Path, slot: usize) -> PathBuf {
+    dir.join(format!("{} {}.save", AUTOSAVE_NAME, slot + 1))
+}
+
+/// Whether a save slot is one of the autosaves, which the game writes to on its own.
+pub fn is_autosave(name: &str) -> bool {
+    (1..=AUTOSAVE_SLOTS).any(|slot| name == format!("{} {}", AUTOSAVE_NAME, slot))
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::test_dir;
+    use crate::engine::grid::{ Grid, Coord };
+    use super::super::SAVE_VERSION;
+
+    // ----- HELPER FUNCTIONS -----
+    fn save(money: i128) -> Save {
+        Save {
+            date: String::new(),
+            version: SAVE_VERSION,
+            cam_pos: Coord::from((0, 0)),
+            selected: Coord::from((0, 0)),
+            money,
+            settings: Settings::new(),
+            grid: Grid::new::<Coord>(Vec::new()),
+            thumbnail: None,
+        }
+    }
+
+    fn interval_settings(minutes: f32) -> Settings {
+        let mut settings = Settings::new();
+        settings.set(AUTOSAVE_INTERVAL_SETTING, minutes);
+        settings
+    }
+
+    // ----- INTERVAL TESTS -----
+    #[test]
+    fn test_autosave_tick() {
+        let mut autosave = Autosave::new(test_dir("autosave", "tick"), &interval_settings(2.));
+        assert_eq!(autosave.interval(), Some(Duration::from_secs(120)));
+
+        assert!(!autosave.tick(Duration::from_secs(60)));
+        assert!(!autosave.tick(Duration::from_secs(59)));
+        assert!(autosave.tick(Duration::from_secs(1)));
+        // keeps asking until a save is written
+        assert!(autosave.tick(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_autosave_settings() {
+        let autosave = Autosave::new(test_dir("autosave", "settings"), &Settings::new());
+        assert_eq!(autosave.interval(), Some(Duration::from_secs_f32(DEFAULT_AUTOSAVE_INTERVAL * 60.)));
+
+        let mut autosave = Autosave::new(test_dir("autosave", "settings"), &interval_settings(0.));
+        assert_eq!(autosave.interval(), None);
+        assert!(!autosave.tick(Duration::MAX));
+
+        autosave.load_settings(&interval_settings(f32::NAN));
+        assert_eq!(autosave.interval(), Some(Duration::from_secs_f32(DEFAULT_AUTOSAVE_INTERVAL * 60.)));
+
+        let mut settings = Settings::new();
+        autosave.set_interval(Some(Duration::from_secs(90)));
+        autosave.store_settings(&mut settings);
+        assert_eq!(settings.get::<f32>(AUTOSAVE_INTERVAL_SETTING), Some(1.5));
+
+        autosave.set_interval(Some(Duration::ZERO));
+        autosave.store_settings(&mut settings);
+        assert_eq!(settings.get::<f32>(AUTOSAVE_INTERVAL_SETTING), Some(0.));
+    }
+
+    // ----- SLOT TESTS -----
+    #[test]
+    fn test_autosave_rotation() {
+        let dir = test_dir("autosave", "rotation");
+        let mut autosave = Autosave::new(&dir, &Settings::new());
+
+        let mut paths = Vec::new();
+        for money in 0..=AUTOSAVE_SLOTS as i128 {
+            assert!(autosave.tick(Duration::MAX));
+            paths.push(autosave.write(&save(money)).unwrap());
+            assert!(!autosave.tick(Duration::ZERO), "ERROR: writing an autosave didn't restart the countdown");
+        }
+
+        assert_eq!(paths[0], dir.join("autosave 1.save"));
+        assert_eq!(paths[AUTOSAVE_SLOTS], paths[0], "ERROR: autosaves didn't wrap around to the first slot");
+        assert_eq!(Save::read(&paths[0]).unwrap().money, AUTOSAVE_SLOTS as i128);
+        assert_eq!(Save::read(&paths[1]).unwrap().money, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_autosave() {
+        assert!(is_autosave("autosave 1"));
+        assert!(is_autosave(&format!("autosave {}", AUTOSAVE_SLOTS)));
+        assert!(!is_autosave(&format!("autosave {}", AUTOSAVE_SLOTS + 1)));
+        assert!(!is_autosave("autosave"));
+        assert!(!is_autosave("my autosave 1"));
+    }
+
+    #[test]
+    fn test_autosave_resumes_at_oldest() {
+        let dir = test_dir("autosave", "resumes_at_oldest");
+        let mut autosave = Autosave::new(&dir, &Settings::new());
+        let paths: Vec<PathBuf> = (0..AUTOSAVE_SLOTS).map(|money| autosave.write(&save(money as i128)).unwrap()).collect();
+
+        // modification times can be too coarse to tell saves written right after each other apart
+        let now = SystemTime::now();
+        for (i, path) in paths.iter().enumerate() {
+            let age = if i == 1 { 3600 } else { 60 };
+            fs::File::options().write(true).open(path).unwrap().set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+
+        let mut autosave = Autosave::new(&dir, &Settings::new());
+        assert_eq!(autosave.write(&save(10)).unwrap(), paths[1]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
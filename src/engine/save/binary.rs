@@ -0,0 +1,471 @@
+// Binary save layout, all numbers are little endian:
+//
+//  magic     4 bytes, "PLSV"
+//  version   u32 major, u32 minor, u32 patch
+//  checksum  u32, CRC-32 of everything after it
+//  date      string
+//  cam_pos   i32 x, i32 y
+//  selected  i32 x, i32 y
+//  money     i128
+//  settings  u32 count, then count * (string name, setting)
+//  grid      u32 column count, then per column:
+//              i32 x, u32 run count, then per run: i32 y of the bottom tile, u32 length, tile
+//  thumbnail since 0.3.0, u8 0 if there is none, else 1 followed by
+//              u32 width, u32 height, u32 rect count, then per rect: u32 x, y, width, height, 3 * u8 color
+//
+// strings are a u32 byte length followed by UTF-8,
+// settings are a u8 tag (0 bool, 1 float, 2 string, 3 list) followed by the value, lists being a u32 count of settings,
+// tiles are a u8 tag (0 air, 1 ground, 2 building) with buildings followed by f32 health, tier and pressure
+
+use crate::engine::grid::{ Grid, Coord, Tile };
+use crate::engine::settings::{ Settings, Setting };
+
+use super::{ Save, SaveError, Version, SAVE_VERSION, MAX_SETTING_DEPTH, MAX_RUN_LEN, grid_runs };
+use super::thumbnail::{ Thumbnail, ThumbnailRect };
+
+pub const MAGIC: [u8;4] = *b"PLSV";
+const HEADER_LEN: usize = 4 + 3 * 4 + 4;
+
+const SETTING_BOOL: u8 = 0;
+const SETTING_FLOAT: u8 = 1;
+const SETTING_STRING: u8 = 2;
+const SETTING_LIST: u8 = 3;
+
+const TILE_AIR: u8 = 0;
+const TILE_GROUND: u8 = 1;
+const TILE_BUILDING: u8 = 2;
+
+// binary saves older than this end after the grid
+const THUMBNAIL_VERSION: Version = Version::new(0, 3, 0);
+
+// ----- CHECKSUM -----
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc = (crc >> 8) ^ CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+// ----- ENCODING -----
+struct Writer {
+    bytes: Vec<u8>,
+}
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn i128(&mut self, value: i128) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn len(&mut self, len: usize) {
+        // nothing in a save comes close to 4 billion entries, running out of memory would come first
+        self.u32(u32::try_from(len).expect("ERROR: save section too long for the binary format"));
+    }
+    fn string(&mut self, value: &str) {
+        self.len(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+    fn coord(&mut self, coord: Coord) {
+        self.i32(coord.x());
+        self.i32(coord.y());
+    }
+
+    fn setting(&mut self, setting: &Setting) {
+        match setting {
+            Setting::Bool(value) => {
+                self.u8(SETTING_BOOL);
+                self.u8(*value as u8);
+            },
+            Setting::Float(value) => {
+                self.u8(SETTING_FLOAT);
+                self.f32(*value);
+            },
+            Setting::String(value) => {
+                self.u8(SETTING_STRING);
+                self.string(value);
+            },
+            Setting::List(values) => {
+                let values: Vec<&Setting> = values.iter().filter(|s| is_encodable(s)).collect();
+                self.u8(SETTING_LIST);
+                self.len(values.len());
+                for value in values {
+                    self.setting(value);
+                }
+            },
+            Setting::Other(_) => unreachable!("ERROR: Other settings are filtered out before encoding"),
+        }
+    }
+
+    fn tile(&mut self, tile: &Tile) {
+        match tile {
+            Tile::Air => self.u8(TILE_AIR),
+            Tile::Ground => self.u8(TILE_GROUND),
+            Tile::Building { health, tier, pressure } => {
+                self.u8(TILE_BUILDING);
+                self.f32(*health);
+                self.f32(*tier);
+                self.f32(*pressure);
+            },
+        }
+    }
+}
+
+// Other settings are opaque trait objects and are left out of the save, just like in the text format
+fn is_encodable(setting: &Setting) -> bool {
+    !matches!(setting, Setting::Other(_))
+}
+
+pub fn encode(save: &Save) -> Vec<u8> {
+    let mut payload = Writer { bytes: Vec::new() };
+
+    payload.string(&save.date);
+    payload.coord(save.cam_pos);
+    payload.coord(save.selected);
+    payload.i128(save.money);
+
+    let mut settings: Vec<(&String, &Setting)> = save.settings.iter().filter(|(_, s)| is_encodable(s)).collect();
+    settings.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    payload.len(settings.len());
+    for (name, setting) in settings {
+        payload.string(name);
+        payload.setting(setting);
+    }
+
+    // runs come sorted column by column, so each column is a contiguous slice of them
+    let runs = grid_runs(&save.grid);
+    let columns: Vec<&[(Coord, Tile, i32)]> = runs.chunk_by(|a, b| a.0.x() == b.0.x()).collect();
+    payload.len(columns.len());
+    for column in columns {
+        payload.i32(column[0].0.x());
+        payload.len(column.len());
+        for (coord, tile, repeats) in column {
+            payload.i32(coord.y());
+            payload.u32(*repeats as u32 + 1);
+            payload.tile(tile);
+        }
+    }
+
+    match &save.thumbnail {
+        None => payload.u8(0),
+        Some(thumbnail) => {
+            payload.u8(1);
+            payload.u32(thumbnail.width);
+            payload.u32(thumbnail.height);
+            payload.len(thumbnail.rects.len());
+            for rect in &thumbnail.rects {
+                payload.u32(rect.x);
+                payload.u32(rect.y);
+                payload.u32(rect.width);
+                payload.u32(rect.height);
+                payload.bytes.extend_from_slice(&rect.color);
+            }
+        },
+    }
+
+    let mut file = Writer { bytes: Vec::with_capacity(HEADER_LEN + payload.bytes.len()) };
+    file.bytes.extend_from_slice(&MAGIC);
+    file.u32(SAVE_VERSION.major);
+    file.u32(SAVE_VERSION.minor);
+    file.u32(SAVE_VERSION.patch);
+    file.u32(crc32(&payload.bytes));
+    file.bytes.extend_from_slice(&payload.bytes);
+    file.bytes
+}
+
+// ----- DECODING -----
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SaveError> {
+        let end = self.offset.checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(SaveError::UnexpectedEndOfData { offset: self.bytes.len() })?;
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+    fn array<const N: usize>(&mut self) -> Result<[u8;N], SaveError> {
+        let mut array = [0u8;N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+    fn u8(&mut self) -> Result<u8, SaveError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, SaveError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    fn i32(&mut self) -> Result<i32, SaveError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+    fn f32(&mut self) -> Result<f32, SaveError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+    fn i128(&mut self) -> Result<i128, SaveError> {
+        Ok(i128::from_le_bytes(self.array()?))
+    }
+    fn string(&mut self) -> Result<String, SaveError> {
+        let len = self.u32()? as usize;
+        let offset = self.offset;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| SaveError::InvalidUtf8 { offset })
+    }
+    fn coord(&mut self) -> Result<Coord, SaveError> {
+        Ok(Coord::from((self.i32()?, self.i32()?)))
+    }
+
+    fn setting(&mut self, depth: usize) -> Result<Setting, SaveError> {
+        let offset = self.offset;
+        match self.u8()? {
+            SETTING_BOOL => match self.u8()? {
+                0 => Ok(Setting::Bool(false)),
+                1 => Ok(Setting::Bool(true)),
+                tag => Err(SaveError::InvalidTag { tag, offset: offset + 1 }),
+            },
+            SETTING_FLOAT => Ok(Setting::Float(self.f32()?)),
+            SETTING_STRING => Ok(Setting::String(self.string()?)),
+            // lists nested deeper than the text format allows are treated as corrupt rather than risking the stack
+            SETTING_LIST if depth < MAX_SETTING_DEPTH => {
+                let mut list = Vec::new();
+                for _ in 0..self.u32()? {
+                    list.push(self.setting(depth + 1)?);
+                }
+                Ok(Setting::List(list))
+            },
+            tag => Err(SaveError::InvalidTag { tag, offset }),
+        }
+    }
+
+    fn thumbnail(&mut self) -> Result<Option<Thumbnail>, SaveError> {
+        let offset = self.offset;
+        match self.u8()? {
+            0 => Ok(None),
+            1 => {
+                let width = self.u32()?;
+                let height = self.u32()?;
+                let mut rects = Vec::new();
+                for _ in 0..self.u32()? {
+                    rects.push(ThumbnailRect { x: self.u32()?, y: self.u32()?, width: self.u32()?, height: self.u32()?, color: self.array()? });
+                }
+                let thumbnail = Thumbnail { width, height, rects };
+                if !thumbnail.is_valid() {
+                    return Err(SaveError::InvalidThumbnail);
+                }
+                Ok(Some(thumbnail))
+            },
+            tag => Err(SaveError::InvalidTag { tag, offset }),
+        }
+    }
+
+    fn tile(&mut self) -> Result<Tile, SaveError> {
+        let offset = self.offset;
+        match self.u8()? {
+            TILE_AIR => Ok(Tile::Air),
+            TILE_GROUND => Ok(Tile::Ground),
+            TILE_BUILDING => Ok(Tile::Building { health: self.f32()?, tier: self.f32()?, pressure: self.f32()? }),
+            tag => Err(SaveError::InvalidTag { tag, offset }),
+        }
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Save, SaveError> {
+    let mut reader = Reader { bytes, offset: 0 };
+
+    if bytes.len() < MAGIC.len() || reader.array()? != MAGIC {
+        return Err(SaveError::InvalidMagic);
+    }
+    let version = Version::new(reader.u32()?, reader.u32()?, reader.u32()?);
+    // a newer version may have changed the layout, so the rest of the file can't be trusted to make sense
+    if version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion { found: version });
+    }
+    let expected = reader.u32()?;
+    let found = crc32(&bytes[reader.offset..]);
+    if expected != found {
+        return Err(SaveError::ChecksumMismatch { expected, found });
+    }
+
+    let date = reader.string()?;
+    let cam_pos = reader.coord()?;
+    let selected = reader.coord()?;
+    let money = reader.i128()?;
+
+    let mut settings = Settings::new();
+    for _ in 0..reader.u32()? {
+        let name = reader.string()?;
+        let setting = reader.setting(0)?;
+        settings.set(&name, setting);
+    }
+
+    let mut grid = Grid::new::<Coord>(Vec::new());
+    for _ in 0..reader.u32()? {
+        let x = reader.i32()?;
+        for _ in 0..reader.u32()? {
+            let offset = reader.offset;
+            let y = reader.i32()?;
+            let len = reader.u32()?;
+            let tile = reader.tile()?;
+            // the last tile of the run has to fit in the grid as well
+            let top = Some(len)
+                .filter(|len| (1..=MAX_RUN_LEN).contains(len))
+                .and_then(|len| y.checked_add(len as i32 - 1))
+                .ok_or(SaveError::RunOutOfRange { offset })?;
+            for y in y..=top {
+                grid.insert((x, y), tile);
+            }
+        }
+    }
+
+    let mut thumbnail = None;
+    if version >= THUMBNAIL_VERSION {
+        thumbnail = reader.thumbnail()?;
+    }
+
+    if reader.offset != bytes.len() {
+        return Err(SaveError::TrailingData { offset: reader.offset });
+    }
+
+    Ok(Save {
+        date,
+        version,
+        cam_pos,
+        selected,
+        money,
+        settings,
+        grid,
+        thumbnail,
+    })
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_SAVE: &str = include_str!("../../../saves/base.save");
+
+    #[test]
+    fn test_crc32() {
+        // reference values of the IEEE CRC-32, as used by zip and png
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414FA339);
+    }
+
+    #[test]
+    fn test_binary_header() {
+        let bytes = encode(&Save::parse(BASE_SAVE).unwrap());
+
+        assert_eq!(bytes[..4], MAGIC);
+        assert_eq!(bytes[4..8], SAVE_VERSION.major.to_le_bytes());
+        assert_eq!(bytes[8..12], SAVE_VERSION.minor.to_le_bytes());
+        assert_eq!(bytes[12..16], SAVE_VERSION.patch.to_le_bytes());
+        assert_eq!(bytes[16..20], crc32(&bytes[HEADER_LEN..]).to_le_bytes());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let save = Save::parse(BASE_SAVE).unwrap();
+        let decoded = decode(&encode(&save)).unwrap();
+
+        assert_eq!(decoded.date, save.date);
+        assert_eq!(decoded.version, SAVE_VERSION);
+        assert_eq!(decoded.cam_pos, save.cam_pos);
+        assert_eq!(decoded.selected, save.selected);
+        assert_eq!(decoded.money, save.money);
+        assert_eq!(decoded.settings.get::<f32>("visible_width"), Some(7.));
+        assert_eq!(decoded.grid, save.grid);
+    }
+
+    #[test]
+    fn test_binary_is_compact() {
+        let save = Save::parse(BASE_SAVE).unwrap();
+        assert!(encode(&save).len() * 4 < save.serialize().len(), "ERROR: binary save is not much smaller than the text one");
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let bytes = encode(&Save::parse(BASE_SAVE).unwrap());
+
+        assert!(matches!(decode(b"PL"), Err(SaveError::InvalidMagic)));
+        assert!(matches!(decode(BASE_SAVE.as_bytes()), Err(SaveError::InvalidMagic)));
+
+        let mut newer = bytes.clone();
+        newer[4..8].copy_from_slice(&(SAVE_VERSION.major + 1).to_le_bytes());
+        assert!(matches!(decode(&newer), Err(SaveError::UnsupportedVersion { .. })));
+
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xFF;
+        assert!(matches!(decode(&corrupted), Err(SaveError::ChecksumMismatch { .. })));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let checksum = crc32(&trailing[HEADER_LEN..]);
+        trailing[16..20].copy_from_slice(&checksum.to_le_bytes());
+        assert!(matches!(decode(&trailing), Err(SaveError::TrailingData { .. })));
+    }
+
+    #[test]
+    fn test_binary_before_thumbnails() {
+        // a 0.2.0 binary save is the same, minus the thumbnail byte at the end
+        let mut bytes = encode(&Save::parse(BASE_SAVE).unwrap());
+        assert_eq!(bytes.pop(), Some(0));
+        bytes[4..16].copy_from_slice(&[0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]);
+        let checksum = crc32(&bytes[HEADER_LEN..]);
+        bytes[16..20].copy_from_slice(&checksum.to_le_bytes());
+
+        let save = decode(&bytes).unwrap();
+        assert_eq!(save.version, Version::new(0, 2, 0));
+        assert_eq!(save.thumbnail, None);
+    }
+
+    #[test]
+    fn test_binary_truncated_never_panics() {
+        let bytes = encode(&Save::parse(BASE_SAVE).unwrap());
+        for len in 0..bytes.len() {
+            assert!(decode(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_binary_bad_payload_never_panics() {
+        // flip every byte of the payload and fix up the checksum, so the decoder has to deal with the garbage itself
+        let bytes = encode(&Save::parse(BASE_SAVE).unwrap());
+        for i in HEADER_LEN..bytes.len().min(HEADER_LEN + 200) {
+            let mut bad = bytes.clone();
+            bad[i] ^= 0xFF;
+            let checksum = crc32(&bad[HEADER_LEN..]);
+            bad[16..20].copy_from_slice(&checksum.to_le_bytes());
+            let _ = decode(&bad);
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use chrono::prelude::{ DateTime, Utc };
+
+use crate::engine::grid::Tile;
+
+use super::{ Save, SaveError, SaveFormat, Version, SAVE_VERSION, storage };
+use super::thumbnail::Thumbnail;
+
+/// How many previous versions of a slot are kept in the backup directory when it is overwritten.
+pub const SLOT_BACKUPS: usize = 3;
+const MAX_NAME_LEN: usize = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlotOrder {
+    Newest,
+    Oldest,
+    Name,
+    Money,
+}
+
+/// What the save menu shows about a save, without keeping its whole grid around.
+#[derive(Debug, Clone)]
+pub struct SaveSlot {
+    pub name: String,
+    pub path: PathBuf,
+    pub date: String,
+    /// When the save was made, from its date or else its file's modification time, None if neither can be read.
+    pub saved_at: Option<DateTime<Utc>>,
+    /// Version the save was written with.
+    pub version: Version,
+    pub money: i128,
+    pub buildings: usize,
+    pub thumbnail: Option<Thumbnail>,
+}
+impl SaveSlot {
+    fn new(name: String, path: PathBuf, save: &Save) -> Self {
+        // dates carry the offset they were saved in, so they're only comparable once parsed
+        let saved_at = DateTime::parse_from_rfc3339(&save.date).map(|date| date.with_timezone(&Utc)).ok()
+            .or_else(|| fs::metadata(&path).and_then(|metadata| metadata.modified()).map(DateTime::<Utc>::from).ok());
+        SaveSlot {
+            name,
+            path,
+            date: save.date.clone(),
+            saved_at,
+            version: save.version,
+            money: save.money,
+            buildings: save.grid.iter().filter(|(_, tile)| matches!(tile, Tile::Building { .. })).count(),
+            thumbnail: save.thumbnail.clone(),
+        }
+    }
+}
+
+/// The saves in a directory, named after their files and kept sorted.
+///
+/// Only `.save` and `.bsave` files are slots, the backup and trash directories next to them are left alone.
+/// When a text and a binary save share a name only the first one by path is a slot, the other is listed as broken.
+#[derive(Debug)]
+pub struct SaveSlots {
+    dir: PathBuf,
+    order: SlotOrder,
+    slots: Vec<SaveSlot>,
+    broken: Vec<(PathBuf, SaveError)>,
+}
+impl SaveSlots {
+    /// Lists the saves in `dir`, creating it if it doesn't exist yet.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<SaveSlots, SaveError> {
+        let mut slots = SaveSlots::empty(dir);
+        slots.refresh()?;
+        Ok(slots)
+    }
+
+    /// No saves in `dir` without touching it, for when `open` fails and listing it can be retried with `refresh`.
+    pub fn empty<P: AsRef<Path>>(dir: P) -> SaveSlots {
+        SaveSlots {
+            dir: dir.as_ref().to_path_buf(),
+            order: SlotOrder::Newest,
+            slots: Vec::new(),
+            broken: Vec::new(),
+        }
+    }
+
+    /// Reads every save in the directory again, to pick up changes made behind the game's back.
+    pub fn refresh(&mut self) -> Result<(), SaveError> {
+        self.slots.clear();
+        self.broken.clear();
+        fs::create_dir_all(&self.dir)?;
+        let mut paths = fs::read_dir(&self.dir)?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>()?;
+        paths.sort_unstable();
+        for path in paths {
+            let Some(name) = slot_name(&path) else {
+                continue;
+            };
+            if self.is_taken(&name) {
+                self.broken.push((path, SaveError::DuplicateSlot { name }));
+                continue;
+            }
+            match Save::read(&path) {
+                Ok(save) => self.slots.push(SaveSlot::new(name, path, &save)),
+                Err(err) => self.broken.push((path, err)),
+            }
+        }
+        self.broken.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        self.sort();
+        Ok(())
+    }
+
+    fn sort(&mut self) {
+        match self.order {
+            SlotOrder::Newest => self.slots.sort_by(|a, b| b.saved_at.cmp(&a.saved_at).then_with(|| a.name.cmp(&b.name))),
+            SlotOrder::Oldest => self.slots.sort_by(|a, b| a.saved_at.cmp(&b.saved_at).then_with(|| a.name.cmp(&b.name))),
+            SlotOrder::Name => self.slots.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()).then_with(|| a.name.cmp(&b.name))),
+            SlotOrder::Money => self.slots.sort_by(|a, b| b.money.cmp(&a.money).then_with(|| a.name.cmp(&b.name))),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn order(&self) -> SlotOrder {
+        self.order
+    }
+
+    pub fn set_order(&mut self, order: SlotOrder) {
+        self.order = order;
+        self.sort();
+    }
+
+    pub fn slots(&self) -> &[SaveSlot] {
+        &self.slots
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SaveSlot> {
+        self.slots.get(index)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&SaveSlot> {
+        self.slots.iter().find(|slot| slot.name == name)
+    }
+
+    /// The first of `prefix 1`, `prefix 2`, ... that no save is called yet, counting broken saves as taken.
+    pub fn unused_name(&self, prefix: &str) -> String {
+        let mut n = 1;
+        loop {
+            let name = format!("{} {}", prefix, n);
+            if !self.is_taken(&name) {
+                return name;
+            }
+            n += 1;
+        }
+    }
+
+    // broken saves keep their name, so a slot can't be saved or renamed over a file next to it
+    fn is_taken(&self, name: &str) -> bool {
+        return self.find(name).is_some() || self.broken_path(name).is_some();
+    }
+
+    fn broken_path(&self, name: &str) -> Option<&PathBuf> {
+        return self.broken.iter().map(|(path, _)| path).find(|path| slot_name(path).as_deref() == Some(name));
+    }
+
+    /// Save files that failed to read, so they can be shown instead of silently disappearing from the menu.
+    pub fn broken(&self) -> &[(PathBuf, SaveError)] {
+        &self.broken
+    }
+
+    pub fn load(&self, name: &str) -> Result<Save, SaveError> {
+        match self.find(name) {
+            Some(slot) => Save::read(&slot.path),
+            None => Err(SaveError::SlotNotFound { name: name.to_owned() }),
+        }
+    }
+
+    /// Writes `save` to the slot called `name`, creating it as a text save if it doesn't exist yet.
+    /// A broken save of the same name is written over. The slot's previous contents are kept as a backup.
+    pub fn save(&mut self, name: &str, save: &Save) -> Result<(), SaveError> {
+        check_name(name)?;
+        let path = match self.find(name) {
+            Some(slot) => slot.path.clone(),
+            None => match self.broken_path(name) {
+                Some(path) => path.clone(),
+                None => self.dir.join(format!("{}.save", name)),
+            },
+        };
+        save.write_with_backups(&path, SLOT_BACKUPS)?;
+
+        self.slots.retain(|slot| slot.name != name);
+        self.broken.retain(|(broken, _)| *broken != path);
+        let mut slot = SaveSlot::new(name.to_owned(), path, save);
+        slot.version = SAVE_VERSION;
+        self.slots.push(slot);
+        self.sort();
+        Ok(())
+    }
+
+    /// Renames a slot and its backups, keeping its format.
+    pub fn rename(&mut self, name: &str, new_name: &str) -> Result<(), SaveError> {
+        check_name(new_name)?;
+        let Some(index) = self.slots.iter().position(|slot| slot.name == name) else {
+            return Err(SaveError::SlotNotFound { name: name.to_owned() });
+        };
+        if self.is_taken(new_name) {
+            return Err(SaveError::SlotExists { name: new_name.to_owned() });
+        }
+
+        let slot = &mut self.slots[index];
+        let extension = match SaveFormat::from_path(&slot.path) {
+            SaveFormat::Text => "save",
+            SaveFormat::Binary => SaveFormat::BINARY_EXTENSION,
+        };
+        let path = self.dir.join(format!("{}.{}", new_name, extension));
+        storage::rename(&slot.path, &path)?;
+        slot.name = new_name.to_owned();
+        slot.path = path;
+        self.sort();
+        Ok(())
+    }
+
+    /// Moves a slot to the trash and returns where it went, so it can be brought back with `storage::restore`.
+    pub fn delete(&mut self, name: &str) -> Result<PathBuf, SaveError> {
+        let Some(index) = self.slots.iter().position(|slot| slot.name == name) else {
+            return Err(SaveError::SlotNotFound { name: name.to_owned() });
+        };
+        let trashed = storage::trash(&self.slots[index].path)?;
+        self.slots.remove(index);
+        Ok(trashed)
+    }
+}
+
+// slots are the visible save files, named after them without the extension
+fn slot_name(path: &Path) -> Option<String> {
+    if !path.is_file() {
+        return None;
+    }
+    let extension = path.extension()?;
+    if extension != "save" && extension != SaveFormat::BINARY_EXTENSION {
+        return None;
+    }
+    let name = path.file_stem()?.to_str()?;
+    if name.starts_with('.') {
+        return None;
+    }
+    Some(name.to_owned())
+}
+
+/// Slot names become file names, so they are limited to letters, digits, spaces, `-` and `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name.trim() == name
+        && name.chars().count() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+fn check_name(name: &str) -> Result<(), SaveError> {
+    if !is_valid_name(name) {
+        return Err(SaveError::InvalidSlotName { name: name.to_owned() });
+    }
+    Ok(())
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::test_dir;
+    use crate::engine::grid::{ Grid, Coord };
+    use crate::engine::settings::Settings;
+
+    // ----- HELPER FUNCTIONS -----
+    fn save(date: &str, money: i128, buildings: i32) -> Save {
+        let mut tiles = vec![((0, -1), Tile::Ground)];
+        for y in 0..buildings {
+            tiles.push(((0, y), Tile::Building { health: 100., tier: 1., pressure: 100. }));
+        }
+        let grid = Grid::new(tiles);
+        let thumbnail = Thumbnail::from_grid(&grid, Coord::from((0, 0)), [3, 3], |_| Some([255, 255, 255]));
+
+        Save {
+            date: date.to_owned(),
+            version: SAVE_VERSION,
+            cam_pos: Coord::from((0, 0)),
+            selected: Coord::from((0, 0)),
+            money,
+            settings: Settings::new(),
+            grid,
+            thumbnail: Some(thumbnail),
+        }
+    }
+
+    fn names(slots: &SaveSlots) -> Vec<&str> {
+        slots.slots().iter().map(|slot| slot.name.as_str()).collect()
+    }
+
+    // ----- LISTING TESTS -----
+    #[test]
+    fn test_slots_metadata() {
+        let dir = test_dir("slots", "metadata");
+        save("2023-06-01T12:00:00.000+02:00", 1500, 2).write(dir.join("city.save")).unwrap();
+        save("2023-06-02T12:00:00.000+02:00", -20, 0).write(dir.join("binary.bsave")).unwrap();
+
+        let slots = SaveSlots::open(&dir).unwrap();
+        let city = slots.find("city").unwrap();
+
+        assert_eq!(city.path, dir.join("city.save"));
+        assert_eq!(city.date, "2023-06-01T12:00:00.000+02:00");
+        assert_eq!(city.version, SAVE_VERSION);
+        assert_eq!(city.money, 1500);
+        assert_eq!(city.buildings, 2);
+        assert_eq!(city.thumbnail.as_ref().unwrap().rects.len(), 1);
+        assert_eq!(slots.find("binary").unwrap().money, -20);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_skip_other_files() {
+        let dir = test_dir("slots", "skip_other_files");
+        let slot = dir.join("city.save");
+        save("", 0, 0).write_with_backups(&slot, SLOT_BACKUPS).unwrap();
+        save("", 0, 0).write_with_backups(&slot, SLOT_BACKUPS).unwrap();
+        fs::write(dir.join("notes.txt"), "not a save").unwrap();
+        fs::write(dir.join(".city.save.tmp"), "half written").unwrap();
+        fs::write(dir.join("broken.save"), "money {").unwrap();
+        fs::create_dir(dir.join("folder.save")).unwrap();
+
+        let slots = SaveSlots::open(&dir).unwrap();
+
+        assert_eq!(names(&slots), vec!["city"]);
+        assert_eq!(slots.broken().len(), 1);
+        assert_eq!(slots.broken()[0].0, dir.join("broken.save"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_empty() {
+        let dir = test_dir("slots", "empty");
+        fs::remove_dir_all(&dir).unwrap();
+        let mut slots = SaveSlots::empty(&dir);
+        assert!(!dir.exists(), "ERROR: empty slots shouldn't touch the disk");
+
+        slots.refresh().unwrap();
+        assert!(dir.is_dir(), "ERROR: refreshing should create the directory");
+        assert!(slots.slots().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_order() {
+        let dir = test_dir("slots", "order");
+        // C is the newest even though its date sorts before b's as text
+        save("2023-06-02T03:00:00.000+02:00", 10, 0).write(dir.join("b.save")).unwrap();
+        save("2023-06-01T23:00:00.000-05:00", 30, 0).write(dir.join("C.save")).unwrap();
+        save("2023-06-01T12:00:00.000+00:00", 20, 0).write(dir.join("a.save")).unwrap();
+
+        let mut slots = SaveSlots::open(&dir).unwrap();
+        assert_eq!(slots.order(), SlotOrder::Newest);
+        assert_eq!(names(&slots), vec!["C", "b", "a"]);
+        slots.set_order(SlotOrder::Oldest);
+        assert_eq!(names(&slots), vec!["a", "b", "C"]);
+        slots.set_order(SlotOrder::Name);
+        assert_eq!(names(&slots), vec!["a", "b", "C"]);
+        slots.set_order(SlotOrder::Money);
+        assert_eq!(names(&slots), vec!["C", "a", "b"]);
+        assert_eq!(slots.get(0).unwrap().name, "C");
+        assert!(slots.get(3).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_saved_at() {
+        let dir = test_dir("slots", "saved_at");
+        save("2023-06-01T12:00:00.000+02:00", 0, 0).write(dir.join("dated.save")).unwrap();
+        save("yesterday", 0, 0).write(dir.join("undated.save")).unwrap();
+
+        let slots = SaveSlots::open(&dir).unwrap();
+        assert_eq!(slots.find("dated").unwrap().saved_at, Some("2023-06-01T10:00:00Z".parse().unwrap()));
+        let modified = fs::metadata(dir.join("undated.save")).unwrap().modified().unwrap();
+        assert_eq!(slots.find("undated").unwrap().saved_at, Some(modified.into()), "ERROR: saves without a valid date should fall back to their file's modification time");
+        assert_eq!(names(&slots), vec!["undated", "dated"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_duplicate_names() {
+        let dir = test_dir("slots", "duplicate_names");
+        save("", 1, 0).write(dir.join("city.bsave")).unwrap();
+        save("", 2, 0).write(dir.join("city.save")).unwrap();
+
+        let mut slots = SaveSlots::open(&dir).unwrap();
+        assert_eq!(names(&slots), vec!["city"]);
+        assert_eq!(slots.find("city").unwrap().path, dir.join("city.bsave"));
+        assert_eq!(slots.broken().len(), 1);
+        assert!(matches!(&slots.broken()[0], (path, SaveError::DuplicateSlot { name }) if *path == dir.join("city.save") && name == "city"), "ERROR: the second save with the same name should be listed as broken");
+
+        // the slot is the only one written to, and the other file keeps the name taken
+        slots.save("city", &save("", 3, 0)).unwrap();
+        assert!(fs::read(dir.join("city.bsave")).unwrap().starts_with(&super::super::binary::MAGIC));
+        assert_eq!(Save::read(dir.join("city.save")).unwrap().money, 2);
+        slots.save("town", &save("", 0, 0)).unwrap();
+        assert!(matches!(slots.rename("town", "city"), Err(SaveError::SlotExists { .. })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // ----- MANAGEMENT TESTS -----
+    #[test]
+    fn test_slots_save_rename_delete() {
+        let dir = test_dir("slots", "save_rename_delete");
+        let mut slots = SaveSlots::open(&dir).unwrap();
+
+        slots.save("my city", &save("2023-06-01", 100, 1)).unwrap();
+        slots.save("my city", &save("2023-06-02", 200, 1)).unwrap();
+        assert_eq!(names(&slots), vec!["my city"]);
+        assert_eq!(slots.load("my city").unwrap().money, 200);
+        assert_eq!(storage::backups(dir.join("my city.save")).unwrap().len(), 1);
+
+        slots.rename("my city", "renamed").unwrap();
+        assert_eq!(names(&slots), vec!["renamed"]);
+        assert!(dir.join("renamed.save").is_file());
+        assert!(!dir.join("my city.save").exists());
+        assert_eq!(storage::backups(dir.join("renamed.save")).unwrap().len(), 1);
+
+        let trashed = slots.delete("renamed").unwrap();
+        assert!(slots.slots().is_empty());
+        assert!(trashed.is_file());
+        assert!(!dir.join("renamed.save").exists());
+
+        // the cached listing matches what's on disk
+        slots.refresh().unwrap();
+        assert!(slots.slots().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_unused_name() {
+        let dir = test_dir("slots", "unused_name");
+        let mut slots = SaveSlots::open(&dir).unwrap();
+        assert_eq!(slots.unused_name("save"), "save 1");
+
+        slots.save("save 1", &save("", 0, 0)).unwrap();
+        slots.save("save 2", &save("", 0, 0)).unwrap();
+        fs::write(dir.join("save 3.save"), "broken").unwrap();
+        slots.refresh().unwrap();
+        assert_eq!(slots.unused_name("save"), "save 4");
+
+        // a deleted save frees its name without clashing with the ones after it
+        slots.delete("save 1").unwrap();
+        assert_eq!(slots.unused_name("save"), "save 1");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_keep_format() {
+        let dir = test_dir("slots", "keep_format");
+        save("", 0, 0).write(dir.join("binary.bsave")).unwrap();
+        let mut slots = SaveSlots::open(&dir).unwrap();
+
+        slots.save("binary", &save("", 5, 0)).unwrap();
+        slots.rename("binary", "still binary").unwrap();
+
+        assert!(fs::read(dir.join("still binary.bsave")).unwrap().starts_with(&super::super::binary::MAGIC));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slots_errors() {
+        let dir = test_dir("slots", "errors");
+        let mut slots = SaveSlots::open(&dir).unwrap();
+        slots.save("a", &save("", 0, 0)).unwrap();
+        slots.save("b", &save("", 0, 0)).unwrap();
+
+        assert!(matches!(slots.save("../escape", &save("", 0, 0)), Err(SaveError::InvalidSlotName { .. })));
+        assert!(matches!(slots.rename("a", "b"), Err(SaveError::SlotExists { .. })));
+        assert!(matches!(slots.rename("missing", "c"), Err(SaveError::SlotNotFound { .. })));
+        assert!(matches!(slots.delete("missing"), Err(SaveError::SlotNotFound { .. })));
+        assert!(matches!(slots.load("missing"), Err(SaveError::SlotNotFound { .. })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_slot_names() {
+        assert!(is_valid_name("city"));
+        assert!(is_valid_name("my city_2-b"));
+        assert!(is_valid_name("miasto łódź"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("   "));
+        assert!(!is_valid_name(" padded"));
+        assert!(!is_valid_name("a/b"));
+        assert!(!is_valid_name(".hidden"));
+        assert!(!is_valid_name("a.save"));
+        assert!(!is_valid_name(&"a".repeat(MAX_NAME_LEN + 1)));
+    }
+}
@@ -0,0 +1,321 @@
+// crash-safe handling of save files on disk:
+// writes never leave a half written save behind, overwritten saves can be kept as rotating backups
+// and deleted saves are moved to a trash directory they can be restored from
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/// Directory next to the saves that holds their backups.
+pub const BACKUP_DIR: &str = "backups";
+/// Directory next to the saves that holds deleted saves until they are restored or cleaned up by hand.
+pub const TRASH_DIR: &str = "trash";
+
+fn file_name(filepath: &Path) -> io::Result<String> {
+    match filepath.file_name() {
+        Some(name) => Ok(name.to_string_lossy().into_owned()),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is not a file path", filepath))),
+    }
+}
+
+// `slot.save` tagged with `1` becomes `slot.1.save`, keeping the extension so the format is still recognised
+fn tagged_name(filepath: &Path, tag: impl std::fmt::Display) -> io::Result<String> {
+    let name = file_name(filepath)?;
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => Ok(format!("{}.{}.{}", stem, tag, extension)),
+        _ => Ok(format!("{}.{}", name, tag)),
+    }
+}
+
+// the inverse of tagged_name, if the tag is a number
+fn untagged_name(name: &str) -> Option<String> {
+    let is_tag = |tag: &str| !tag.is_empty() && tag.bytes().all(|b| b.is_ascii_digit());
+    let (rest, last) = name.rsplit_once('.')?;
+    if is_tag(last) {
+        return Some(rest.to_owned());
+    }
+    let (stem, tag) = rest.rsplit_once('.')?;
+    if is_tag(tag) && !stem.is_empty() {
+        return Some(format!("{}.{}", stem, last));
+    }
+    None
+}
+
+fn parent(filepath: &Path) -> &Path {
+    match filepath.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+// makes renames and removals in the directory survive a power loss, not needed on windows where renames are durable
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Writes `contents` to `filepath` so that after a crash the file holds either the old or the new contents, never a mix.
+///
+/// The contents go to a temporary file in the same directory, which is flushed to disk and then renamed over the target.
+pub fn write_atomic<P: AsRef<Path>>(filepath: P, contents: &[u8]) -> io::Result<()> {
+    let filepath = filepath.as_ref();
+    let dir = parent(filepath);
+    let temp_path = dir.join(format!(".{}.tmp", file_name(filepath)?));
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp_path, filepath)?;
+        sync_dir(dir)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn backup_path(filepath: &Path, n: usize) -> io::Result<PathBuf> {
+    Ok(parent(filepath).join(BACKUP_DIR).join(tagged_name(filepath, n)?))
+}
+
+/// Copies the current contents of `filepath` into its newest backup, shifting older backups back and
+/// dropping the ones past `keep`. Does nothing if the file doesn't exist yet.
+pub fn rotate_backups<P: AsRef<Path>>(filepath: P, keep: usize) -> io::Result<()> {
+    let filepath = filepath.as_ref();
+    if keep == 0 || !filepath.is_file() {
+        return Ok(());
+    }
+    fs::create_dir_all(parent(filepath).join(BACKUP_DIR))?;
+
+    for n in (1..=keep).rev() {
+        let from = backup_path(filepath, n)?;
+        if !from.exists() {
+            continue;
+        }
+        if n == keep {
+            fs::remove_file(from)?;
+        } else {
+            fs::rename(from, backup_path(filepath, n + 1)?)?;
+        }
+    }
+    write_atomic(backup_path(filepath, 1)?, &fs::read(filepath)?)
+}
+
+/// Backups of `filepath` that still exist, newest first.
+pub fn backups<P: AsRef<Path>>(filepath: P) -> io::Result<Vec<PathBuf>> {
+    let filepath = filepath.as_ref();
+    let mut backups = Vec::new();
+    let mut n = 1;
+    loop {
+        let path = backup_path(filepath, n)?;
+        if !path.is_file() {
+            return Ok(backups);
+        }
+        backups.push(path);
+        n += 1;
+    }
+}
+
+/// Renames a save along with its backups, refusing to overwrite an existing file.
+pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+    if to.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} already exists", to)));
+    }
+
+    fs::rename(from, to)?;
+    // backups left behind by an earlier save of the same name would otherwise get mixed in
+    for stale in backups(to)? {
+        fs::remove_file(stale)?;
+    }
+    for (n, backup) in backups(from)?.into_iter().enumerate() {
+        fs::rename(backup, backup_path(to, n + 1)?)?;
+    }
+    sync_dir(parent(to))?;
+    if to.parent() != from.parent() {
+        sync_dir(parent(from))?;
+    }
+    Ok(())
+}
+
+/// Moves a save into the trash directory next to it and returns its new path.
+///
+/// Trashed files get the time of deletion added to their name, so trashing a save with the same name twice keeps both.
+pub fn trash<P: AsRef<Path>>(filepath: P) -> io::Result<PathBuf> {
+    let filepath = filepath.as_ref();
+    let trash_dir = parent(filepath).join(TRASH_DIR);
+    fs::create_dir_all(&trash_dir)?;
+
+    let mut stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or(0);
+    let mut trashed = trash_dir.join(tagged_name(filepath, stamp)?);
+    while trashed.exists() {
+        stamp += 1;
+        trashed = trash_dir.join(tagged_name(filepath, stamp)?);
+    }
+
+    fs::rename(filepath, &trashed)?;
+    sync_dir(&trash_dir)?;
+    sync_dir(parent(filepath))?;
+    Ok(trashed)
+}
+
+/// Moves a file out of the trash back to where it was deleted from and returns that path.
+///
+/// Fails with `AlreadyExists` instead of overwriting a save that has taken its place since.
+pub fn restore<P: AsRef<Path>>(trashed: P) -> io::Result<PathBuf> {
+    let trashed = trashed.as_ref();
+    let trash_dir = parent(trashed);
+    if trash_dir.file_name() != Some(std::ffi::OsStr::new(TRASH_DIR)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is not in a trash directory", trashed)));
+    }
+
+    let original_name = match untagged_name(&file_name(trashed)?) {
+        Some(original_name) => original_name,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} was not trashed by the game", trashed))),
+    };
+    let restored = parent(trash_dir).join(original_name);
+    if restored.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} already exists", restored)));
+    }
+
+    fs::rename(trashed, &restored)?;
+    sync_dir(parent(trash_dir))?;
+    Ok(restored)
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::testing::test_dir;
+
+    // ----- HELPER FUNCTIONS -----
+    // a fresh directory per test, since tests run in parallel
+    // ----- NAME TESTS -----
+    #[test]
+    fn test_tagged_names() {
+        assert_eq!(tagged_name(Path::new("saves/slot.save"), 3).unwrap(), "slot.3.save");
+        assert_eq!(tagged_name(Path::new("slot.bsave"), 1697500000000u128).unwrap(), "slot.1697500000000.bsave");
+        assert_eq!(tagged_name(Path::new("slot"), 3).unwrap(), "slot.3");
+        assert_eq!(tagged_name(Path::new(".hidden"), 3).unwrap(), ".hidden.3");
+
+        assert_eq!(untagged_name("slot.3.save"), Some("slot.save".to_owned()));
+        assert_eq!(untagged_name("slot.3"), Some("slot".to_owned()));
+        assert_eq!(untagged_name(".hidden.3"), Some(".hidden".to_owned()));
+        assert_eq!(untagged_name("slot.save"), None);
+        assert_eq!(untagged_name("slot.x3.save"), None);
+    }
+
+    // ----- WRITE TESTS -----
+    #[test]
+    fn test_write_atomic() {
+        let dir = test_dir("storage", "write_atomic");
+        let filepath = dir.join("slot.save");
+
+        write_atomic(&filepath, b"first").unwrap();
+        write_atomic(&filepath, b"second").unwrap();
+
+        assert_eq!(fs::read(&filepath).unwrap(), b"second");
+        // the temporary file doesn't stay behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_failure_keeps_old_contents() {
+        let dir = test_dir("storage", "write_atomic_failure");
+        let filepath = dir.join("slot.save");
+        write_atomic(&filepath, b"old").unwrap();
+
+        // the rename fails because a directory is in the way of the temporary file
+        fs::create_dir(dir.join(".slot.save.tmp")).unwrap();
+        assert!(write_atomic(&filepath, b"new").is_err());
+
+        assert_eq!(fs::read(&filepath).unwrap(), b"old");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // ----- BACKUP TESTS -----
+    #[test]
+    fn test_rotate_backups() {
+        let dir = test_dir("storage", "rotate_backups");
+        let filepath = dir.join("slot.save");
+
+        rotate_backups(&filepath, 2).unwrap();
+        assert!(!dir.join(BACKUP_DIR).exists(), "ERROR: backed up a save that doesn't exist");
+
+        for contents in ["1", "2", "3", "4"] {
+            rotate_backups(&filepath, 2).unwrap();
+            write_atomic(&filepath, contents.as_bytes()).unwrap();
+        }
+
+        let backups = backups(&filepath).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read(&backups[0]).unwrap(), b"3");
+        assert_eq!(fs::read(&backups[1]).unwrap(), b"2");
+        assert_eq!(fs::read(&filepath).unwrap(), b"4");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rename_with_backups() {
+        let dir = test_dir("storage", "rename_with_backups");
+        let from = dir.join("old.save");
+        let to = dir.join("new.save");
+        for contents in ["1", "2", "3"] {
+            rotate_backups(&from, 5).unwrap();
+            write_atomic(&from, contents.as_bytes()).unwrap();
+        }
+
+        rename(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read(&to).unwrap(), b"3");
+        assert!(backups(&from).unwrap().is_empty());
+        assert_eq!(backups(&to).unwrap().len(), 2);
+
+        write_atomic(&from, b"other").unwrap();
+        assert_eq!(rename(&from, &to).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&to).unwrap(), b"3");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // ----- TRASH TESTS -----
+    #[test]
+    fn test_trash_and_restore() {
+        let dir = test_dir("storage", "trash_and_restore");
+        let filepath = dir.join("slot.save");
+        write_atomic(&filepath, b"first").unwrap();
+        let first = trash(&filepath).unwrap();
+        write_atomic(&filepath, b"second").unwrap();
+        let second = trash(&filepath).unwrap();
+
+        assert!(!filepath.exists());
+        assert_ne!(first, second, "ERROR: trashing a save twice overwrote the first one");
+        assert_eq!(parent(&first), dir.join(TRASH_DIR));
+
+        assert_eq!(restore(&first).unwrap(), filepath);
+        assert_eq!(fs::read(&filepath).unwrap(), b"first");
+        assert_eq!(restore(&second).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert!(second.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_outside_trash() {
+        let dir = test_dir("storage", "restore_outside_trash");
+        let filepath = dir.join("slot.123.save");
+        write_atomic(&filepath, b"save").unwrap();
+
+        assert_eq!(restore(&filepath).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+use crate::engine::grid::{ Grid, Coord, Tile };
+
+/// Thumbnails are kept small, larger visible areas are cropped around their center.
+pub const MAX_THUMBNAIL_SIZE: u32 = 64;
+
+/// Colors close to how the game draws tiles, air is left empty.
+pub fn tile_color(tile: &Tile) -> Option<[u8;3]> {
+    match tile {
+        Tile::Air => None,
+        Tile::Ground => Some([51, 20, 0]),
+        Tile::Building { .. } => Some([77, 77, 77]),
+    }
+}
+
+/// A filled rectangle of a thumbnail, in tiles from its bottom left corner.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ThumbnailRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub color: [u8;3],
+}
+
+/// A small vector picture of the part of the grid that was on screen when saving,
+/// stored as rectangles so the save menu can draw it at any size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rects: Vec<ThumbnailRect>,
+}
+impl Thumbnail {
+    /// Pictures the `size` tiles around `center`, laid out the same way the game draws the visible grid.
+    /// Tiles `color` returns None for are left empty.
+    pub fn from_grid<F>(grid: &Grid, center: Coord, size: [u32;2], color: F) -> Thumbnail
+    where
+        F: Fn(&Tile) -> Option<[u8;3]>
+    {
+        let width = size[0].min(MAX_THUMBNAIL_SIZE);
+        let height = size[1].min(MAX_THUMBNAIL_SIZE);
+        let left = i64::from(center.x()) - i64::from(width.saturating_sub(1) / 2);
+        let bottom = i64::from(center.y()) - i64::from(height.saturating_sub(1) / 2);
+
+        let color_at = |x: u32, y: u32| {
+            let x = i32::try_from(left + i64::from(x)).ok()?;
+            let y = i32::try_from(bottom + i64::from(y)).ok()?;
+            grid.get((x, y)).and_then(&color)
+        };
+
+        let mut rects: Vec<ThumbnailRect> = Vec::new();
+        // rects that reach the row below the current one, which can be grown upwards
+        let mut open: Vec<usize> = Vec::new();
+        for y in 0..height {
+            let mut still_open = Vec::new();
+            let mut x = 0;
+            while x < width {
+                let Some(color) = color_at(x, y) else {
+                    x += 1;
+                    continue;
+                };
+                let start = x;
+                while x < width && color_at(x, y) == Some(color) {
+                    x += 1;
+                }
+
+                let run = ThumbnailRect { x: start, y, width: x - start, height: 1, color };
+                let below = open.iter().copied().find(|&i| {
+                    let rect = &rects[i];
+                    rect.x == run.x && rect.width == run.width && rect.color == run.color
+                });
+                match below {
+                    Some(i) => {
+                        rects[i].height += 1;
+                        still_open.push(i);
+                    },
+                    None => {
+                        still_open.push(rects.len());
+                        rects.push(run);
+                    },
+                }
+            }
+            open = still_open;
+        }
+
+        Thumbnail { width, height, rects }
+    }
+
+    /// Whether every rect is non-empty and lies inside the thumbnail, which holds for anything `from_grid` makes.
+    pub fn is_valid(&self) -> bool {
+        self.width <= MAX_THUMBNAIL_SIZE
+            && self.height <= MAX_THUMBNAIL_SIZE
+            && self.rects.iter().all(|rect| {
+                rect.width > 0
+                    && rect.height > 0
+                    && rect.x.checked_add(rect.width).is_some_and(|right| right <= self.width)
+                    && rect.y.checked_add(rect.height).is_some_and(|top| top <= self.height)
+            })
+    }
+}
+
+// ----- TESTS -----
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUND: [u8;3] = [51, 20, 0];
+    const BUILDING: [u8;3] = [77, 77, 77];
+
+    #[test]
+    fn test_thumbnail_merges_rects() {
+        let mut tiles = Vec::new();
+        for x in -1..=1 {
+            tiles.push(((x, -1), Tile::Ground));
+            tiles.push(((x, -2), Tile::Ground));
+            tiles.push(((x, 0), Tile::Air));
+        }
+        tiles.push(((0, 0), Tile::Building { health: 100., tier: 1., pressure: 100. }));
+        let grid = Grid::new(tiles);
+
+        let thumbnail = Thumbnail::from_grid(&grid, Coord::from((0, 0)), [3, 3], tile_color);
+
+        assert_eq!(thumbnail.width, 3);
+        assert_eq!(thumbnail.height, 3);
+        assert_eq!(thumbnail.rects, vec![
+            ThumbnailRect { x: 0, y: 0, width: 3, height: 1, color: GROUND },
+            ThumbnailRect { x: 1, y: 1, width: 1, height: 1, color: BUILDING },
+        ]);
+        assert!(thumbnail.is_valid());
+    }
+
+    #[test]
+    fn test_thumbnail_follows_center() {
+        let grid = Grid::new(vec![((10, 10), Tile::Ground), ((0, 0), Tile::Ground)]);
+
+        let thumbnail = Thumbnail::from_grid(&grid, Coord::from((10, 11)), [7, 5], tile_color);
+        assert_eq!(thumbnail.rects, vec![ThumbnailRect { x: 3, y: 1, width: 1, height: 1, color: GROUND }]);
+
+        // centered on the edge of the grid, nothing past it is looked up
+        let thumbnail = Thumbnail::from_grid(&grid, Coord::from((i32::MAX, i32::MIN)), [7, 5], tile_color);
+        assert!(thumbnail.rects.is_empty());
+    }
+
+    #[test]
+    fn test_thumbnail_is_small() {
+        let grid = Grid::new(vec![((0, 0), Tile::Ground)]);
+        let thumbnail = Thumbnail::from_grid(&grid, Coord::from((0, 0)), [1000, 1000], tile_color);
+
+        assert_eq!(thumbnail.width, MAX_THUMBNAIL_SIZE);
+        assert_eq!(thumbnail.height, MAX_THUMBNAIL_SIZE);
+        assert_eq!(thumbnail.rects.len(), 1);
+    }
+
+    #[test]
+    fn test_thumbnail_validity() {
+        let rect = ThumbnailRect { x: 0, y: 0, width: 2, height: 2, color: GROUND };
+        let thumbnail = |rect| Thumbnail { width: 2, height: 2, rects: vec![rect] };
+
+        assert!(thumbnail(rect).is_valid());
+        assert!(!thumbnail(ThumbnailRect { x: 1, ..rect }).is_valid());
+        assert!(!thumbnail(ThumbnailRect { height: 0, ..rect }).is_valid());
+        assert!(!thumbnail(ThumbnailRect { x: u32::MAX, ..rect }).is_valid());
+        assert!(!Thumbnail { width: MAX_THUMBNAIL_SIZE + 1, height: 1, rects: Vec::new() }.is_valid());
+    }
+}
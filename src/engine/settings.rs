@@ -9,10 +9,12 @@ impl Clone for Box<dyn OtherSetting> {
     }
 }
 
+#[derive(Debug, Clone)]
 pub enum Setting {
     Bool(bool),
     Float(f32),
     String(String),
+    List(Vec<Setting>),
     Other(Box<dyn OtherSetting>)
 }
 impl From<bool> for Setting {
@@ -30,6 +32,11 @@ impl From<String> for Setting {
         Setting::String(value)
     }
 }
+impl<S: Into<Setting>> From<Vec<S>> for Setting {
+    fn from(value: Vec<S>) -> Self {
+        Setting::List(value.into_iter().map(|s| s.into()).collect())
+    }
+}
 impl<O> From<O> for Setting 
 where
     O: OtherSetting + 'static
@@ -63,6 +70,14 @@ impl From<&Setting> for Option<String> {
         return None;
     }
 }
+impl From<&Setting> for Option<Vec<Setting>> {
+    fn from(value: &Setting) -> Self {
+        if let Setting::List(value) = value {
+            return Some(value.clone());
+        }
+        return None;
+    }
+}
 
 impl From<&Setting> for Option<Box<dyn OtherSetting>> {
     fn from(value: &Setting) -> Self {
@@ -73,20 +88,21 @@ impl From<&Setting> for Option<Box<dyn OtherSetting>> {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Settings {
     settings: HashMap<String, Setting>,
 }
 impl Settings {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Settings { 
             settings: HashMap::new(), 
         }
     }
 
-    fn get_setting(&self, setting_name: &str) -> Option<&Setting> {
+    pub fn get_setting(&self, setting_name: &str) -> Option<&Setting> {
         self.settings.get(setting_name)
     }
-    fn get<'a, I>(&'a self, setting_name: &str) -> Option<I> 
+    pub fn get<'a, I>(&'a self, setting_name: &str) -> Option<I> 
     where 
         Option<I>: From<&'a Setting>
     {
@@ -97,9 +113,17 @@ impl Settings {
         }
     }
 
-    fn set<S: Into<Setting>>(&mut self, setting_name: &str, value: S) {
+    pub fn set<S: Into<Setting>>(&mut self, setting_name: &str, value: S) {
         self.settings.insert(setting_name.to_owned(), value.into());
     }
+
+    pub fn remove(&mut self, setting_name: &str) -> Option<Setting> {
+        self.settings.remove(setting_name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Setting)> {
+        self.settings.iter()
+    }
 }
 
 // ----- TESTS -----
@@ -132,6 +156,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_settings_list() {
+        let mut settings = Settings::new();
+
+        settings.set("list_setting", vec![7., 5.]);
+
+        let list = settings.get::<Vec<Setting>>("list_setting").unwrap();
+        assert_eq!(
+            vec![Some(7.), Some(5.)],
+            list.iter().map(|s| s.into()).collect::<Vec<Option<f32>>>(),
+            "ERROR: failed assertion when getting Vec<Setting> from settings"
+        );
+    }
+
     #[test]
     fn test_settings_other() {
         let mut settings = Settings::new();
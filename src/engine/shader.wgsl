@@ -1,3 +1,13 @@
+// the game's State in lib.rs draws flat colored shapes with this, the engine's Renderer draws with shapes.wgsl
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+// moves world space vertices into clip space, an identity matrix for shapes drawn straight in clip space
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec4<f32>,
@@ -12,11 +22,11 @@ struct VertexOutput {
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     out.color = in.color;
-    out.clip_position = vec4<f32>(in.position, 1.0);
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(in.color);
-}
\ No newline at end of file
+}
@@ -0,0 +1,43 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+// moves world space vertices into clip space, an identity matrix for shapes drawn straight in clip space
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) fill_color: vec4<f32>,
+    @location(3) fill: vec4<f32>,
+    @location(4) fill_kind: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) fill_color: vec4<f32>,
+    @location(2) fill: vec4<f32>,
+    @location(3) @interpolate(flat) fill_kind: u32,
+    // where the fragment is in the space the shape was drawn in, which fills are laid out in
+    @location(4) position: vec2<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = in.color;
+    out.fill_color = in.fill_color;
+    out.fill = in.fill;
+    out.fill_kind = in.fill_kind;
+    out.position = in.position.xy;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = fwidth(hatch_distances(in.position, in.fill));
+    return fill(in.fill_kind, in.position, in.color, in.fill_color, in.fill, pixel);
+}
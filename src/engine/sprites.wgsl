@@ -0,0 +1,44 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+// moves world space sprites into clip space, an identity matrix for sprites drawn straight in clip space
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// every image sprites are drawn from, packed together
+@group(1) @binding(0)
+var atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.uv = in.uv;
+    out.color = in.color;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(atlas, atlas_sampler, in.uv) * in.color;
+    // transparent pixels would still write their depth and hide what is drawn behind them later
+    if color.a < 0.004 {
+        discard;
+    }
+    return color;
+}
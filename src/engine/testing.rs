@@ -0,0 +1,12 @@
+// fixtures shared by the tests of several modules
+use std::fs;
+use std::path::PathBuf;
+
+/// An empty directory in the system's temp directory for a test to write to, named after its module and the test.
+/// Whatever an earlier run left in it is removed first.
+pub fn test_dir(module: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("poliosis_test_{}_{}", module, name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
@@ -0,0 +1,52 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+
+// tiles are always drawn in world space
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// a vertex of the template mesh of a tile kind, a unit large and centered on the origin
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) fill_color: vec4<f32>,
+    @location(3) fill: vec4<f32>,
+    @location(4) fill_kind: u32,
+};
+
+// one tile drawn from the template, moved to where it is in the world and tinted
+struct InstanceInput {
+    @location(5) offset: vec3<f32>,
+    @location(6) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) fill_color: vec4<f32>,
+    @location(2) fill: vec4<f32>,
+    @location(3) @interpolate(flat) fill_kind: u32,
+    // fills are laid out in the template's space, so every tile gets the same pattern
+    @location(4) position: vec2<f32>,
+    @location(5) tint: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = in.color;
+    out.fill_color = in.fill_color;
+    out.fill = in.fill;
+    out.fill_kind = in.fill_kind;
+    out.position = in.position.xy;
+    out.tint = instance.color;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position + instance.offset, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = fwidth(hatch_distances(in.position, in.fill));
+    return fill(in.fill_kind, in.position, in.color, in.fill_color, in.fill, pixel) * in.tint;
+}
@@ -1,20 +1,31 @@
+use std::collections::HashMap;
+
 use winit::event::ElementState;
 
 use crate::engine::draw::{ Renderer, Point, Color };
 use crate::engine::window::InputHandler;
 
+// what menus remember between frames, kept by whoever builds a UIContext every frame
+#[derive(Default)]
+struct UIState {
+    // keyed by menu id
+    scroll: HashMap<&'static str, f32>,
+}
+
 struct UIContext<'a> {
     menus: Vec<Menu>,
     renderer: &'a mut Renderer,
     input_handler: &'a mut InputHandler,
+    state: &'a mut UIState,
 }
 impl<'a> UIContext<'a> {
-    fn new(renderer: &'a mut Renderer, input_handler: &'a mut InputHandler) -> Self {
+    fn new(renderer: &'a mut Renderer, input_handler: &'a mut InputHandler, state: &'a mut UIState) -> Self {
         
         UIContext { 
             menus: Vec::new(), 
             renderer, 
             input_handler,
+            state,
         }
     }
 
@@ -29,37 +40,47 @@ impl<'a> UIContext<'a> {
 
     fn draw_menus(&mut self) {
         for menu in self.menus.iter_mut() {
-            menu.draw_menu(self.renderer, self.input_handler)
+            let scroll = self.state.scroll.entry(menu.id).or_insert(0.);
+            menu.scroll = *scroll;
+            menu.draw_menu(self.renderer, self.input_handler);
+            *scroll = menu.scroll;
         }
     }
 }
 
 
 struct Menu {
+    // menus keep their scroll between frames by it, so menus on screen at the same time need their own
+    id: &'static str,
     wigets: Vec<Box<dyn Widget>>,
     corners: [Point;2],
     frame_thickness: f32,
     bg_color: Color,
     frame_color: Color,
     spacing: f32,
-    default_text_color: Color
+    default_text_color: Color,
+    // how far the widgets are scrolled up, when there are more of them than fit in the menu
+    scroll: f32,
 }
 impl Default for Menu {
     fn default() -> Self {
         Menu {
+            id: "",
             wigets: Vec::new(),
             corners: [Point::ZERO, Point::ZERO],
             frame_thickness: 0.01,
             bg_color: Color::BLACK,
             frame_color: Color::WHITE,
             spacing: 0.005,
-            default_text_color: Color::WHITE
+            default_text_color: Color::WHITE,
+            scroll: 0.,
         }  
     }
 }
 impl Menu {
-    fn new<P: Into<Point>>(corners: [P;2], frame_thickness: f32, bg_color: Color, frame_color: Color, spacing: f32, default_text_color: Color) -> Self {
+    fn new<P: Into<Point>>(id: &'static str, corners: [P;2], frame_thickness: f32, bg_color: Color, frame_color: Color, spacing: f32, default_text_color: Color) -> Self {
         Menu {
+            id,
             wigets: Vec::new(),
             corners: corners.map(|p| p.into()),
             frame_thickness,
@@ -67,6 +88,7 @@ impl Menu {
             frame_color,
             spacing,
             default_text_color,
+            scroll: 0.,
         }
     }
     fn from_corners<P: Into<Point>>(corners: [P;2]) -> Self{
@@ -77,15 +99,47 @@ impl Menu {
     }
 
     fn draw_menu(&mut self, renderer: &mut Renderer, input_handler: &mut InputHandler) {
+        if input_handler.scroll_delta != 0. && input_handler.cursor_position.within(self.corners) {
+            self.scroll_by(-input_handler.scroll_delta);
+        }
+
         renderer.draw_rect(self.corners, self.bg_color);
         renderer.draw_box(self.corners, self.frame_thickness, self.frame_color);
+        // input keeps click callbacks from earlier frames, the ones of buttons that aren't drawn this frame would be left behind
+        input_handler.remove_mouse_click_event_callbacks_within(self.corners);
 
-        let mut widget_offset = self.corners[0] - [-1. * (self.frame_thickness + self.spacing), self.frame_thickness + self.spacing].into();
+        // widgets scrolled out of the frame are cut off at its edges, and can only be clicked within it
+        let inner = self.inner_corners();
+        renderer.push_clip(inner);
+        let mut widget_offset = self.corners[0] - [-(self.frame_thickness + self.spacing), self.frame_thickness + self.spacing - self.scroll].into();
         for (i, widget) in self.wigets.iter_mut().enumerate() {
             widget_offset = widget_offset - [0., self.spacing * i as f32].into();
-            widget.display_widget(renderer, input_handler,  widget_offset);
-            widget_offset = widget_offset - [0., widget.height()].into();
+            let bottom = widget_offset.y - widget.height();
+            // widgets entirely out of view aren't drawn
+            if widget_offset.y > inner[1].y && bottom < inner[0].y {
+                widget.display_widget(renderer, input_handler, widget_offset, inner);
+            }
+            widget_offset.y = bottom;
         }
+        renderer.pop_clip();
+    }
+
+    // the corners of the menu within its frame
+    fn inner_corners(&self) -> [Point;2] {
+        [self.corners[0].add_x_sub_y(self.frame_thickness), self.corners[1].add_x_sub_y(-self.frame_thickness)]
+    }
+
+    // how far below the top of the frame the widgets go, spaced the way draw_menu spaces them
+    fn content_height(&self) -> f32 {
+        let widgets: f32 = self.wigets.iter().enumerate().map(|(i, widget)| self.spacing * i as f32 + widget.height()).sum();
+        return self.spacing + widgets;
+    }
+
+    /// Scrolls the widgets up by `amount`, down if it's negative, no further than the last one is in view.
+    fn scroll_by(&mut self, amount: f32) {
+        let inner = self.inner_corners();
+        let max_scroll = (self.content_height() - (inner[0].y - inner[1].y)).max(0.);
+        self.scroll = (self.scroll + amount).clamp(0., max_scroll);
     }
 
     fn add_widget<W: Widget + 'static>(&mut self, widget: W) {
@@ -96,7 +150,8 @@ impl Menu {
 }
 
 trait Widget {
-    fn display_widget(&mut self, renderer: &mut Renderer, input_handler: &mut InputHandler, position: Point);
+    // clip is the part of the screen the widget can be seen and clicked in
+    fn display_widget(&mut self, renderer: &mut Renderer, input_handler: &mut InputHandler, position: Point, clip: [Point;2]);
     fn height(&self) -> f32;
     fn set_text_color_if_none(&mut self, text_color: Color);
 }
@@ -129,7 +184,7 @@ impl Widget for Label<'_> {
         self.font_size * self.text.split("\n").collect::<Vec<_>>().len() as f32
     }
 
-    fn display_widget(&mut self, renderer: &mut Renderer, _input_handler: &mut InputHandler, position: Point) {
+    fn display_widget(&mut self, renderer: &mut Renderer, _input_handler: &mut InputHandler, position: Point, _clip: [Point;2]) {
         if let Some(text_color) = self.text_color {
             renderer.draw_text(position, self.text, text_color, self.font_size)
         } else {
@@ -189,12 +244,13 @@ impl<'a> Button<'a> {
             callback, 
         }
     }
-    fn calculate_bounds(&mut self, position: Point) {
+    fn calculate_bounds(&mut self, renderer: &mut Renderer, position: Point) {
         let num_of_lines = self.text.split("\n").collect::<Vec<_>>().len() as f32;
+        let [text_width, _] = renderer.measure_text(self.text, self.font_size);
         self.bounds = Some([
             position,
             [
-                position.x + (((self.text.len() as f32 / num_of_lines) * (self.font_size / 2.)) * 0.95) + (2. * (self.frame_thickness + self.padding)), 
+                position.x + text_width + (2. * (self.frame_thickness + self.padding)), 
                 position.y - (self.font_size * num_of_lines) - (2. * (self.frame_thickness + self.padding)),
             ].into(),
         ])
@@ -204,9 +260,11 @@ impl Widget for Button<'_> {
     fn height(&self) -> f32 {
         self.font_size * self.text.split("\n").collect::<Vec<_>>().len() as f32 + (2. * (self.frame_thickness + self.padding))
     }
-    fn display_widget(&mut self, renderer: &mut Renderer, input_handler: &mut InputHandler, position: Point) {
-        self.calculate_bounds(position);
-        input_handler.add_mouse_click_event_callback(winit::event::MouseButton::Left, self.bounds, self.callback);
+    fn display_widget(&mut self, renderer: &mut Renderer, input_handler: &mut InputHandler, position: Point, clip: [Point;2]) {
+        self.calculate_bounds(renderer, position);
+        if let Some(bounds) = self.bounds.and_then(|bounds| intersect(bounds, clip)) {
+            input_handler.add_mouse_click_event_callback(winit::event::MouseButton::Left, Some(bounds), self.callback);
+        }
         
         if let Some(text_color) = self.text_color {
             if let Some(bounds) = self.bounds {
@@ -226,12 +284,23 @@ impl Widget for Button<'_> {
     }
 }
 
+// the overlap of two rectangles given by their top left and bottom right corners, None if they don't overlap
+fn intersect(a: [Point;2], b: [Point;2]) -> Option<[Point;2]> {
+    let top_left: Point = [a[0].x.max(b[0].x), a[0].y.min(b[0].y)].into();
+    let bottom_right: Point = [a[1].x.min(b[1].x), a[1].y.max(b[1].y)].into();
+    if top_left.x >= bottom_right.x || top_left.y <= bottom_right.y {
+        return None;
+    }
+    return Some([top_left, bottom_right]);
+}
+
 // ----- TESTS -----
 #[cfg(test)]
 mod tests {
     use crate::engine::window::WindowHandler;
 
     use super::*;  
+    use crate::engine::draw::snapshot::headless;
     
     use winit::window::WindowBuilder;
     use winit::event_loop::EventLoopBuilder;
@@ -245,9 +314,10 @@ mod tests {
                 WindowBuilder::default(),
                 EventLoopBuilder::default().with_any_thread(true),
             ).await;
-            window_handler.main_loop(|renderer, input_handler| {
+            let mut ui_state = UIState::default();
+            window_handler.main_loop(move |renderer, input_handler| {
 
-                let mut ui = UIContext::new(renderer, input_handler);
+                let mut ui = UIContext::new(renderer, input_handler, &mut ui_state);
                 
                 let test_menu = ui.add_menu(Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]));
 
@@ -282,7 +352,8 @@ mod tests {
                 WindowBuilder::default(),
                 EventLoopBuilder::default().with_any_thread(true),
             ).await;
-            window_handler.main_loop(|renderer, input_handler| {
+            let mut ui_state = UIState::default();
+            window_handler.main_loop(move |renderer, input_handler| {
 
                 let my_custom_default_button: Button = Button{
                     text: "HELLO!",
@@ -290,7 +361,7 @@ mod tests {
                     ..Button::default()
                 };
 
-                let mut ui = UIContext::new(renderer, input_handler);
+                let mut ui = UIContext::new(renderer, input_handler, &mut ui_state);
                 
                 let mut test_menu = Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]);
 
@@ -316,8 +387,9 @@ mod tests {
                 WindowBuilder::default(),
                 EventLoopBuilder::default().with_any_thread(true),
             ).await;
-            window_handler.main_loop(|renderer, input_handler| {
-                let mut ui = UIContext::new(renderer, input_handler);
+            let mut ui_state = UIState::default();
+            window_handler.main_loop(move |renderer, input_handler| {
+                let mut ui = UIContext::new(renderer, input_handler, &mut ui_state);
                 let first_menu = ui.add_menu(Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]));
                 first_menu.add_widget(
                     Label{
@@ -351,4 +423,106 @@ mod tests {
         pollster::block_on(run())
     }
 
-}
\ No newline at end of file
+    // ----- SCROLLING TESTS -----
+    #[test]
+    fn test_menu_scroll_by() {
+        let mut menu = Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]);
+        menu.scroll_by(0.5);
+        assert_eq!(menu.scroll, 0., "ERROR: menus whose widgets fit shouldn't scroll");
+
+        for _ in 0..20 {
+            menu.add_widget(Label { text: "Hello World", ..Default::default() });
+        }
+        menu.scroll_by(-0.5);
+        assert_eq!(menu.scroll, 0.);
+        menu.scroll_by(0.5);
+        assert_eq!(menu.scroll, 0.5);
+        menu.scroll_by(100.);
+        assert!((menu.scroll - (menu.content_height() - 0.98)).abs() < 1e-5, "ERROR: menus shouldn't scroll past their last widget");
+    }
+
+    #[test]
+    fn test_menu_clip() {
+        let Some(mut renderer) = headless(64, 64) else { return };
+        let mut input_handler = InputHandler::new();
+        let mut menu = Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]);
+        for _ in 0..20 {
+            menu.add_widget(Label { text: "Hello World", ..Default::default() });
+        }
+        // scrolling over the menu, halfway down its widgets
+        input_handler.scroll_delta = -0.5;
+        menu.draw_menu(&mut renderer, &mut input_handler);
+        renderer.render().unwrap();
+
+        assert_eq!(menu.scroll, 0.5);
+        assert_eq!(renderer.clip_depth(), 0, "ERROR: menus should pop the clips they push");
+        let frame = renderer.read_frame().unwrap();
+        let outside = (0..64 * 64).map(|i| (i % 64, i / 64)).filter(|&(x, y)| !(16..48).contains(&x) || !(16..48).contains(&y));
+        assert!(outside.into_iter().all(|(x, y)| frame.pixel(x, y) == [0, 0, 0, 255]), "ERROR: widgets shouldn't be drawn outside of their menu");
+    }
+
+    #[test]
+    fn test_ui_state_scroll() {
+        let Some(mut renderer) = headless(64, 64) else { return };
+        let mut input_handler = InputHandler::new();
+        let mut ui_state = UIState::default();
+        let list = || {
+            let mut menu = Menu { id: "list", ..Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]) };
+            for _ in 0..20 {
+                menu.add_widget(Label { text: "Hello World", ..Default::default() });
+            }
+            menu
+        };
+
+        // menus are built again every frame, but keep their scroll
+        input_handler.scroll_delta = -0.5;
+        let mut ui = UIContext::new(&mut renderer, &mut input_handler, &mut ui_state);
+        ui.add_menu(list());
+        ui.draw_menus();
+        input_handler.scroll_delta = 0.;
+        let mut ui = UIContext::new(&mut renderer, &mut input_handler, &mut ui_state);
+        ui.add_menu(list());
+        ui.add_menu(Menu { id: "other", ..Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]) });
+        ui.draw_menus();
+        assert_eq!(ui.menus[0].scroll, 0.5, "ERROR: menus should keep their scroll between frames");
+        assert_eq!(ui_state.scroll.get("list"), Some(&0.5));
+        assert_eq!(ui_state.scroll.get("other"), Some(&0.), "ERROR: menus should only scroll by their own id");
+    }
+
+    // ----- CLICKING TESTS -----
+    #[test]
+    fn test_menu_click_bounds() {
+        let Some(mut renderer) = headless(64, 64) else { return };
+        let mut input_handler = InputHandler::new();
+        let mut menu = Menu::from_corners([[-0.5, 0.5], [0.5, -0.5]]);
+        menu.add_widget(Button { text: "Hello World!", ..Default::default() });
+        for _ in 0..20 {
+            menu.add_widget(Label { text: "Hello World", ..Default::default() });
+        }
+        let inner = menu.inner_corners();
+
+        // the button is cut off at the top of the menu, and can only be clicked where it's seen
+        menu.scroll = 0.05;
+        menu.draw_menu(&mut renderer, &mut input_handler);
+        let bounds = input_handler.mouse_click_bounds(winit::event::MouseButton::Left).unwrap();
+        assert_eq!(bounds[0].y, inner[0].y, "ERROR: click bounds should be clipped to the menu");
+        assert!(bounds[0].within(inner) && bounds[1].within(inner));
+
+        // scrolled out of view, the button isn't clickable anymore
+        menu.scroll = 1.;
+        menu.draw_menu(&mut renderer, &mut input_handler);
+        assert_eq!(input_handler.mouse_click_bounds(winit::event::MouseButton::Left), None, "ERROR: hidden buttons shouldn't be clickable");
+
+        // callbacks outside of the menu are left alone
+        input_handler.add_mouse_click_event_callback(winit::event::MouseButton::Left, Some([[0.6, 0.5].into(), [0.9, 0.4].into()]), |_| {});
+        menu.draw_menu(&mut renderer, &mut input_handler);
+        assert!(input_handler.mouse_click_bounds(winit::event::MouseButton::Left).is_some());
+    }
+
+    #[test]
+    fn test_intersect() {
+        let a = [[-0.5, 0.5].into(), [0.5, -0.5].into()];
+        assert_eq!(intersect(a, [[0., 1.].into(), [1., 0.].into()]), Some([[0., 0.5].into(), [0.5, 0.].into()]));
+        assert_eq!(intersect(a, [[0.5, 1.].into(), [1., 0.].into()]), None, "ERROR: rectangles that only touch shouldn't overlap");
+    }
+}
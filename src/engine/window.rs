@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
-use winit::event::{VirtualKeyCode, Event, WindowEvent, KeyboardInput, ElementState, MouseButton};
+use winit::event::{VirtualKeyCode, Event, WindowEvent, KeyboardInput, ElementState, MouseButton, MouseScrollDelta};
 use winit::event_loop::EventLoop;
 use winit::window::WindowBuilder;
 use winit::{event_loop::EventLoopBuilder, window::Window};
 
 use crate::engine::draw::Renderer;
+use crate::engine::capture::CaptureKeys;
 
 use super::draw::Point;
 
@@ -55,6 +56,10 @@ impl WindowHandler {
                         WindowEvent::Resized(new_size) => {
                             self.renderer.resize(new_size);
                         },
+                        WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                            self.renderer.set_scale_factor(scale_factor);
+                            self.renderer.resize(*new_inner_size);
+                        },
                         WindowEvent::CloseRequested => {
                             control_flow.set_exit();
                         },
@@ -71,18 +76,34 @@ impl WindowHandler {
                                 }
                             }
                         }
+                        WindowEvent::MouseWheel { delta, .. } => {
+                            self.input_handler.scroll_delta += match delta {
+                                MouseScrollDelta::LineDelta(_, y) => y * 0.1,
+                                MouseScrollDelta::PixelDelta(position) => (position.y / (self.renderer.size.height as f64 / 2.)) as f32,
+                            };
+                        }
                         WindowEvent::KeyboardInput { input: KeyboardInput{ state: key_state, virtual_keycode: Some(event_key), .. }, .. } => { 
                             for (callback_key, callback) in self.input_handler.key_event_callbacks.iter() {
                                 if callback_key == &event_key {
                                     callback(key_state);
                                 }
                             }
+                            if key_state == ElementState::Pressed {
+                                self.input_handler.capture_keys.key_pressed(event_key);
+                            }
                         }
                         _ => ()
                     }
                 },
                 Event::MainEventsCleared => {
+                    if self.input_handler.capture_keys.before_frame() {
+                        self.renderer.request_capture();
+                    }
+
                     f(&mut self.renderer, &mut self.input_handler);
+                    self.input_handler.scroll_delta = 0.;
+
+                    self.input_handler.capture_keys.after_frame(self.renderer.take_capture());
                 },
                 _ => ()
             }
@@ -92,14 +113,19 @@ impl WindowHandler {
 pub struct InputHandler {
     key_event_callbacks: HashMap<VirtualKeyCode, fn(ElementState) -> ()>,
     mouse_click_event_callbacks: HashMap<MouseButton, ([Point;2], fn(ElementState) -> ())>,
+    capture_keys: CaptureKeys,
     pub cursor_position: Point,
+    /// How far the mouse wheel was scrolled since the last frame in clip space, positive when scrolled up.
+    pub scroll_delta: f32,
 }
 impl InputHandler {
     pub fn new() -> Self {
         InputHandler { 
             key_event_callbacks: HashMap::new(), 
             mouse_click_event_callbacks: HashMap::new(), 
+            capture_keys: CaptureKeys::default(),
             cursor_position: Point::ZERO,
+            scroll_delta: 0.,
         }
     }
     pub fn add_key_event_callback(&mut self, key: VirtualKeyCode, callback: fn(ElementState)) {
@@ -113,6 +139,22 @@ impl InputHandler {
             self.mouse_click_event_callbacks.insert(button, (bounds, callback));
         }
     }
+    /// Removes the mouse click callbacks whose bounds lie entirely within `bounds`.
+    pub fn remove_mouse_click_event_callbacks_within(&mut self, bounds: [Point;2]) {
+        self.mouse_click_event_callbacks.retain(|_, (callback_bounds, _)| !(callback_bounds[0].within(bounds) && callback_bounds[1].within(bounds)));
+    }
+    /// Where clicking `button` calls its callback, if it has one.
+    pub fn mouse_click_bounds(&self, button: MouseButton) -> Option<[Point;2]> {
+        return self.mouse_click_event_callbacks.get(&button).map(|(bounds, _)| *bounds);
+    }
+    /// Pressing `key` saves the next frame to `capture::SCREENSHOT_DIR`.
+    pub fn bind_screenshot(&mut self, key: VirtualKeyCode) {
+        self.capture_keys.bind_screenshot(key);
+    }
+    /// Pressing `key` starts recording every `every`th frame to `capture::RECORDING_DIR`, pressing it again stops.
+    pub fn bind_recording(&mut self, key: VirtualKeyCode, every: u32) {
+        self.capture_keys.bind_recording(key, every);
+    }
 }
 
 // ----- TESTS -----
@@ -121,6 +163,18 @@ mod tests {
     use super::*;  
     
     use winit::platform::wayland::EventLoopBuilderExtWayland;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::engine::capture::SCREENSHOT_DIR;
+    use crate::engine::draw::snapshot::headless;
+
+    // ----- HELPER FUNCTIONS -----
+    fn screenshots() -> Vec<PathBuf> {
+        return match fs::read_dir(SCREENSHOT_DIR) {
+            Ok(entries) => entries.map(|entry| entry.unwrap().path()).collect(),
+            Err(_) => Vec::new(),
+        };
+    }
 
     #[test]
     fn test_windowhandler() {
@@ -155,6 +209,29 @@ mod tests {
         pollster::block_on(run())
     }
 
+    #[test]
+    fn test_inputhandler_screenshot() {
+        let Some(mut renderer) = headless(32, 32) else { return };
+        let mut input_handler = InputHandler::new();
+        input_handler.bind_screenshot(VirtualKeyCode::F12);
+        let existing = screenshots();
+
+        // what main_loop does for a pressed key and the frame after it
+        input_handler.capture_keys.key_pressed(VirtualKeyCode::F12);
+        if input_handler.capture_keys.before_frame() {
+            renderer.request_capture();
+        }
+        renderer.draw_triangle([[0.0, 0.5], [-0.5, -0.5], [0.5, -0.5]], [1., 0., 0.]);
+        renderer.render().unwrap();
+        input_handler.capture_keys.after_frame(renderer.take_capture());
+
+        let written: Vec<_> = screenshots().into_iter().filter(|path| !existing.contains(path)).collect();
+        assert_eq!(written.len(), 1, "ERROR: pressing the screenshot key should write one screenshot");
+        let png = fs::read(&written[0]).unwrap();
+        fs::remove_file(&written[0]).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n", "ERROR: screenshot wasn't written as a PNG");
+    }
+
     #[test]
     fn test_inputhandler_cursor_position() {
         async fn run() {
@@ -188,4 +265,15 @@ mod tests {
         }
         pollster::block_on(run())
     }
+
+    #[test]
+    fn test_inputhandler_remove_mouse_click_callbacks() {
+        let mut input_handler = InputHandler::new();
+        input_handler.add_mouse_click_event_callback(MouseButton::Left, Some([[-0.5, 0.5].into(), [0.5, -0.5].into()]), |_| {});
+        input_handler.add_mouse_click_event_callback(MouseButton::Right, Some([[0.5, 0.5].into(), [1., -0.5].into()]), |_| {});
+
+        input_handler.remove_mouse_click_event_callbacks_within([[-1., 1.].into(), [0.75, -1.].into()]);
+        assert_eq!(input_handler.mouse_click_bounds(MouseButton::Left), None);
+        assert_eq!(input_handler.mouse_click_bounds(MouseButton::Right), Some([[0.5, 0.5].into(), [1., -0.5].into()]), "ERROR: callbacks reaching out of the bounds should be kept");
+    }
 }
\ No newline at end of file
@@ -5,23 +5,24 @@ use winit::{
 };
 use wgpu::util::DeviceExt;
 use std::ops::Add;
-use std::collections::HashMap;
-use wgpu_glyph::{ab_glyph, GlyphBrushBuilder, Section, Text};
-use std::time::Duration;
+use wgpu_glyph::{ab_glyph, GlyphBrushBuilder, OwnedSection, Section, Text};
+use std::time::{ Duration, Instant };
 use std::thread::sleep;
-use std::fs::{
-    File,
-    self,
-};
-use std::error::Error;
-use std::io::prelude::*;
-use regex::Regex;
 use chrono::prelude::{
     SecondsFormat,
     Local,
 };
-use std::fmt;
-use std::path;
+
+pub mod engine;
+mod game;
+use engine::grid::{ Coord, Direction, Grid, Tile };
+use engine::save::{ autosave, Autosave, Save, SaveError, SaveSlots, SAVE_VERSION };
+use engine::save::thumbnail::{ self, Thumbnail };
+use engine::settings::Settings;
+use engine::draw::{ self, Camera, ChunkMeshes, Frame, MeshStore };
+use engine::draw::text;
+use engine::draw::camera::DEFAULT_ANIMATION;
+use engine::capture::{ self, CaptureKeys };
 
 
 #[repr(C)]
@@ -85,28 +86,34 @@ impl ColorRGB {
     }
 }
 
+#[derive(Default)]
 struct TwinBuffers {
     vertices: Vec<Vertex>,
-    indices: Vec<u16>,
+    indices: Vec<u32>,
+    // the first world_indices indices are drawn in world space through the camera, like the grid
+    world_indices: usize,
 }
 impl TwinBuffers {
     fn new() -> Self {
         TwinBuffers {
             vertices: Vec::new(),
             indices: Vec::new(),
+            world_indices: 0,
         }
     }
 
     fn draw_triangle(&mut self, points: [[f32;2];3], color: [f32;3]) {
+        // indices point at vertices, so they start from how many vertices there are before these
+        let offset = u32::try_from(self.vertices.len() + 2).map(|last| last - 2)
+            .expect("ERROR: too many vertices to index with u32");
+
         self.vertices.push(Vertex::new([points[0][0], points[0][1], 0.0], color));
         self.vertices.push(Vertex::new([points[1][0], points[1][1], 0.0], color));
         self.vertices.push(Vertex::new([points[2][0], points[2][1], 0.0], color));
 
-        let offset = self.indices.len();
-
-        self.indices.push((offset + 0) as u16);
-        self.indices.push((offset + 1) as u16);
-        self.indices.push((offset + 2) as u16);
+        self.indices.push(offset);
+        self.indices.push(offset + 1);
+        self.indices.push(offset + 2);
     }
 
     fn draw_rectangle(&mut self, corners: [[f32;2];2], color: [f32;3]) {
@@ -206,340 +213,142 @@ impl TwinBuffers {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Tile {
-    Air,
-    Ground,
-    Building { health: f32, tier: f32 , pressure: f32},
-}
+// converts a loaded save into the values run() keeps its state in
+fn unpack_save(save: Save) -> ([f32;2], Grid, Coord, Coord, i32) {
+    let visible_size = [
+        save.settings.get::<f32>("visible_width").unwrap_or(7.),
+        save.settings.get::<f32>("visible_height").unwrap_or(5.),
+    ];
+    let money = save.money.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
 
-impl fmt::Display for Tile {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
+    (visible_size, save.grid, save.cam_pos, save.selected, money)
 }
 
-#[derive(Debug)]
-struct SaveError(String);
-
-impl std::fmt::Display for SaveError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "An error ocured while parsing the save file: {}", self.0)
-    }
-}
-
-impl Error for SaveError {}
-
-#[derive(Debug)]
-struct Settings {
-    tile_ratio: [i32;2],
-}
-
-struct Save<'a> {
-    filepath: &'a str,
-    date: &'a str,
-    ver: &'a str,
-    cam_pos: [i32;2],
-    selected: [i32;2],
-    money: i32,
-    settings: Settings,
-    grid: HashMap<i32, HashMap<i32, Tile>>,
-}
-impl Save<'_> {
-    fn read(filepath: &str) -> Result<Save, Box<dyn Error>> {
-        println!("beggining read at {}", filepath);
-        let mut file = File::open(filepath)?;
-        let mut contents = String::new();
-
-        file.read_to_string(&mut contents)?;
-
-        let contents = &*Regex::new(r"\s")?.replace_all(&contents, ""); // shadow content for itself with all whitespaces removed
-        let contents = &contents.to_lowercase();
-
-        let cam_pos = Regex::new(r"camera_position\{x:([0-9|-]+?),y:([0-9|-]+?),\}")?.captures(contents).unwrap();
-        let cam_pos: [i32;2] = [cam_pos[1].parse()?, cam_pos[2].parse()?];
-
-        let selected = Regex::new(r"selected\{x:([0-9|-]+?),y:([0-9|-]+?),\}")?.captures(contents).unwrap();
-        let selected: [i32;2] = [selected[1].parse()?, selected[2].parse()?];
-
-        let money = Regex::new(r"money\{([0-9|-]+?)\}")?.captures(contents).unwrap();
-        let money: i32 = money[1].parse()?;
-
-        let settings = Regex::new(r"settings\{tile_ratio:\[([0-9|-]+?),([0-9|-]+?)\],\}")?.captures(contents).unwrap();
-        let settings: Settings = Settings { tile_ratio: [settings[1].parse()?, settings[2].parse()?] };
-
-        let mut grid: HashMap<i32, HashMap<i32, Tile>> = HashMap::new();
-
-        for t in Regex::new(r"tile\{x:([0-9|-]+?),y:([0-9|-]+?),filling:(.+?),\},")?.captures_iter(contents) {
-            let x: i32 = t[1].parse()?;
-            let y: i32 = t[2].parse()?;
-            let filling = match &t[3] {
-                "air" => { Tile::Air },
-                "ground" => { Tile::Ground },
-                _ if &t[3][..8] == "building" => { 
-
-                    let b =  Regex::new(r"building\{health:([0-9|-|.]+?),tier:([0-9|-]+?)")?.captures(&t[3]).unwrap();
-
-                    let health: f32 = b[1].parse()?;
-                    let tier: f32 = b[2].parse()?;
-
-                    Tile::Building {health: health, tier: tier, pressure: 0.}
-                },
-                _ => { Tile::Air },
-            };
-
-
-            if !grid.contains_key(&x) {
-                grid.insert(x, HashMap::from([
-                    (y, filling)
-                ]));
-            } else{
-                grid.get_mut(&x)
-                    .unwrap()
-                    .insert(y, filling);
-            }
-        }
-
-        for t in Regex::new(r"tile\{x:([0-9|-]+?),y:([0-9|-]+?),filling:([a-z]+?),\},\[repeat\(([0-9]+?)\)times\(([a-z]+?)\)\]")?.captures_iter(contents) {
-            let x: i32 = t[1].parse()?;
-            let y: i32 = t[2].parse()?;
-            let filling = match &t[3] {
-                "air" => { Tile::Air },
-                "ground" => { Tile::Ground },
-                _ if &t[3][..8] == "building" => { 
-
-                    let b =  Regex::new(r"building\{health:([0-9|-|.]+?),tier:([0-9|-]+?)")?.captures(&t[3]).unwrap();
-
-                    let health: f32 = b[1].parse()?;
-                    let tier: f32 = b[2].parse()?;
-
-                    Tile::Building {health: health, tier: tier, pressure: 0.}
-                },
-                _ => { Tile::Air },
-            };
-
-            let rep_num: i32 = t[4].parse()?;
-            let rep_direction: i32 = match &t[5] {
-                "up" => { 1 },
-                "down" => { -1},
-                _ => { return Err(Box::new(SaveError(format!("incorrect repetition direction in tile at x: {}, y: {}", x, y)))) },
-            };
-
-            for i in 0..rep_num{
-                grid.get_mut(&x)
-                        .unwrap()
-                        .insert(y + ((i + 1) * rep_direction), filling);
-            }
-        }
-      
-        Ok(Save {
-            filepath,
-            date: "",
-            ver: "",
-            cam_pos,
-            selected,
-            money,
-            settings,
-            grid,
-        })
-    }
-
-    fn write(
-        filepath: &str,
-        date: &str,
-        ver: &str,
-        cam_pos: [i32;2],
-        selected: [i32;2],
-        money: &i32,
-        settings: Settings,
-        grid: &HashMap<i32, HashMap<i32, Tile>>,
-     ) -> Result<(), Box<dyn Error>>{
-
-        let mut contents = String::new();
-        // generating the contents in memory before pushing it all at once to the save file might suffer from drawbacks 
-        // and not behave as desired during abrupt program termination
-
-        contents.push_str(&format!("[Date: {}] \n", date));
-        contents.push_str(&format!("[Version: {}] \n\n", ver));
-
-        // values aren't space padded
-        contents.push_str(&format!("camera_position {{ \n    x: {},\n    y: {},\n}}\n\n", cam_pos[0], cam_pos[1]));
-        contents.push_str(&format!("selected {{ \n    x: {},\n    y: {},\n}}\n\n", selected[0], selected[1]));
-        contents.push_str(&format!("money {{ {} }}\n\n", money));
-        contents.push_str(&format!("settings {{ \n    tile_ratio: {:?},\n}}\n\n", settings.tile_ratio));
-        
-        contents.push_str(&format!("grid {{ \n"));
-
-
-        let mut max_x_len = 1;
-        let mut max_y_len = 1;
-        let mut max_f_len = 1;
-        let mut max_r_len = 1;
-
-        let mut previous: Option<Tile> = None;
-        let mut rep_count = 0;
-
-        for col_key in grid.keys() {
-            if col_key.to_string().len() > max_x_len {
-                max_x_len = col_key.to_string().len();
-            }
-            let mut row_keys = grid.get(&col_key).unwrap().keys().collect::<Vec<&i32>>();
-            row_keys.sort();
-            for row_key in &row_keys {
-                if row_key.to_string().len() > max_y_len {
-                    max_y_len = row_key.to_string().len();
-                }
-                let filling = *grid.get(col_key).unwrap().get(row_key).unwrap();
-                if filling.to_string().len() > max_f_len {
-                    if !matches!(filling, Tile::Building{..}) {
-                        max_f_len = filling.to_string().len();
-                    }
-                }
-
-                if previous == Some(filling) && *row_key != row_keys[0] {
-                    rep_count += 1;
-                } else {
-                    rep_count = 1;
-                    previous = Some(filling);
-                }
-
-                if rep_count.to_string().len() > max_r_len {
-                    max_r_len = rep_count.to_string().len();
-                }
-
-            }
-
-
-        }
-
-        let mut previous: Option<Tile> = None;
-        let mut rep_count: i32 = 0;
-        
-        let mut col_keys = grid.keys().collect::<Vec<&i32>>();
-        col_keys.sort();
-        for col_key in col_keys.iter().rev() {
-            let mut row_keys = grid.get(&col_key).unwrap().keys().collect::<Vec<&i32>>();
-            row_keys.sort();
-            for row_key in row_keys.iter().rev() {
-                let mut row_key = **row_key;
-                
-                let filling = *grid.get(&col_key).unwrap().get(&row_key).unwrap();                      
-                
-                if previous == Some(filling) && row_key != *row_keys[0] {
-                    rep_count += 1;
-                } else{    
-                    if row_key == *row_keys[0] {
-                        rep_count += 1;
-                        let row_key = &mut row_key;
-                        *row_key -= 1
-                    }
-                    
-                    let x_spacing = " ".repeat(max_x_len - col_key.to_string().len());
-
-                    if rep_count == 1 && previous != None{
-                        let y_spacing = " ".repeat(max_y_len - (row_key).to_string().len());
-                        let f_spacing;
-                        if let Tile::Building { health, tier, .. } = previous.unwrap() {
-                            contents.push_str(&format!("    tile {{ x: {}{}, y: {}{}, filling: Building {{ health: {}, tier: {}, }}, }},\n", x_spacing, col_key, y_spacing, row_key + 1, health, tier));
-                        } else {
-                            f_spacing = " ".repeat(max_f_len - filling.to_string().len());
-                            contents.push_str(&format!("    tile {{ x: {}{}, y: {}{}, filling: {}{}, }},\n", x_spacing, col_key, y_spacing, row_key + 1, f_spacing, previous.unwrap(),));
-                        }
-                    } else if rep_count > 1{
-                        let y_spacing = " ".repeat(max_y_len - (row_key + 1).to_string().len());
-                        let f_spacing;
-                        if !matches!(filling, Tile::Building{..}) {
-                            f_spacing = " ".repeat(max_f_len - previous.unwrap().to_string().len());
-                        } else {
-                            f_spacing = String::new();
-                        }
-                        let r_padding = " ".repeat(max_r_len - (rep_count - 1).to_string().len());
-                        contents.push_str(&format!("    tile {{ x: {}{}, y: {}{}, filling: {}{}, }}, [ Repeat ({}{}) times (  up) ]\n", x_spacing, col_key, y_spacing, row_key + 1, f_spacing, previous.unwrap(), r_padding, rep_count - 1));
-                    }
-
-
-                    
-                    previous = Some(filling);
-                    rep_count = 1;
-                }                
-            }
-            contents.push_str(&format!("\n"));
-            previous = None;
-        }
-        
-        contents.push_str(&format!("}}"));
-
-
-        fs::write(filepath, &contents)?;
-
-        Ok(())
+// the inverse of unpack_save, stamped with the current date
+fn pack_save(visible_size: [f32;2], grid: &Grid, cam_pos: Coord, selected: Coord, money: i32, autosave: &Autosave) -> Save {
+    let mut settings = Settings::new();
+    settings.set("visible_width", visible_size[0]);
+    settings.set("visible_height", visible_size[1]);
+    autosave.store_settings(&mut settings);
+
+    let thumbnail_size = visible_size.map(|size| size.round().max(0.) as u32);
+    let thumbnail = Thumbnail::from_grid(grid, cam_pos, thumbnail_size, thumbnail::tile_color);
+
+    Save {
+        date: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        version: SAVE_VERSION,
+        cam_pos,
+        selected,
+        money: money.into(),
+        settings,
+        grid: grid.clone(),
+        thumbnail: Some(thumbnail),
     }
 }
 
-
 pub async fn run() {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
     let mut buffers = TwinBuffers::new();
     
-    let save = Save::read("saves/base.save").unwrap();
-    let mut visible_size = save.settings.tile_ratio;
+    let base_save = match Save::read("saves/base.save") {
+        Ok(save) => save,
+        Err(err) => {
+            match (&err, err.position()) {
+                (SaveError::Io(io_err), _) if io_err.kind() == std::io::ErrorKind::NotFound => eprintln!("saves/base.save doesn't exist, starting with an empty grid"),
+                (_, Some(position)) => eprintln!("saves/base.save is broken at line {}, column {}, starting with an empty grid\n{}", position.line, position.column, err),
+                (_, None) => eprintln!("saves/base.save couldn't be loaded, starting with an empty grid\n{}", err),
+            }
+            Save {
+                date: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+                version: SAVE_VERSION,
+                cam_pos: Coord::from((0, 0)),
+                selected: Coord::from((0, 0)),
+                money: 0,
+                settings: Settings::new(),
+                grid: Grid::new(Vec::<(Coord, Tile)>::new()),
+                thumbnail: None,
+            }
+        },
+    };
+    let mut autosave = Autosave::new("./saves", &base_save.settings);
+    let mut last_frame = Instant::now();
+    let (mut visible_size, mut grid, mut cam_pos, mut selected, mut money) = unpack_save(base_save);
+
+    // how much Q and E change the visible size by
+    const ZOOM_STEP: f32 = 1.25;
+    // the simulation steps once per unpaused frame, this is the time each step stands for
+    const SIMULATION_STEP: Duration = Duration::from_micros(16_667);
+
+    // moves the camera without animating, when a save is loaded
+    fn jump_camera(camera: &mut Camera, cam_pos: Coord, visible_size: [f32;2]) {
+        camera.set_position(camera.grid_to_world(cam_pos));
+        camera.set_view_size(visible_size);
+    }
 
-    let mut grid = save.grid;
-    let mut cam_pos = save.cam_pos;
-    let mut selected = save.selected;
+    // reads the save at index in the save menu, with the autosave interval it was saved with
+    fn load_slot(save_slots: &SaveSlots, index: usize, autosave: &mut Autosave) -> Option<([f32;2], Grid, Coord, Coord, i32)> {
+        let slot = save_slots.get(index)?;
+        match save_slots.load(&slot.name) {
+            Ok(save) => {
+                autosave.load_settings(&save.settings);
+                return Some(unpack_save(save));
+            },
+            Err(err) => {
+                eprintln!("{}", err);
+                return None;
+            },
+        }
+    }
 
-    fn draw_grid(visible_size: &[i32;2], cam_pos: [i32;2], selected: [i32;2], grid: &HashMap<i32, HashMap<i32, Tile>>, buffers: &mut TwinBuffers) {
-        let tile_width = 2. / visible_size[0] as f32;
-        let tile_height = 2. / visible_size[1] as f32;
+    // tiles are drawn in world space, where each one is a unit large and centered on its coord
+    fn draw_tile(camera: &Camera, coord: Coord, tile: &Tile, selected: bool, buffers: &mut TwinBuffers) {
+        let center = camera.grid_to_world(coord);
 
-        for col_key in -(visible_size[0] - 1) / 2..(visible_size[0] + 1) / 2 {
+        let corner_a = [center.x - 0.5, center.y + 0.5];
+        let corner_c = [center.x + 0.5, center.y - 0.5];
 
-            for row_key in -(visible_size[1] - 1) / 2..(visible_size[1] + 1) / 2 {
-                let offset_x = tile_width * col_key as f32;
-                let offset_y = tile_height * row_key as f32;
+        let mut air_color = [0.01, 0.01, 0.01];
+        let mut ground_color = [0.2, 0.08, 0.0];
 
-                let corner_a = [offset_x - tile_width / 2., offset_y + tile_height / 2.];
-                let corner_c = [offset_x + tile_width / 2., offset_y - tile_height / 2.];
+        if selected {
+            air_color = [1., 1., 0.];
+            ground_color = [1., 1., 0.];
+        }
 
-                let mut air_color = [0.01, 0.01, 0.01];
-                let mut ground_color = [0.2, 0.08, 0.0];
+        let thickness = 0.03;
 
-                if col_key == selected[0] - cam_pos[0] && row_key == selected[1] - cam_pos[1] {
-                    air_color = [1., 1., 0.];
-                    ground_color = [1., 1., 0.];
+        match tile {
+            Tile::Air => {
+                buffers.draw_box([corner_a, corner_c], thickness, air_color)
+            },
+            Tile::Ground => {
+                buffers.draw_crossed_box([corner_a, corner_c], thickness, 3, ground_color)
+            },
+            Tile::Building {health, tier, ..} => {
+                let mut building_color = [0.3 - (((health / tier) - 100.) / 100.), 0.3 + (((health / tier) - 100.) / 200.), 0.3 + (((health / tier) - 100.) / 300.)];
+                if selected {
+                    building_color = [1., 1., 0.];
                 }
+                buffers.draw_lined_box([corner_a, corner_c], thickness, *tier as u8, building_color, false)
+            },
+        }
+    }
 
-                let thickness = 0.01;
-
-                match grid.get(&(col_key + cam_pos[0])) {
-                    Some(col) => {
-                        match col.get(&(row_key + cam_pos[1])) {
-                            Some(Tile::Air) => {
-                                buffers.draw_box([corner_a, corner_c], thickness, air_color)
-                            },
-                            Some(Tile::Ground) => {
-                                buffers.draw_crossed_box([corner_a, corner_c], thickness, 3, ground_color)
-                            },
-                            Some(Tile::Building {health, tier, ..}) => {
-                                let mut building_color = [0.3 - (((health / tier) - 100.) / 100.), 0.3 + (((health / tier) - 100.) / 200.), 0.3 + (((health / tier) - 100.) / 300.)];
-                                if col_key == selected[0] - cam_pos[0] && row_key == selected[1] - cam_pos[1] {
-                                    building_color = [1., 1., 0.];
-                                }
-                                buffers.draw_lined_box([corner_a, corner_c], thickness, *tier as u8, building_color, false)
-                            },
-                            None => {},
-                        }
-                    },
-                    None => {},
-                }
-            }
+    // the grid itself is kept in State chunk by chunk, only the selected tile is drawn again every frame, on top of it
+    fn draw_selected(camera: &Camera, selected: Coord, grid: &Grid, buffers: &mut TwinBuffers) {
+        if let Some(tile) = grid.get(selected) {
+            draw_tile(camera, selected, tile, true, buffers);
         }
+        buffers.world_indices = buffers.indices.len();
     }
 
     
-    draw_grid(&visible_size, cam_pos, selected, &grid, &mut buffers);
+    let mut camera = Camera::new(window.inner_size());
+    jump_camera(&mut camera, cam_pos, visible_size);
+
+    draw_selected(&camera, selected, &grid, &mut buffers);
 
     // reset selected
     
@@ -563,111 +372,96 @@ pub async fn run() {
 
 
     let mut state = State::new(&window, &buffers).await;
+    // only chunks that changed since they were last drawn are tessellated again
+    let mut grid_meshes: ChunkMeshes<State> = ChunkMeshes::new();
 
     let mut pause = false;
     let mut placing_menu_open = false;
     let mut save_menu_open = false;
+    // set by the first press of V in the save menu, the second one deletes the saves
+    let mut confirm_delete = false;
+    // the game runs without saves too, the save menu shows why they're missing
+    let (mut save_slots, mut save_slots_error) = match SaveSlots::open("./saves") {
+        Ok(save_slots) => (save_slots, None),
+        Err(err) => (SaveSlots::empty("./saves"), Some(err)),
+    };
+    let mut save_page = 0;
+    let mut capture_keys = CaptureKeys::default();
+    capture_keys.bind_screenshot(VirtualKeyCode::F12);
+    capture_keys.bind_recording(VirtualKeyCode::F11, capture::DEFAULT_RECORD_EVERY);
 
     
-    fn gravity(grid: &mut HashMap<i32, HashMap<i32, Tile>>) {
-        let mut buildings: Vec<(i32, i32)> = Vec::new();
-        for col in grid.keys() {
-            for t in grid.get(col).unwrap() {
-                match t.1 {
-                    Tile::Building {..} => {
-                        buildings.push( ( *col, *t.0 ) )
-                    },
-                    _ => {},
-                }
-            }
-        }
+    fn gravity(grid: &mut Grid) {
+        let buildings: Vec<Coord> = grid.iter()
+            .filter(|(_, tile)| matches!(tile, Tile::Building {..}))
+            .map(|(coord, _)| coord)
+            .collect();
 
         for b in buildings {
-            if let Some(filling) = grid.get_mut(&b.0).unwrap().get_mut(&(b.1 - 1)) {
-                match filling {
-                    Tile::Air => {
-                        *grid.get_mut(&b.0).unwrap().get_mut(&(b.1 - 1)).unwrap() = *grid.get(&b.0).unwrap().get(&(b.1)).unwrap();
-                        
-                        *grid.get_mut(&b.0).unwrap().get_mut(&(b.1)).unwrap() = Tile::Air;
-
-                        // should probably deal with pressure instead of weight
-                        if let Tile::Building{ health, tier, .. } = grid.get_mut(&b.0).unwrap().get_mut(&(b.1 - 1)).unwrap() {
-                            *health -= *tier * 10. / (*tier * 0.3);
-                        }
-                    },
-                    _ => {},
+            let below = b.step(Direction::Down);
+            if let Some(Tile::Air) = grid.get(below) {
+                let mut building = *grid.get(b).unwrap();
+
+                // should probably deal with pressure instead of weight
+                if let Tile::Building{ health, tier, .. } = &mut building {
+                    *health -= *tier * 10. / (*tier * 0.3);
                 }
+
+                grid.insert(below, building);
+                grid.insert(b, Tile::Air);
             }
         }
     }
 
     // weight_distrib should be event driven instead of per frame
-    fn weight_distrib(grid: &mut HashMap<i32, HashMap<i32, Tile>>) {
-        let mut buildings: Vec<(i32, i32)> = Vec::new();
-        for col in grid.keys() {
-            for t in grid.get(col).unwrap() {
-                match t.1 {
-                    Tile::Building {..} => {
-                        buildings.push( ( *col, *t.0 ) )
-                    },
-                    _ => {},
-                }
-            }
-        }
+    fn weight_distrib(grid: &mut Grid) {
+        let buildings: Vec<Coord> = grid.iter()
+            .filter(|(_, tile)| matches!(tile, Tile::Building {..}))
+            .map(|(coord, _)| coord)
+            .collect();
 
         for b in buildings {
             let mut other_pressure: f32 = 0.;
-            if let Some(Tile::Building {pressure, ..}) = grid.get(&b.0).unwrap().get(&(b.1 + 1)) {
+            if let Some(Tile::Building {pressure, ..}) = grid.get(b.step(Direction::Up)) {
                 other_pressure = *pressure;
             }
 
-            if let Tile::Building {tier, pressure, ..} = grid.get_mut(&b.0).unwrap().get_mut(&(b.1)).unwrap() {
-                    *pressure = (*tier * 100.) + other_pressure;
-            }            
-        }
-    }
-
-    fn decay(grid: &mut HashMap<i32, HashMap<i32, Tile>>) {
-        let mut buildings: Vec<(i32, i32)> = Vec::new();
-        for col in grid.keys() {
-            for t in grid.get(col).unwrap() {
-                match t.1 {
-                    Tile::Building {..} => {
-                        buildings.push( ( *col, *t.0 ) )
-                    },
-                    _ => {},
+            // borrowing mutably bumps the chunk's version and has it drawn again, so only changed pressures are written
+            let Some(&Tile::Building {tier, pressure, ..}) = grid.get(b) else {
+                continue;
+            };
+            let new_pressure = (tier * 100.) + other_pressure;
+            if new_pressure != pressure {
+                if let Some(Tile::Building {pressure, ..}) = grid.get_mut(b) {
+                    *pressure = new_pressure;
                 }
             }
         }
+    }
+
+    // only borrows the buildings mutably, so chunks without any aren't drawn again
+    fn decay(grid: &mut Grid) {
+        let buildings: Vec<Coord> = grid.iter()
+            .filter(|(_, tile)| matches!(tile, Tile::Building {..}))
+            .map(|(coord, _)| coord)
+            .collect();
 
         for b in buildings {
-            if let Tile::Building{ health, pressure, .. } = grid.get_mut(&b.0).unwrap().get_mut(&(b.1)).unwrap() {
-                *health -= *pressure / 10000.;
+            if let Some(tile) = grid.get_mut(b) {
+                if let Tile::Building{ health, pressure, .. } = tile {
+                    *health -= *pressure / 10000.;
 
-                if *health <= 0. {
-                    *grid.get_mut(&b.0).unwrap().get_mut(&(b.1)).unwrap() = Tile::Air;
+                    if *health <= 0. {
+                        *tile = Tile::Air;
+                    }
                 }
             }
         }
     }
 
-    let mut money: i32 = save.money;
-
-    fn rent_collection(grid: &mut HashMap<i32, HashMap<i32, Tile>>, money: &mut i32) {
-        let mut buildings: Vec<(i32, i32)> = Vec::new();
-        for col in grid.keys() {
-            for t in grid.get(col).unwrap() {
-                match t.1 {
-                    Tile::Building {..} => {
-                        buildings.push( ( *col, *t.0 ) )
-                    },
-                    _ => {},
-                }
-            }
-        }
-
-        for b in buildings {
-            if let Tile::Building{ tier, .. } = grid.get_mut(&b.0).unwrap().get_mut(&(b.1)).unwrap() {
+    fn rent_collection(grid: &Grid, money: &mut i32) {
+        for (_, tile) in grid.iter() {
+            if let Tile::Building{ tier, .. } = tile {
                 *money += (*tier * 100. * 1.2 / 60.) as i32;
             }
         }
@@ -675,15 +469,10 @@ pub async fn run() {
     }
 
 
-    fn draw_menu(state: &mut State, buffers: &mut TwinBuffers, grid: &HashMap<i32, HashMap<i32, Tile>>, selected_pos: &[i32;2], money: &i32) {
-        let mut selected_filling: Option<&Tile> = None;
-        let selected_x: i32 = selected_pos[0];
-        let selected_y: i32 = selected_pos[1];
-        if let Some(col) = grid.get(&selected_pos[0]) {
-            if let Some(tile) = col.get(&selected_pos[1]) {
-                selected_filling = Some(tile);
-            }
-        }
+    fn draw_menu(state: &mut State, buffers: &mut TwinBuffers, grid: &Grid, selected_pos: &Coord, money: &i32) {
+        let selected_x: i32 = selected_pos.x();
+        let selected_y: i32 = selected_pos.y();
+        let selected_filling: Option<&Tile> = grid.get(*selected_pos);
 
         buffers.draw_rectangle([[0.25, 1.], [1., 0.7]], [0., 0., 0.]);
         buffers.draw_box([[0.25, 1.], [1., 0.7]], 0.01, [1., 1., 1.]);
@@ -713,44 +502,61 @@ pub async fn run() {
         state.draw_text([0.625, 0.475], &format!("5) Tier 5"), [1., 1., 1., 1.], 0.05);
     }
 
-    fn place_building(tier: i32, selected: [i32;2], grid: &mut HashMap<i32, HashMap<i32, Tile>>, money: &mut i32, pause: bool) {
-        let can_place = if let Some(col) = grid.get(&selected[0]) {
-            match col.get(&(selected[1] - 1)) {
-                Some(&Tile::Ground) => { true },
-                Some(&Tile::Building {..}) => { true },
-                _ => { false }
-            }
-        } else {
-            false
+    fn place_building(tier: i32, selected: Coord, grid: &mut Grid, money: &mut i32, pause: bool) {
+        let can_place = match grid.get(selected.step(Direction::Down)) {
+            Some(&Tile::Ground) => { true },
+            Some(&Tile::Building {..}) => { true },
+            _ => { false }
         };
         
 
 
-        if let Some(col) = grid.get_mut(&selected[0]) {
-            if let Some(tile) = col.get_mut(&selected[1]) {
-                if *tile == Tile::Air && can_place && *money >= tier * 10000 {
-                    *money -= tier * 10000;
-                    *tile = Tile::Building { health: tier as f32 * 100., tier: tier as f32, pressure: 0. };
-                }
+        if let Some(tile) = grid.get_mut(selected) {
+            if *tile == Tile::Air && can_place && *money >= tier * 10000 {
+                *money -= tier * 10000;
+                *tile = Tile::Building { health: tier as f32 * 100., tier: tier as f32, pressure: 0. };
             }
         }
     }
 
-    fn draw_save_menu(state: &mut State, buffers: &mut TwinBuffers) {
+    const SAVES_PER_PAGE: usize = 5;
+
+    fn draw_save_menu(state: &mut State, buffers: &mut TwinBuffers, save_slots: &SaveSlots, save_slots_error: Option<&SaveError>, save_page: usize, confirm_delete: bool) {
         buffers.draw_rectangle([[-0.925, 0.825], [0.175, -0.875]], [0., 0., 0.]);
         buffers.draw_box([[-0.925, 0.825], [0.175, -0.875]], 0.01, [1., 1., 1.]);
 
+        let mut y = 0.8;
+        for (i, slot) in save_slots.slots().iter().skip(save_page * SAVES_PER_PAGE).take(SAVES_PER_PAGE).enumerate() {
+            let thumbnail_corners = [[-0.9, y - 0.02], [-0.6, y - 0.28]];
+            buffers.draw_rectangle(thumbnail_corners, [0.01, 0.01, 0.01]);
+            if let Some(thumbnail) = &slot.thumbnail {
+                let tile_width = 0.3 / thumbnail.width.max(1) as f32;
+                let tile_height = 0.26 / thumbnail.height.max(1) as f32;
+                for rect in &thumbnail.rects {
+                    let left = thumbnail_corners[0][0] + rect.x as f32 * tile_width;
+                    let bottom = thumbnail_corners[1][1] + rect.y as f32 * tile_height;
+                    let color = rect.color.map(|channel| channel as f32 / 255.);
+                    buffers.draw_rectangle([[left, bottom + rect.height as f32 * tile_height], [left + rect.width as f32 * tile_width, bottom]], color);
+                }
+            }
+            buffers.draw_box(thumbnail_corners, 0.005, [1., 1., 1.]);
 
-        let saves = fs::read_dir("./saves").unwrap();
+            let date: String = slot.date.chars().take(16).collect();
+            state.draw_text([-0.55, y], &format!("{}) {}", i + 1, slot.name), [1., 1., 1., 1.], 0.08);
+            state.draw_text([-0.55, y - 0.1], &format!("{}  ${}", date.replace('T', " "), slot.money), [0.7, 0.7, 0.7, 1.], 0.05);
+            state.draw_text([-0.55, y - 0.17], &format!("{} buildings, v{}", slot.buildings, slot.version), [0.7, 0.7, 0.7, 1.], 0.05);
+            y -= 0.32;
+        }
 
-        let mut i = 0;
-        let mut y = 0.8;
-        for save in saves {
-            i += 1;
-            state.draw_text([-0.9, y], &format!("{}) {}", i, &(save.unwrap().path().to_str().unwrap()[8..])), [1., 1., 1., 1.], 0.1);
-            y -= 0.15;
+        if let Some(err) = save_slots_error {
+            state.draw_text([-0.9, y], &format!("saves couldn't be listed: {}", err), [1., 0.3, 0.3, 1.], 0.04);
+        }
+        if confirm_delete {
+            state.draw_text([-0.9, -0.7], "press V again to move every save but base and the autosaves to the trash", [1., 0.3, 0.3, 1.], 0.04);
         }
 
+        let pages = save_slots.slots().len().div_ceil(SAVES_PER_PAGE).max(1);
+        state.draw_text([-0.9, -0.78], &format!("page {}/{}, page up and page down to turn", save_page + 1, pages), [0.7, 0.7, 0.7, 1.], 0.05);
     }
 
 
@@ -767,7 +573,15 @@ pub async fn run() {
                         ..
                     },
                 ..
-            } => *control_flow = ControlFlow::Exit,
+            } => {
+                // the escape key sends a release after the press, the game is only saved once
+                if *control_flow != ControlFlow::Exit {
+                    if let Err(err) = autosave.write(&pack_save(visible_size, &grid, cam_pos, selected, money, &autosave)) {
+                        eprintln!("{}", err);
+                    }
+                }
+                *control_flow = ControlFlow::Exit;
+            },
 
             WindowEvent::KeyboardInput {
                 input:
@@ -778,7 +592,8 @@ pub async fn run() {
                     },
                 ..
             } => {
-                visible_size = [visible_size[0] + 2, visible_size[1] + 2];
+                visible_size = visible_size.map(|size| size * ZOOM_STEP);
+                camera.zoom_to(visible_size, DEFAULT_ANIMATION);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -790,7 +605,8 @@ pub async fn run() {
                     },
                 ..
             } => {
-                visible_size = [visible_size[0] - 2, visible_size[1] - 2];
+                visible_size = visible_size.map(|size| (size / ZOOM_STEP).max(1.));
+                camera.zoom_to(visible_size, DEFAULT_ANIMATION);
                 window.request_redraw();
             },
 
@@ -803,7 +619,8 @@ pub async fn run() {
                     },
                 ..
             } => {
-                cam_pos[1] += 1;
+                cam_pos = cam_pos.step(Direction::Up);
+                camera.pan_to(camera.grid_to_world(cam_pos), DEFAULT_ANIMATION);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -815,7 +632,8 @@ pub async fn run() {
                     },
                 ..
             } => {
-                cam_pos[1] -= 1;
+                cam_pos = cam_pos.step(Direction::Down);
+                camera.pan_to(camera.grid_to_world(cam_pos), DEFAULT_ANIMATION);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -827,7 +645,8 @@ pub async fn run() {
                     },
                 ..
             } => {
-                cam_pos[0] += 1;
+                cam_pos = cam_pos.step(Direction::Right);
+                camera.pan_to(camera.grid_to_world(cam_pos), DEFAULT_ANIMATION);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -839,7 +658,8 @@ pub async fn run() {
                     },
                 ..
             } => {
-                cam_pos[0] -= 1;
+                cam_pos = cam_pos.step(Direction::Left);
+                camera.pan_to(camera.grid_to_world(cam_pos), DEFAULT_ANIMATION);
                 window.request_redraw();
             },
 
@@ -852,7 +672,7 @@ pub async fn run() {
                     },
                 ..
             } => {
-                selected[1] += 1;
+                selected = selected.step(Direction::Up);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -864,7 +684,7 @@ pub async fn run() {
                     },
                 ..
             } => {
-                selected[1] -= 1;
+                selected = selected.step(Direction::Down);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -876,7 +696,7 @@ pub async fn run() {
                     },
                 ..
             } => {
-                selected[0] += 1;
+                selected = selected.step(Direction::Right);
                 window.request_redraw();
             },
             WindowEvent::KeyboardInput {
@@ -888,7 +708,20 @@ pub async fn run() {
                     },
                 ..
             } => {
-                selected[0] -= 1;
+                selected = selected.step(Direction::Left);
+                window.request_redraw();
+            },
+
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if capture_keys.is_bound(key) => {
+                capture_keys.key_pressed(key);
                 window.request_redraw();
             },
 
@@ -917,29 +750,16 @@ pub async fn run() {
                     },
                 ..
             } => {
-                let mut buildings: Vec<(i32, i32)> = Vec::new();
-                for col in grid.keys() {
-                    for t in grid.get(col).unwrap() {
-                        match t.1 {
-                            Tile::Building {..} => {
-                                buildings.push( ( *col, *t.0 ) )
+                if !pause {
+                    for (_, filling) in grid.iter_mut() {
+                        match filling {
+                            Tile::Building {health, ..} => {
+                                *health *= 0.1
                             },
                             _ => {},
                         }
                     }
                 }
-                if !pause {
-                    for b in buildings {
-                        if let Some(filling) = grid.get_mut(&b.0).unwrap().get_mut(&(b.1)) {
-                            match filling {
-                                Tile::Building {health, ..} => {
-                                    *health *= 0.1
-                                },
-                                _ => {},
-                            }
-                        }
-                    }
-                }
                 
                 
                 window.request_redraw();
@@ -956,28 +776,14 @@ pub async fn run() {
                     },
                 ..
             } => {
-                let mut i = 0;
-                for _save in fs::read_dir("./saves").unwrap() {
-                    i += 1;
+                let name = save_slots.unused_name("save");
+                if let Err(err) = save_slots.save(&name, &pack_save(visible_size, &grid, cam_pos, selected, money, &autosave)) {
+                    eprintln!("{}", err);
                 }
-
-                let filepath = &format!("saves/test{}.save", i + 1);            
-                let date = &Local::now().to_rfc3339_opts(SecondsFormat::Millis, false);
-                let ver = env!("CARGO_PKG_VERSION");
-
-                Save::write(
-                    filepath,
-                    date,
-                    ver,
-                    cam_pos,
-                    selected,
-                    &money,
-                    Settings {tile_ratio: visible_size},
-                    &grid,
-                ).unwrap();
             },
 
-            // delete saves
+            // move saves to the trash from the save menu once V is pressed a second time to confirm,
+            // they can be brought back with engine::save::storage::restore
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
@@ -986,204 +792,133 @@ pub async fn run() {
                         ..
                     },
                 ..
-            } => {
-                for save in fs::read_dir("./saves").unwrap() {
-                    let path = save.unwrap().path();
-                    if path != path::PathBuf::from("./saves/base.save") {
-                        fs::remove_file(path).unwrap();
+            } if save_menu_open => {
+                if confirm_delete {
+                    let names: Vec<String> = save_slots.slots().iter()
+                        .map(|slot| slot.name.clone())
+                        .filter(|name| name != "base" && !autosave::is_autosave(name))
+                        .collect();
+                    for name in names {
+                        if let Err(err) = save_slots.delete(&name) {
+                            eprintln!("{}", err);
+                        }
                     }
+                    save_page = 0;
                 }
+                confirm_delete = !confirm_delete;
+                window.request_redraw();
             },
         
+            // the number keys load the save they're next to in the save menu, or place the building tier they're next to
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::Key1),
+                        virtual_keycode: Some(key @ (VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3 | VirtualKeyCode::Key4 | VirtualKeyCode::Key5)),
                         state: ElementState::Pressed,
                         ..
                     },
                 ..
             } => {
+                let n = match key {
+                    VirtualKeyCode::Key1 => 1,
+                    VirtualKeyCode::Key2 => 2,
+                    VirtualKeyCode::Key3 => 3,
+                    VirtualKeyCode::Key4 => 4,
+                    _ => 5,
+                };
                 if save_menu_open {
-                    let mut save_paths = Vec::new();
-                    for save in fs::read_dir("./saves").unwrap() {
-                        let path = save.unwrap().path();
-                        save_paths.push(path);
+                    if let Some(save) = load_slot(&save_slots, save_page * SAVES_PER_PAGE + n - 1, &mut autosave) {
+                        (visible_size, grid, cam_pos, selected, money) = save;
+                        jump_camera(&mut camera, cam_pos, visible_size);
+                        grid_meshes.clear(&mut state);
                     }
-                    let save = Save::read(&save_paths[0].to_str().unwrap()[2..]).unwrap();
-
-                    visible_size = save.settings.tile_ratio;
-                    grid = save.grid;
-                    cam_pos = save.cam_pos;
-                    selected = save.selected;
-                    money = save.money;
-                    
                 } else if placing_menu_open {
-                    place_building(1, selected, &mut grid, &mut money, pause);
+                    place_building(n as i32, selected, &mut grid, &mut money, pause);
                 }
 
                 window.request_redraw();
             },
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::Key2),
-                        state: ElementState::Pressed,
-                        ..
-                    },
-                ..
-            }  => {
-                if save_menu_open {
-                    let mut save_paths = Vec::new();
-                    for save in fs::read_dir("./saves").unwrap() {
-                        let path = save.unwrap().path();
-                        save_paths.push(path);
-                    }
-                    let save = Save::read(&save_paths[1].to_str().unwrap()[2..]).unwrap();
-
-                    visible_size = save.settings.tile_ratio;
-                    grid = save.grid;
-                    cam_pos = save.cam_pos;
-                    selected = save.selected;
-                    money = save.money;
-                    
-                } else if placing_menu_open {
-                    place_building(2, selected, &mut grid, &mut money, pause);
-                }
 
-                window.request_redraw();
-            },
+            // open place menu
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::Key3),
+                        virtual_keycode: Some(VirtualKeyCode::Return),
                         state: ElementState::Pressed,
                         ..
                     },
                 ..
             } => {
-                if save_menu_open {
-                    let mut save_paths = Vec::new();
-                    for save in fs::read_dir("./saves").unwrap() {
-                        let path = save.unwrap().path();
-                        save_paths.push(path);
-                    }
-                    let save = Save::read(&save_paths[2].to_str().unwrap()[2..]).unwrap();
-
-                    visible_size = save.settings.tile_ratio;
-                    grid = save.grid;
-                    cam_pos = save.cam_pos;
-                    selected = save.selected;
-                    money = save.money;
-                    
-                } else if placing_menu_open {
-                    place_building(3, selected, &mut grid, &mut money, pause);
+                if placing_menu_open {
+                    placing_menu_open = false;
+                } else {
+                    placing_menu_open = true;
                 }
-
                 window.request_redraw();
             },
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::Key4),
-                        state: ElementState::Pressed,
-                        ..
-                    },
-                ..
-            } => {
-                if save_menu_open {
-                    let mut save_paths = Vec::new();
-                    for save in fs::read_dir("./saves").unwrap() {
-                        let path = save.unwrap().path();
-                        save_paths.push(path);
-                    }
-                    let save = Save::read(&save_paths[3].to_str().unwrap()[2..]).unwrap();
-
-                    visible_size = save.settings.tile_ratio;
-                    grid = save.grid;
-                    cam_pos = save.cam_pos;
-                    selected = save.selected;
-                    money = save.money;
-                    
-                } else if placing_menu_open {
-                    place_building(4, selected, &mut grid, &mut money, pause);
-                }
 
-                window.request_redraw();
-            },
+            // open save menu
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::Key5),
+                        virtual_keycode: Some(VirtualKeyCode::B),
                         state: ElementState::Pressed,
                         ..
                     },
                 ..
             } => {
+                confirm_delete = false;
                 if save_menu_open {
-                    let mut save_paths = Vec::new();
-                    for save in fs::read_dir("./saves").unwrap() {
-                        let path = save.unwrap().path();
-                        save_paths.push(path);
-                    }
-                    let save = Save::read(&save_paths[4].to_str().unwrap()[2..]).unwrap();
-
-                    visible_size = save.settings.tile_ratio;
-                    grid = save.grid;
-                    cam_pos = save.cam_pos;
-                    selected = save.selected;
-                    money = save.money;
-                    
-                } else if placing_menu_open {
-                    place_building(5, selected, &mut grid, &mut money, pause);
+                    save_menu_open = false;
+                    pause = false;
+                } else {
+                    // pick up saves that were added or removed outside of the game
+                    save_slots_error = save_slots.refresh().err();
+                    save_page = 0;
+                    save_menu_open = true;
+                    pause = true;
                 }
-
                 window.request_redraw();
             },
 
-            // open place menu
+            // turn the pages of the save menu
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::Return),
+                        virtual_keycode: Some(VirtualKeyCode::PageUp),
                         state: ElementState::Pressed,
                         ..
                     },
                 ..
             } => {
-                if placing_menu_open {
-                    placing_menu_open = false;
-                } else {
-                    placing_menu_open = true;
+                if save_menu_open {
+                    save_page = save_page.saturating_sub(1);
                 }
                 window.request_redraw();
             },
-
-            // open save menu
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
-                        virtual_keycode: Some(VirtualKeyCode::B),
+                        virtual_keycode: Some(VirtualKeyCode::PageDown),
                         state: ElementState::Pressed,
                         ..
                     },
                 ..
             } => {
-                if save_menu_open {
-                    save_menu_open = false;
-                    pause = false;
-                } else {
-                    save_menu_open = true;
-                    pause = true;
+                if save_menu_open && (save_page + 1) * SAVES_PER_PAGE < save_slots.slots().len() {
+                    save_page += 1;
                 }
                 window.request_redraw();
             },
 
             WindowEvent::Resized(physical_size) => {
                 state.resize(physical_size);
+                camera.set_viewport(physical_size);
             },
-            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                state.scale_factor = scale_factor;
                 state.resize(*new_inner_size);
+                camera.set_viewport(*new_inner_size);
             },
             _ => {},
         },
@@ -1193,28 +928,49 @@ pub async fn run() {
         Event::RedrawRequested(_) => {
             buffers.vertices = Vec::new();
             buffers.indices = Vec::new();
+            let frame_time = last_frame.elapsed();
+            last_frame = Instant::now();
             weight_distrib(&mut grid);
             if !pause {
                 gravity(&mut grid);
                 decay(&mut grid);
-                rent_collection(&mut grid, &mut money);
+                rent_collection(&grid, &mut money);
+
+                // only simulated time counts towards the next autosave, however long the frames take
+                if autosave.tick(SIMULATION_STEP) {
+                    if let Err(err) = autosave.write(&pack_save(visible_size, &grid, cam_pos, selected, money, &autosave)) {
+                        eprintln!("{}", err);
+                    }
+                }
             }
-            draw_grid(&visible_size, cam_pos, selected, &grid, &mut buffers);
+            // frames are only drawn on input, so the time since the last one can be far longer than a frame,
+            // it's capped so animations started by that input still play out
+            // redrawing continues until the camera settles
+            if camera.update(frame_time.min(Duration::from_millis(33))) {
+                window.request_redraw();
+            }
+            let (bottom_left, top_right) = camera.visible_tiles();
+            grid_meshes.draw(&mut state, &grid, bottom_left, top_right, |buffers, coord, tile| draw_tile(&camera, coord, tile, false, buffers));
+            draw_selected(&camera, selected, &grid, &mut buffers);
             if placing_menu_open {
                 draw_placing_menu(&mut state, &mut buffers);
             }
             if save_menu_open {
-                draw_save_menu(&mut state, &mut buffers);
+                draw_save_menu(&mut state, &mut buffers, &save_slots, save_slots_error.as_ref(), save_page, confirm_delete);
             }
             draw_menu(&mut state, &mut buffers, &grid, &selected, &money);
             state.update_buffers(&mut buffers);
+            state.update_camera(&camera);
 
-            match state.render(&buffers.indices) {
+            state.capture_requested = capture_keys.before_frame();
+            match state.render(&buffers) {
                 Ok(_) => {}
                 Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
                 Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                 Err(e) => eprintln!("{:?}", e),
             }
+
+            capture_keys.after_frame(state.captured.take());
         }
         _ => {}
     });
@@ -1222,20 +978,47 @@ pub async fn run() {
 
 // wgpu setup boilerplate
 
+// how high text is drawn at least, in logical pixels
+const MIN_TEXT_HEIGHT: f32 = 8.;
+
+// the buffers a chunk of the grid is drawn from, see the MeshStore impl of State
+struct GridMesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
 struct State {
     size: winit::dpi::PhysicalSize<u32>,
+    // how many physical pixels a logical one is, as winit reports it for the window
+    scale_factor: f64,
     surface: wgpu::Surface,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
 
     render_pipeline: wgpu::RenderPipeline,
+    // the grid is drawn through the camera, everything after it straight in clip space
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    clip_bind_group: wgpu::BindGroup,
 
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
 
+    // indexed by the ids ChunkMeshes keeps, None for removed meshes whose ids are free to be reused
+    grid_meshes: Vec<Option<GridMesh>>,
+    // the grid meshes drawn this frame
+    queued_grid_meshes: Vec<usize>,
+
     staging_belt: wgpu::util::StagingBelt,
     glyph_brush: wgpu_glyph::GlyphBrush<()>,
+    // text is queued for every pass drawing the frame, the glyph brush forgets it after one
+    sections: Vec<OwnedSection>,
+
+    // set to keep a copy of the next rendered frame in captured, for screenshots and recordings
+    capture_requested: bool,
+    captured: Option<Frame>,
 }
 
 impl State {
@@ -1336,6 +1119,32 @@ impl State {
             },
             multiview: None,
         });
+
+        let camera_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&Camera::new(size).view_proj()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+        let clip_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&Camera::new(size).view_proj()),
+                usage: wgpu::BufferUsages::UNIFORM,
+            }
+        );
+        let camera_bind_group_layout = render_pipeline.get_bind_group_layout(0);
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: camera_buffer.as_entire_binding() }],
+        });
+        let clip_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: clip_buffer.as_entire_binding() }],
+        });
         
         let vertex_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
@@ -1365,18 +1174,29 @@ impl State {
 
         State {
             size,
+            scale_factor: window.scale_factor(),
             surface,
             device,
             queue,
             config,
 
             render_pipeline,
+            camera_buffer,
+            camera_bind_group,
+            clip_bind_group,
 
             vertex_buffer,
             index_buffer,
 
+            grid_meshes: Vec::new(),
+            queued_grid_meshes: Vec::new(),
+
             staging_belt,
             glyph_brush,
+            sections: Vec::new(),
+
+            capture_requested: false,
+            captured: None,
         }
     }
 
@@ -1407,17 +1227,35 @@ impl State {
         );
     }
 
-    fn render(&mut self, indices: &Vec<u16>) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    fn upload_grid_mesh(&self, buffers: &TwinBuffers) -> GridMesh {
+        GridMesh {
+            vertex_buffer: self.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&buffers.vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }
+            ),
+            index_buffer: self.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&buffers.indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                }
+            ),
+            index_count: buffers.indices.len() as u32,
+        }
+    }
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None, });
+    fn update_camera(&mut self, camera: &Camera) {
+        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&camera.view_proj()));
+    }
 
+    fn draw_frame(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, buffers: &TwinBuffers) {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -1433,25 +1271,66 @@ impl State {
         });
         
         render_pass.set_pipeline(&self.render_pipeline);
+
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        for chunk in self.queued_grid_meshes.iter().filter_map(|&id| self.grid_meshes[id].as_ref()) {
+            render_pass.set_vertex_buffer(0, chunk.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(chunk.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..chunk.index_count, 0, 0..1);
+        }
+
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        let world_indices = buffers.world_indices.min(buffers.indices.len()) as u32;
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.draw_indexed(0..world_indices, 0, 0..1);
+        render_pass.set_bind_group(0, &self.clip_bind_group, &[]);
+        render_pass.draw_indexed(world_indices..buffers.indices.len() as u32, 0, 0..1);
 
         drop(render_pass);
 
+        for section in &self.sections {
+            self.glyph_brush.queue(section);
+        }
         self.glyph_brush.draw_queued(
             &self.device,
             &mut self.staging_belt,
-            &mut encoder,
-            &view,
+            encoder,
+            view,
             self.size.width,
             self.size.height,
         ).unwrap();
+    }
+
+    fn render(&mut self, buffers: &TwinBuffers) -> Result<(), wgpu::SurfaceError> {
+        let output = self.surface.get_current_texture()?;
+
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None, });
+
+        self.draw_frame(&mut encoder, &view, buffers);
+
+        // the surface usually can't be copied from, so a captured frame is drawn a second time into a texture that can be
+        let capture_texture = if self.capture_requested {
+            let texture = draw::create_capture_texture(&self.device, self.size, self.config.format);
+            self.draw_frame(&mut encoder, &texture.create_view(&wgpu::TextureViewDescriptor::default()), buffers);
+            Some(texture)
+        } else {
+            None
+        };
 
         self.staging_belt.finish();
     
         self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(texture) = capture_texture {
+            self.captured = draw::read_texture(&self.device, &self.queue, &texture).map_err(|err| eprintln!("{}", err)).ok();
+        }
+        self.capture_requested = false;
         output.present();
+        self.staging_belt.recall();
+        self.sections.clear();
+        self.queued_grid_meshes.clear();
     
         Ok(())
     }
@@ -1460,12 +1339,49 @@ impl State {
         let width = self.size.width as f32;
         let height = self.size.height as f32;
 
-        self.glyph_brush.queue(Section {
-            screen_position: ((width / 2.) + (position[0] / 2.) * width + 1., (height / 2.) + ((position[1] * -1.) / 2.) * height),
+        // shrunk with the window, but never past what stays readable on the screen's scale factor
+        let px_scale = text::px_scale(scale, self.size).y.max(MIN_TEXT_HEIGHT * self.scale_factor as f32);
+
+        self.sections.push(Section {
+            screen_position: ((width / 2.) + (position[0] / 2.) * width + 1., (height / 2.) + (-position[1] / 2.) * height),
             text: vec![Text::new(text)
                 .with_color(color)
-                .with_scale(ab_glyph::PxScale {x: (scale / 2.) * width, y: (scale / 2.) * height})],
+                .with_scale(px_scale)],
             ..Section::default()
-        });
+        }.to_owned());
+    }
+}
+impl MeshStore for State {
+    type Mesh = TwinBuffers;
+    type Id = usize;
+
+    fn create_mesh(&mut self, buffers: &TwinBuffers) -> usize {
+        let mesh = Some(self.upload_grid_mesh(buffers));
+        match self.grid_meshes.iter().position(|slot| slot.is_none()) {
+            Some(free) => {
+                self.grid_meshes[free] = mesh;
+                return free;
+            },
+            None => {
+                self.grid_meshes.push(mesh);
+                return self.grid_meshes.len() - 1;
+            },
+        }
+    }
+
+    fn update_mesh(&mut self, id: usize, buffers: &TwinBuffers) {
+        if self.grid_meshes.get(id).is_some_and(|slot| slot.is_some()) {
+            self.grid_meshes[id] = Some(self.upload_grid_mesh(buffers));
+        }
+    }
+
+    fn remove_mesh(&mut self, id: usize) {
+        if let Some(slot) = self.grid_meshes.get_mut(id) {
+            *slot = None;
+        }
+    }
+
+    fn draw_mesh(&mut self, id: usize) {
+        self.queued_grid_meshes.push(id);
     }
 }
\ No newline at end of file
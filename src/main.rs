@@ -1,9 +1,21 @@
 //use poliosis_test::run;
+use std::env;
+use std::process::ExitCode;
 
+use poliosis_test::engine::save::Save;
 
-mod engine;
-mod game;
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let [command, from, to] = args.as_slice() {
+        if command == "convert" {
+            if let Err(err) = Save::convert(from, to) {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+            return ExitCode::SUCCESS;
+        }
+    }
 
-fn main() {
     //pollster::block_on(run());
-}
\ No newline at end of file
+    return ExitCode::SUCCESS;
+}